@@ -8,11 +8,11 @@ if [ -z "$DATABASE_URL" ]; then
     exit 1
 fi
 
+# Migrations are embedded in the server binary and tracked in _sqlx_migrations.
+# Only the [database] settings are needed; see CURL_COMMANDS.md for databases
+# created by the old psql version of this script.
 echo "Running migrations..."
 
-psql "$DATABASE_URL" -f migrations/000_users_table.sql
-psql "$DATABASE_URL" -f migrations/001_initial_schema.sql
-psql "$DATABASE_URL" -f migrations/002_fix_timestamp_types.sql
+cargo run --quiet -- migrate up
 
 echo "Migrations completed successfully!"
-
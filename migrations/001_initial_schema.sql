@@ -33,14 +33,9 @@ CREATE TABLE proposals (
     created_by BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
     created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
     executed_at TIMESTAMP WITH TIME ZONE,
-    transaction_data TEXT,
-
-    -- Ensure creator is a multisig owner
-    CONSTRAINT creator_is_multisig_owner CHECK (
-        created_by IN (
-            SELECT unnest(owners) FROM multisigs WHERE id = multisig_id
-        )
-    )
+    transaction_data TEXT
+    -- Ownership of creators and approvers is enforced by the application; Postgres does not
+    -- allow subqueries in CHECK constraints.
 );
 
 -- Create indexes for proposals
@@ -55,15 +50,6 @@ CREATE TABLE proposal_approvals (
     user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
     approved_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
 
-    -- Ensure approver is a multisig owner
-    CONSTRAINT approver_is_multisig_owner CHECK (
-        user_id IN (
-            SELECT unnest(owners)
-            FROM multisigs
-            WHERE id = (SELECT multisig_id FROM proposals WHERE id = proposal_id)
-        )
-    ),
-
     -- One approval per user per proposal
     UNIQUE(proposal_id, user_id)
 );
@@ -0,0 +1,12 @@
+-- Composite indexes backing keyset pagination on list endpoints
+
+-- Multisig listing orders by (created_at, id); owner filtering uses the existing GIN index
+CREATE INDEX IF NOT EXISTS idx_multisigs_created_at_id ON multisigs (created_at DESC, id DESC);
+
+-- Proposal listing within a multisig, optionally narrowed by status or creator
+CREATE INDEX IF NOT EXISTS idx_proposals_multisig_created_at_id
+    ON proposals (multisig_id, created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_proposals_multisig_status_created_at_id
+    ON proposals (multisig_id, status, created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_proposals_multisig_created_by_created_at_id
+    ON proposals (multisig_id, created_by, created_at DESC, id DESC);
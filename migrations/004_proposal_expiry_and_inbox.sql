@@ -0,0 +1,8 @@
+-- Proposal expiry and indexes for the per-user inbox
+
+ALTER TABLE proposals ADD COLUMN IF NOT EXISTS expires_at TIMESTAMP WITH TIME ZONE;
+
+-- Inbox only ever looks at proposals that still need a vote or an execution
+CREATE INDEX IF NOT EXISTS idx_proposals_pending_multisig
+    ON proposals (multisig_id, expires_at)
+    WHERE status IN ('active', 'approved');
@@ -0,0 +1,20 @@
+-- Proposal history: one row per state change, written in the same transaction as the change
+
+CREATE TYPE proposal_event_type AS ENUM (
+    'created', 'activated', 'approved', 'revoked', 'rejected', 'executed', 'expired'
+);
+
+CREATE TABLE proposal_events (
+    id BIGSERIAL PRIMARY KEY,
+    proposal_id BIGINT NOT NULL REFERENCES proposals(id) ON DELETE CASCADE,
+    event_type proposal_event_type NOT NULL,
+    -- NULL for system-initiated events such as expiry
+    actor_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    old_status proposal_status,
+    new_status proposal_status,
+    metadata JSONB NOT NULL DEFAULT '{}'::jsonb,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_proposal_events_proposal_id ON proposal_events (proposal_id, created_at, id);
+CREATE INDEX idx_proposal_events_actor_id ON proposal_events (actor_id);
@@ -0,0 +1,26 @@
+-- Email notifications for proposal activity with per-user preferences and digests
+
+CREATE TYPE digest_frequency AS ENUM ('immediate', 'hourly', 'daily');
+
+-- Users without a row get the column defaults
+CREATE TABLE notification_preferences (
+    user_id BIGINT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    email_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    notify_vote_needed BOOLEAN NOT NULL DEFAULT TRUE,
+    notify_threshold_reached BOOLEAN NOT NULL DEFAULT TRUE,
+    digest_frequency digest_frequency NOT NULL DEFAULT 'immediate',
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TYPE notification_kind AS ENUM ('vote_needed', 'threshold_reached');
+
+CREATE TABLE notifications (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    proposal_id BIGINT NOT NULL REFERENCES proposals(id) ON DELETE CASCADE,
+    kind notification_kind NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_notifications_pending ON notifications (user_id, created_at) WHERE sent_at IS NULL;
@@ -0,0 +1,30 @@
+-- Rotating refresh tokens and access token revocation
+
+CREATE TABLE refresh_tokens (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Every token obtained by rotating from the same login shares a family
+    family_id VARCHAR(64) NOT NULL,
+    parent_id BIGINT REFERENCES refresh_tokens(id) ON DELETE SET NULL,
+    -- SHA-256 of the token; the raw value is only ever returned to the client
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    -- jti of the access token issued alongside this refresh token
+    access_jti VARCHAR(64) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    revoked_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_refresh_tokens_family_id ON refresh_tokens (family_id);
+CREATE INDEX idx_refresh_tokens_access_jti ON refresh_tokens (access_jti);
+
+CREATE TABLE revoked_tokens (
+    jti VARCHAR(64) PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    revoked_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    -- Rows can be purged once the access token would have expired anyway
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX idx_revoked_tokens_expires_at ON revoked_tokens (expires_at);
@@ -0,0 +1,30 @@
+-- WebAuthn / passkey credentials and ceremony challenges
+
+CREATE TABLE webauthn_credentials (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- base64url credential id as sent by the authenticator
+    credential_id TEXT NOT NULL UNIQUE,
+    -- COSE_Key bytes from the attested credential data
+    public_key BYTEA NOT NULL,
+    algorithm INTEGER NOT NULL,
+    sign_count BIGINT NOT NULL DEFAULT 0,
+    name VARCHAR(100) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_webauthn_credentials_user_id ON webauthn_credentials (user_id);
+
+CREATE TYPE webauthn_ceremony AS ENUM ('registration', 'authentication', 'step_up');
+
+CREATE TABLE webauthn_challenges (
+    id BIGSERIAL PRIMARY KEY,
+    -- NULL for discoverable-credential logins where the user is not known yet
+    user_id BIGINT REFERENCES users(id) ON DELETE CASCADE,
+    ceremony webauthn_ceremony NOT NULL,
+    challenge TEXT NOT NULL UNIQUE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    consumed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,16 @@
+-- Login attempts backing per-account and per-IP rate limiting
+
+CREATE TABLE login_attempts (
+    id BIGSERIAL PRIMARY KEY,
+    -- Lowercased email as submitted, so unknown accounts are throttled the same way
+    email VARCHAR(255) NOT NULL,
+    user_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    ip_address VARCHAR(64),
+    succeeded BOOLEAN NOT NULL,
+    attempted_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    -- Set by a successful login or an admin unlock; cleared failures no longer count
+    cleared_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_login_attempts_email ON login_attempts (email, attempted_at);
+CREATE INDEX idx_login_attempts_ip ON login_attempts (ip_address, attempted_at);
@@ -0,0 +1,20 @@
+-- API keys for service accounts and bots. Only a SHA-256 hash of the full key is stored; the
+-- public prefix identifies the key without revealing the secret.
+
+CREATE TABLE api_keys (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    prefix VARCHAR(32) NOT NULL UNIQUE,
+    key_hash VARCHAR(64) NOT NULL,
+    -- e.g. {'proposals:read','proposals:create'}
+    scopes TEXT[] NOT NULL,
+    -- NULL means every multisig the owning user belongs to
+    multisig_id BIGINT REFERENCES multisigs(id) ON DELETE CASCADE,
+    expires_at TIMESTAMP WITH TIME ZONE,
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_api_keys_user_id ON api_keys (user_id);
@@ -0,0 +1,28 @@
+-- Generic background job queue. Workers claim due rows with FOR UPDATE SKIP LOCKED and lease them
+-- through locked_until; a job that keeps failing ends up 'dead' for an admin to inspect or retry.
+
+CREATE TYPE job_status AS ENUM ('pending', 'running', 'succeeded', 'dead', 'cancelled');
+
+CREATE TABLE jobs (
+    id BIGSERIAL PRIMARY KEY,
+    kind VARCHAR(100) NOT NULL,
+    payload JSONB NOT NULL,
+    status job_status NOT NULL DEFAULT 'pending',
+    -- At most one pending or running job per (kind, unique_key)
+    unique_key VARCHAR(255),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL CHECK (max_attempts > 0),
+    run_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMP WITH TIME ZONE,
+    last_error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE UNIQUE INDEX idx_jobs_unique_key ON jobs (kind, unique_key)
+    WHERE unique_key IS NOT NULL AND status IN ('pending', 'running');
+
+CREATE INDEX idx_jobs_due ON jobs (run_at) WHERE status = 'pending';
+CREATE INDEX idx_jobs_leased ON jobs (locked_until) WHERE status = 'running';
+CREATE INDEX idx_jobs_status ON jobs (status, created_at DESC, id DESC);
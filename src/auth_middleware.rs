@@ -1,47 +1,208 @@
-use actix_web::{Error, FromRequest, HttpRequest, dev::Payload};
-use futures_util::future::{Ready, ready};
-use jsonwebtoken::{DecodingKey, Validation, decode};
+use actix_web::{Error, FromRequest, HttpRequest, dev::Payload, web};
+use chrono::{DateTime, Utc};
+use futures_util::future::LocalBoxFuture;
 
+use crate::db::{DbPool, is_token_revoked, is_user_admin, touch_session};
+use crate::errors::AppError;
+use crate::jwt::JwtKeySet;
+use crate::models::{Actor, ApiKeyGrant};
+use crate::services::ApiKeyService;
+use crate::telemetry::record_user;
+
+/// A signed-in user or an API key acting for its owner. Handlers that take this must pass
+/// `actor()` down to a service that checks the key's scopes.
 #[derive(Debug)]
 pub struct AuthUser {
     pub user_id: i64,
+    pub session_id: Option<i64>,
+    pub api_key: Option<ApiKeyGrant>,
+}
+
+impl AuthUser {
+    pub fn actor(&self) -> Actor {
+        Actor {
+            user_id: self.user_id,
+            session_id: self.session_id,
+            api_key: self.api_key.clone(),
+        }
+    }
 }
 
 impl FromRequest for AuthUser {
     type Error = Error;
-    type Future = Ready<Result<Self, Error>>;
+    type Future = LocalBoxFuture<'static, Result<Self, Error>>;
 
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        let header = req.headers().get("Authorization");
+        let credential = credential(req);
+        let pool = req.app_data::<web::Data<DbPool>>().cloned();
+        let keys = req.app_data::<web::Data<JwtKeySet>>().cloned();
 
-        if header.is_none() {
-            return ready(Err(actix_web::error::ErrorUnauthorized("Missing token")));
-        }
+        Box::pin(async move {
+            let pool = pool.ok_or_else(|| {
+                actix_web::error::ErrorInternalServerError("Database pool not configured")
+            })?;
 
-        let auth_header = header.unwrap().to_str().unwrap_or("");
-        if !auth_header.starts_with("Bearer ") {
-            return ready(Err(actix_web::error::ErrorUnauthorized(
-                "Invalid token format",
-            )));
-        }
+            match credential? {
+                Credential::Bearer(token) => {
+                    let session = authenticate_session(&pool, keys, &token).await?;
+                    Ok(AuthUser {
+                        user_id: session.user_id,
+                        session_id: Some(session.session_id),
+                        api_key: None,
+                    })
+                }
+                Credential::ApiKey(key) => {
+                    let api_key = ApiKeyService::authenticate(&pool, &key)
+                        .await
+                        .map_err(|e| match e {
+                            AppError::Authentication(msg) => {
+                                actix_web::error::ErrorUnauthorized(msg)
+                            }
+                            other => actix_web::error::ErrorInternalServerError(other),
+                        })?;
+                    record_user(api_key.user_id);
+                    Ok(AuthUser {
+                        user_id: api_key.user_id,
+                        session_id: None,
+                        api_key: Some(api_key.grant()),
+                    })
+                }
+            }
+        })
+    }
+}
 
-        let token = auth_header.trim_start_matches("Bearer ").trim();
-        let secret = std::env::var("JWT_SECRET").unwrap();
-        let validation = Validation::default();
-
-        let decoded = decode::<crate::routes::auth::Claims>(
-            token,
-            &DecodingKey::from_secret(secret.as_bytes()),
-            &validation,
-        );
-
-        match decoded {
-            Ok(data) => ready(Ok(AuthUser {
-                user_id: data.claims.sub,
-            })),
-            Err(_) => ready(Err(actix_web::error::ErrorUnauthorized(
-                "Invalid or expired token",
-            ))),
-        }
+/// A user signed in with a session token. Account management, key management and other
+/// endpoints that bots have no business calling use this, so API keys are refused.
+#[derive(Debug)]
+pub struct SessionUser {
+    pub user_id: i64,
+    pub jti: String,
+    pub session_id: i64,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl FromRequest for SessionUser {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let credential = credential(req);
+        let pool = req.app_data::<web::Data<DbPool>>().cloned();
+        let keys = req.app_data::<web::Data<JwtKeySet>>().cloned();
+
+        Box::pin(async move {
+            let token = match credential? {
+                Credential::Bearer(token) => token,
+                Credential::ApiKey(_) => {
+                    return Err(actix_web::error::ErrorForbidden(
+                        "API keys cannot be used for this endpoint",
+                    ));
+                }
+            };
+
+            let pool = pool.ok_or_else(|| {
+                actix_web::error::ErrorInternalServerError("Database pool not configured")
+            })?;
+
+            authenticate_session(&pool, keys, &token).await
+        })
+    }
+}
+
+async fn authenticate_session(
+    pool: &DbPool,
+    keys: Option<web::Data<JwtKeySet>>,
+    token: &str,
+) -> Result<SessionUser, Error> {
+    let keys =
+        keys.ok_or_else(|| actix_web::error::ErrorInternalServerError("JWT keys not configured"))?;
+    let claims = keys
+        .decode(token)
+        .map_err(|_| actix_web::error::ErrorUnauthorized("Invalid or expired token"))?;
+
+    let revoked = is_token_revoked(pool, &claims.jti)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    if revoked {
+        return Err(actix_web::error::ErrorUnauthorized(
+            "Token has been revoked",
+        ));
+    }
+
+    let session_active = touch_session(pool, claims.sid)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    if !session_active {
+        return Err(actix_web::error::ErrorUnauthorized(
+            "Session has been revoked",
+        ));
+    }
+
+    record_user(claims.sub);
+    Ok(SessionUser {
+        user_id: claims.sub,
+        jti: claims.jti,
+        session_id: claims.sid,
+        expires_at: DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(Utc::now),
+    })
+}
+
+enum Credential {
+    Bearer(String),
+    ApiKey(String),
+}
+
+fn credential(req: &HttpRequest) -> Result<Credential, Error> {
+    let header = req
+        .headers()
+        .get("Authorization")
+        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Missing token"))?;
+
+    let auth_header = header.to_str().unwrap_or("");
+    if let Some(token) = auth_header.strip_prefix("Bearer ") {
+        return Ok(Credential::Bearer(token.trim().to_string()));
+    }
+
+    if let Some(key) = auth_header.strip_prefix("ApiKey ") {
+        return Ok(Credential::ApiKey(key.trim().to_string()));
+    }
+
+    Err(actix_web::error::ErrorUnauthorized("Invalid token format"))
+}
+
+#[derive(Debug)]
+pub struct AdminUser {
+    pub user_id: i64,
+}
+
+impl FromRequest for AdminUser {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let session_user = SessionUser::from_request(req, payload);
+        let pool = req.app_data::<web::Data<DbPool>>().cloned();
+
+        Box::pin(async move {
+            let session_user = session_user.await?;
+            let pool = pool.ok_or_else(|| {
+                actix_web::error::ErrorInternalServerError("Database pool not configured")
+            })?;
+
+            let is_admin = is_user_admin(&pool, session_user.user_id)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+
+            if !is_admin {
+                return Err(actix_web::error::ErrorForbidden("Admin access required"));
+            }
+
+            Ok(AdminUser {
+                user_id: session_user.user_id,
+            })
+        })
     }
 }
@@ -0,0 +1,120 @@
+use crate::db::DbPool;
+use crate::migrations::{MIGRATOR, MigrationState, migration_status, run_migrations};
+use crate::models::AuditSigningKey;
+use crate::services::AuditService;
+
+const USAGE: &str = "Usage: solana-multisig-server [audit verify | audit checkpoint | \
+migrate up | migrate status | migrate verify]";
+
+pub fn is_migrate_command(args: &[String]) -> bool {
+    args.first().is_some_and(|command| command == "migrate")
+}
+
+// Runs a one-off command instead of the HTTP server. Returns the process exit code.
+pub async fn run(pool: &DbPool, audit_key: &AuditSigningKey, args: &[String]) -> i32 {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["audit", "verify"] => match AuditService::verify_chain(pool).await {
+            Ok(verification) => {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&verification).unwrap_or_default()
+                );
+                if verification.valid { 0 } else { 1 }
+            }
+            Err(e) => {
+                eprintln!("Audit verification failed: {}", e);
+                2
+            }
+        },
+        ["audit", "checkpoint"] => {
+            match AuditService::create_checkpoint(pool, audit_key, None).await {
+                Ok(checkpoint) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&checkpoint).unwrap_or_default()
+                    );
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Audit checkpoint failed: {}", e);
+                    2
+                }
+            }
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            2
+        }
+    }
+}
+
+// `migrate` subcommands only get the database settings, see `Config::load_database`.
+pub async fn run_migrate(pool: &DbPool, args: &[String]) -> i32 {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["migrate", "up"] => match run_migrations(pool).await {
+            Ok(()) => {
+                println!("Database is up to date");
+                0
+            }
+            Err(e) => {
+                eprintln!("Migration failed: {}", e);
+                2
+            }
+        },
+        ["migrate", "status"] => match migration_status(pool).await {
+            Ok(statuses) => {
+                for status in &statuses {
+                    println!(
+                        "{:>4}  {:<18} {}",
+                        status.version,
+                        status.state.as_str(),
+                        status.description
+                    );
+                }
+                0
+            }
+            Err(e) => {
+                eprintln!("Migration status failed: {}", e);
+                2
+            }
+        },
+        // Exit code 1 on drift, so deploy pipelines can gate on it.
+        ["migrate", "verify"] => match migration_status(pool).await {
+            Ok(statuses) => {
+                let drifted: Vec<_> = statuses.iter().filter(|s| s.is_drift()).collect();
+                let pending = statuses
+                    .iter()
+                    .filter(|s| s.state == MigrationState::Pending)
+                    .count();
+
+                for status in &drifted {
+                    eprintln!(
+                        "Migration {} {}: {}",
+                        status.version,
+                        status.description,
+                        status.state.as_str()
+                    );
+                }
+                println!(
+                    "{} drifted, {} pending, {} embedded",
+                    drifted.len(),
+                    pending,
+                    MIGRATOR.iter().count()
+                );
+                if drifted.is_empty() { 0 } else { 1 }
+            }
+            Err(e) => {
+                eprintln!("Migration verification failed: {}", e);
+                2
+            }
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            2
+        }
+    }
+}
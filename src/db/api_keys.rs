@@ -0,0 +1,195 @@
+use crate::db::DbPool;
+use crate::errors::AppResult;
+use crate::models::{API_KEY_LAST_USED_INTERVAL_SECONDS, ApiKey, CreateApiKey};
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+
+fn api_key_row(row: sqlx::postgres::PgRow) -> ApiKey {
+    ApiKey::from_db(
+        row.get::<i64, _>("id"),
+        row.get::<i64, _>("user_id"),
+        row.get::<String, _>("name"),
+        row.get::<String, _>("prefix"),
+        row.get::<String, _>("key_hash"),
+        row.get::<Vec<String>, _>("scopes"),
+        row.get::<Option<i64>, _>("multisig_id"),
+        row.get::<Option<DateTime<Utc>>, _>("expires_at"),
+        row.get::<Option<DateTime<Utc>>, _>("last_used_at"),
+        row.get::<DateTime<Utc>, _>("created_at"),
+        row.get::<Option<DateTime<Utc>>, _>("revoked_at"),
+    )
+}
+
+fn scope_strings(key_data: &CreateApiKey) -> Vec<String> {
+    key_data
+        .scopes
+        .iter()
+        .map(|scope| scope.as_str().to_string())
+        .collect()
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn create_api_key(
+    pool: &DbPool,
+    user_id: i64,
+    key_data: &CreateApiKey,
+    prefix: &str,
+    key_hash: &str,
+) -> AppResult<ApiKey> {
+    let api_key = sqlx::query(
+        r#"
+        INSERT INTO api_keys (user_id, name, prefix, key_hash, scopes, multisig_id, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, user_id, name, prefix, key_hash, scopes, multisig_id, expires_at,
+                  last_used_at, created_at, revoked_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(&key_data.name)
+    .bind(prefix)
+    .bind(key_hash)
+    .bind(scope_strings(key_data))
+    .bind(key_data.multisig_id)
+    .bind(key_data.expires_at)
+    .map(api_key_row)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(api_key)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_user_api_keys(pool: &DbPool, user_id: i64) -> AppResult<Vec<ApiKey>> {
+    let api_keys = sqlx::query(
+        r#"
+        SELECT id, user_id, name, prefix, key_hash, scopes, multisig_id, expires_at,
+               last_used_at, created_at, revoked_at
+        FROM api_keys
+        WHERE user_id = $1 AND revoked_at IS NULL
+        ORDER BY created_at, id
+        "#,
+    )
+    .bind(user_id)
+    .map(api_key_row)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(api_keys)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn count_user_api_keys(pool: &DbPool, user_id: i64) -> AppResult<i64> {
+    let count = sqlx::query(
+        "SELECT COUNT(*) AS count FROM api_keys WHERE user_id = $1 AND revoked_at IS NULL",
+    )
+    .bind(user_id)
+    .map(|row: sqlx::postgres::PgRow| row.get::<i64, _>("count"))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_user_api_key(
+    pool: &DbPool,
+    user_id: i64,
+    api_key_id: i64,
+) -> AppResult<Option<ApiKey>> {
+    let api_key = sqlx::query(
+        r#"
+        SELECT id, user_id, name, prefix, key_hash, scopes, multisig_id, expires_at,
+               last_used_at, created_at, revoked_at
+        FROM api_keys
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(api_key_id)
+    .bind(user_id)
+    .map(api_key_row)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(api_key)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_api_key_by_prefix(pool: &DbPool, prefix: &str) -> AppResult<Option<ApiKey>> {
+    let api_key = sqlx::query(
+        r#"
+        SELECT id, user_id, name, prefix, key_hash, scopes, multisig_id, expires_at,
+               last_used_at, created_at, revoked_at
+        FROM api_keys
+        WHERE prefix = $1
+        "#,
+    )
+    .bind(prefix)
+    .map(api_key_row)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(api_key)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn update_api_key(
+    pool: &DbPool,
+    api_key_id: i64,
+    key_data: &CreateApiKey,
+) -> AppResult<ApiKey> {
+    let api_key = sqlx::query(
+        r#"
+        UPDATE api_keys
+        SET name = $2, scopes = $3, expires_at = $4
+        WHERE id = $1
+        RETURNING id, user_id, name, prefix, key_hash, scopes, multisig_id, expires_at,
+                  last_used_at, created_at, revoked_at
+        "#,
+    )
+    .bind(api_key_id)
+    .bind(&key_data.name)
+    .bind(scope_strings(key_data))
+    .bind(key_data.expires_at)
+    .map(api_key_row)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(api_key)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn revoke_api_key(pool: &DbPool, user_id: i64, api_key_id: i64) -> AppResult<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE api_keys
+        SET revoked_at = NOW()
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(api_key_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Like session touches, `last_used_at` is only written once per interval so busy bots do not
+// update the row on every request.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn touch_api_key(pool: &DbPool, api_key_id: i64) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE api_keys
+        SET last_used_at = NOW()
+        WHERE id = $1
+          AND (last_used_at IS NULL OR last_used_at < NOW() - make_interval(secs => $2))
+        "#,
+    )
+    .bind(api_key_id)
+    .bind(API_KEY_LAST_USED_INTERVAL_SECONDS as f64)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use crate::db::DbPool;
+use crate::errors::AppResult;
+use crate::models::{AUDIT_GENESIS_HASH, AuditEntry, CreateAuditEntry};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, Row};
+
+// Serializes writers on a transaction-scoped advisory lock so every entry links to the one
+// committed immediately before it.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn append_audit_entry(
+    conn: &mut PgConnection,
+    entry_data: CreateAuditEntry,
+) -> AppResult<AuditEntry> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('audit_log'))")
+        .execute(&mut *conn)
+        .await?;
+
+    let prev_hash = sqlx::query(
+        r#"
+        SELECT entry_hash FROM audit_log
+        ORDER BY id DESC
+        LIMIT 1
+        "#,
+    )
+    .map(|row: sqlx::postgres::PgRow| row.get::<String, _>("entry_hash"))
+    .fetch_optional(&mut *conn)
+    .await?
+    .unwrap_or_else(|| AUDIT_GENESIS_HASH.to_string());
+
+    let id = sqlx::query("SELECT nextval(pg_get_serial_sequence('audit_log', 'id')) as id")
+        .map(|row: sqlx::postgres::PgRow| row.get::<i64, _>("id"))
+        .fetch_one(&mut *conn)
+        .await?;
+
+    // Postgres keeps microsecond precision; truncate first so the stored value hashes the same.
+    let occurred_at = DateTime::<Utc>::from_timestamp_micros(Utc::now().timestamp_micros())
+        .unwrap_or_else(Utc::now);
+    let action = entry_data.action.as_str();
+
+    let entry_hash = AuditEntry::compute_hash(
+        &prev_hash,
+        id,
+        occurred_at,
+        entry_data.actor_id,
+        action,
+        entry_data.entity_type.as_deref(),
+        entry_data.entity_id,
+        &entry_data.details,
+    );
+
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (id, occurred_at, actor_id, action, entity_type, entity_id, details, prev_hash, entry_hash)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+    )
+    .bind(id)
+    .bind(occurred_at)
+    .bind(entry_data.actor_id)
+    .bind(action)
+    .bind(&entry_data.entity_type)
+    .bind(entry_data.entity_id)
+    .bind(&entry_data.details)
+    .bind(&prev_hash)
+    .bind(&entry_hash)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(AuditEntry::from_db(
+        id,
+        occurred_at,
+        entry_data.actor_id,
+        action.to_string(),
+        entry_data.entity_type,
+        entry_data.entity_id,
+        entry_data.details,
+        prev_hash,
+        entry_hash,
+    ))
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_audit_entries_after(
+    pool: &DbPool,
+    after_id: i64,
+    limit: i64,
+) -> AppResult<Vec<AuditEntry>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, occurred_at, actor_id, action, entity_type, entity_id, details, prev_hash, entry_hash
+        FROM audit_log
+        WHERE id > $1
+        ORDER BY id ASC
+        LIMIT $2
+        "#,
+    )
+    .bind(after_id)
+    .bind(limit)
+    .map(|row: sqlx::postgres::PgRow| {
+        (
+            row.get::<i64, _>("id"),
+            row.get::<DateTime<Utc>, _>("occurred_at"),
+            row.get::<Option<i64>, _>("actor_id"),
+            row.get::<String, _>("action"),
+            row.get::<Option<String>, _>("entity_type"),
+            row.get::<Option<i64>, _>("entity_id"),
+            row.get::<serde_json::Value, _>("details"),
+            row.get::<String, _>("prev_hash"),
+            row.get::<String, _>("entry_hash"),
+        )
+    })
+    .fetch_all(pool)
+    .await?;
+
+    let entries = rows
+        .into_iter()
+        .map(|r| AuditEntry::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7, r.8))
+        .collect();
+
+    Ok(entries)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_latest_audit_entry(pool: &DbPool) -> AppResult<Option<(i64, String)>> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, entry_hash FROM audit_log
+        ORDER BY id DESC
+        LIMIT 1
+        "#,
+    )
+    .map(|row: sqlx::postgres::PgRow| (row.get::<i64, _>("id"), row.get::<String, _>("entry_hash")))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row)
+}
@@ -0,0 +1,292 @@
+use crate::db::{DbPool, push_keyset_pagination};
+use crate::errors::{AppError, AppResult};
+use crate::models::{Cursor, Job, JobFilter, JobStatus, NewJob, Page, PageRequest};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, Postgres, QueryBuilder, Row};
+
+fn job_row(row: sqlx::postgres::PgRow) -> Job {
+    Job {
+        id: row.get::<i64, _>("id"),
+        kind: row.get::<String, _>("kind"),
+        payload: row.get::<serde_json::Value, _>("payload"),
+        status: row.get::<JobStatus, _>("status"),
+        unique_key: row.get::<Option<String>, _>("unique_key"),
+        attempts: row.get::<i32, _>("attempts"),
+        max_attempts: row.get::<i32, _>("max_attempts"),
+        run_at: row.get::<DateTime<Utc>, _>("run_at"),
+        locked_until: row.get::<Option<DateTime<Utc>>, _>("locked_until"),
+        last_error: row.get::<Option<String>, _>("last_error"),
+        created_at: row.get::<DateTime<Utc>, _>("created_at"),
+        updated_at: row.get::<DateTime<Utc>, _>("updated_at"),
+        finished_at: row.get::<Option<DateTime<Utc>>, _>("finished_at"),
+    }
+}
+
+// Takes a connection so callers can enqueue inside the transaction that makes the job necessary.
+// Returns None when a live job with the same unique key already exists.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn enqueue_job(conn: &mut PgConnection, job: &NewJob) -> AppResult<Option<Job>> {
+    let job = sqlx::query(
+        r#"
+        INSERT INTO jobs (kind, payload, max_attempts, run_at, unique_key)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (kind, unique_key)
+            WHERE unique_key IS NOT NULL AND status IN ('pending', 'running')
+            DO NOTHING
+        RETURNING id, kind, payload, status, unique_key, attempts, max_attempts, run_at,
+                  locked_until, last_error, created_at, updated_at, finished_at
+        "#,
+    )
+    .bind(job.kind)
+    .bind(&job.payload)
+    .bind(job.max_attempts)
+    .bind(job.run_at)
+    .bind(&job.unique_key)
+    .map(job_row)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(job)
+}
+
+// Claiming counts as an attempt and leases the row through `locked_until`. A worker that dies
+// mid-job leaves it running until the lease runs out; it is then claimed again, or dead-lettered
+// if that was its last attempt.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn claim_due_jobs(pool: &DbPool, limit: i64, lease_seconds: i64) -> AppResult<Vec<Job>> {
+    sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = 'dead',
+            locked_until = NULL,
+            last_error = 'Lease expired on the final attempt',
+            updated_at = NOW(),
+            finished_at = NOW()
+        WHERE status = 'running' AND locked_until <= NOW() AND attempts >= max_attempts
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    let jobs = sqlx::query(
+        r#"
+        WITH due AS (
+            SELECT id FROM jobs
+            WHERE (status = 'pending' AND run_at <= NOW())
+               OR (status = 'running' AND locked_until <= NOW())
+            ORDER BY run_at ASC
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        )
+        UPDATE jobs j
+        SET status = 'running',
+            attempts = j.attempts + 1,
+            locked_until = NOW() + make_interval(secs => $2),
+            updated_at = NOW()
+        FROM due
+        WHERE j.id = due.id
+        RETURNING j.id, j.kind, j.payload, j.status, j.unique_key, j.attempts, j.max_attempts,
+                  j.run_at, j.locked_until, j.last_error, j.created_at, j.updated_at,
+                  j.finished_at
+        "#,
+    )
+    .bind(limit)
+    .bind(lease_seconds as f64)
+    .map(job_row)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(jobs)
+}
+
+// The claim is identified by the attempt number it was given. Only a job this worker still holds is
+// updated: a job cancelled mid-run stays cancelled, and once the lease has run out and another
+// worker claimed the job again, the attempt count has moved on and this worker's result is dropped.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn complete_job(pool: &DbPool, job_id: i64, attempt: i32) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = 'succeeded', locked_until = NULL, updated_at = NOW(), finished_at = NOW()
+        WHERE id = $1 AND status = 'running' AND attempts = $2
+        "#,
+    )
+    .bind(job_id)
+    .bind(attempt)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Hands back a claimed job that was never started, without charging it an attempt.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn release_job(pool: &DbPool, job_id: i64, attempt: i32) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = 'pending', attempts = attempts - 1, locked_until = NULL, updated_at = NOW()
+        WHERE id = $1 AND status = 'running' AND attempts = $2
+        "#,
+    )
+    .bind(job_id)
+    .bind(attempt)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Puts a failed job back to pending at `retry_at`, or marks it dead when there is none.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn fail_job(
+    pool: &DbPool,
+    job_id: i64,
+    attempt: i32,
+    error: &str,
+    retry_at: Option<DateTime<Utc>>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = CASE WHEN $3::TIMESTAMPTZ IS NULL THEN 'dead'::job_status ELSE 'pending' END,
+            run_at = COALESCE($3, run_at),
+            locked_until = NULL,
+            last_error = $2,
+            updated_at = NOW(),
+            finished_at = CASE WHEN $3::TIMESTAMPTZ IS NULL THEN NOW() ELSE NULL END
+        WHERE id = $1 AND status = 'running' AND attempts = $4
+        "#,
+    )
+    .bind(job_id)
+    .bind(error)
+    .bind(retry_at)
+    .bind(attempt)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_job_by_id(pool: &DbPool, job_id: i64) -> AppResult<Option<Job>> {
+    let job = sqlx::query(
+        r#"
+        SELECT id, kind, payload, status, unique_key, attempts, max_attempts, run_at,
+               locked_until, last_error, created_at, updated_at, finished_at
+        FROM jobs
+        WHERE id = $1
+        "#,
+    )
+    .bind(job_id)
+    .map(job_row)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(job)
+}
+
+fn push_job_filters(builder: &mut QueryBuilder<'_, Postgres>, filter: &JobFilter) {
+    builder.push(" WHERE TRUE");
+
+    if let Some(status) = filter.status {
+        builder.push(" AND status = ").push_bind(status);
+    }
+
+    if let Some(kind) = &filter.kind {
+        builder.push(" AND kind = ").push_bind(kind.clone());
+    }
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_jobs(
+    pool: &DbPool,
+    filter: &JobFilter,
+    page: &PageRequest,
+) -> AppResult<Page<Job>> {
+    let mut count_builder = QueryBuilder::<Postgres>::new("SELECT COUNT(*) as count FROM jobs");
+    push_job_filters(&mut count_builder, filter);
+
+    let total_count = count_builder
+        .build()
+        .map(|row: sqlx::postgres::PgRow| row.get::<i64, _>("count"))
+        .fetch_one(pool)
+        .await?;
+
+    let mut builder = QueryBuilder::<Postgres>::new(
+        r#"
+        SELECT id, kind, payload, status, unique_key, attempts, max_attempts, run_at,
+               locked_until, last_error, created_at, updated_at, finished_at
+        FROM jobs"#,
+    );
+    push_job_filters(&mut builder, filter);
+    push_keyset_pagination(&mut builder, page);
+
+    let jobs = builder.build().map(job_row).fetch_all(pool).await?;
+
+    Ok(Page::from_rows(jobs, page.limit, total_count, |job| {
+        Cursor::new(job.created_at, job.id)
+    }))
+}
+
+// Starts the job over with a fresh set of attempts. Fails with a conflict if another live job
+// already holds the same unique key.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn retry_job(pool: &DbPool, job_id: i64) -> AppResult<Option<Job>> {
+    let result = sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = 'pending', attempts = 0, run_at = NOW(), locked_until = NULL,
+            updated_at = NOW(), finished_at = NULL
+        WHERE id = $1 AND status IN ('dead', 'cancelled')
+        RETURNING id, kind, payload, status, unique_key, attempts, max_attempts, run_at,
+                  locked_until, last_error, created_at, updated_at, finished_at
+        "#,
+    )
+    .bind(job_id)
+    .map(job_row)
+    .fetch_optional(pool)
+    .await;
+
+    match result {
+        Ok(job) => Ok(job),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Err(AppError::Conflict(
+            "Another job with the same unique key is already pending or running".to_string(),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn cancel_job(pool: &DbPool, job_id: i64) -> AppResult<Option<Job>> {
+    let job = sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = 'cancelled', locked_until = NULL, updated_at = NOW(), finished_at = NOW()
+        WHERE id = $1 AND status IN ('pending', 'running')
+        RETURNING id, kind, payload, status, unique_key, attempts, max_attempts, run_at,
+                  locked_until, last_error, created_at, updated_at, finished_at
+        "#,
+    )
+    .bind(job_id)
+    .map(job_row)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(job)
+}
+
+// Dead jobs are kept until an admin retries or inspects them.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn delete_finished_jobs_before(pool: &DbPool, before: DateTime<Utc>) -> AppResult<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM jobs WHERE status IN ('succeeded', 'cancelled') AND finished_at < $1
+        "#,
+    )
+    .bind(before)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
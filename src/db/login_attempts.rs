@@ -0,0 +1,117 @@
+use crate::db::DbPool;
+use crate::errors::AppResult;
+use crate::models::{CreateLoginAttempt, LoginAttemptStats};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, Row};
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn record_login_attempt(
+    conn: &mut PgConnection,
+    attempt: CreateLoginAttempt,
+) -> AppResult<i64> {
+    let id = sqlx::query(
+        r#"
+        INSERT INTO login_attempts (email, user_id, ip_address, succeeded)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+    )
+    .bind(&attempt.email)
+    .bind(attempt.user_id)
+    .bind(&attempt.ip_address)
+    .bind(attempt.succeeded)
+    .map(|row: sqlx::postgres::PgRow| row.get::<i64, _>("id"))
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(id)
+}
+
+// Held until the transaction ends, so checking the count and recording the next attempt for one
+// account cannot interleave with another request for the same account.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn lock_login_attempts(conn: &mut PgConnection, email: &str) -> AppResult<()> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('login_attempts:' || $1))")
+        .bind(email)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn mark_login_attempt_succeeded(pool: &DbPool, attempt_id: i64) -> AppResult<()> {
+    sqlx::query("UPDATE login_attempts SET succeeded = TRUE WHERE id = $1")
+        .bind(attempt_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn get_login_attempt_stats(
+    conn: &mut PgConnection,
+    email: &str,
+    ip_address: Option<&str>,
+    since: DateTime<Utc>,
+) -> AppResult<LoginAttemptStats> {
+    let stats = sqlx::query(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE email = $1) AS account_failures,
+            MAX(attempted_at) FILTER (WHERE email = $1) AS last_account_failure,
+            COUNT(*) FILTER (WHERE ip_address = $2) AS ip_failures,
+            MIN(attempted_at) FILTER (WHERE ip_address = $2) AS oldest_ip_failure
+        FROM login_attempts
+        WHERE (email = $1 OR ip_address = $2)
+          AND NOT succeeded
+          AND cleared_at IS NULL
+          AND attempted_at > $3
+        "#,
+    )
+    .bind(email)
+    .bind(ip_address)
+    .bind(since)
+    .map(|row: sqlx::postgres::PgRow| LoginAttemptStats {
+        account_failures: row.get::<i64, _>("account_failures"),
+        last_account_failure: row.get::<Option<DateTime<Utc>>, _>("last_account_failure"),
+        ip_failures: row.get::<i64, _>("ip_failures"),
+        oldest_ip_failure: row.get::<Option<DateTime<Utc>>, _>("oldest_ip_failure"),
+    })
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(stats)
+}
+
+// Clears outstanding failures for an account; the per-IP count is left alone.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn clear_login_failures(conn: &mut PgConnection, email: &str) -> AppResult<u64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE login_attempts
+        SET cleared_at = NOW()
+        WHERE email = $1 AND NOT succeeded AND cleared_at IS NULL
+        "#,
+    )
+    .bind(email)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn delete_login_attempts_before(pool: &DbPool, before: DateTime<Utc>) -> AppResult<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM login_attempts WHERE attempted_at < $1
+        "#,
+    )
+    .bind(before)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
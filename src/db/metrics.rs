@@ -0,0 +1,79 @@
+use crate::db::DbPool;
+use crate::errors::AppResult;
+use crate::models::{JobStatus, ProposalStatus};
+use sqlx::Row;
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn count_proposals_by_status(pool: &DbPool) -> AppResult<Vec<(ProposalStatus, i64)>> {
+    let counts = sqlx::query(
+        r#"
+        SELECT status, COUNT(*) AS count
+        FROM proposals
+        GROUP BY status
+        "#,
+    )
+    .map(|row: sqlx::postgres::PgRow| {
+        (
+            row.get::<ProposalStatus, _>("status"),
+            row.get::<i64, _>("count"),
+        )
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(counts)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn count_jobs_by_status(pool: &DbPool) -> AppResult<Vec<(JobStatus, i64)>> {
+    let counts = sqlx::query(
+        r#"
+        SELECT status, COUNT(*) AS count
+        FROM jobs
+        GROUP BY status
+        "#,
+    )
+    .map(|row: sqlx::postgres::PgRow| {
+        (
+            row.get::<JobStatus, _>("status"),
+            row.get::<i64, _>("count"),
+        )
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(counts)
+}
+
+/// Pending webhook deliveries and the age in seconds of the oldest one.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn get_webhook_backlog(pool: &DbPool) -> AppResult<(i64, i64)> {
+    let backlog = sqlx::query(
+        r#"
+        SELECT COUNT(*) AS pending,
+               COALESCE(EXTRACT(EPOCH FROM NOW() - MIN(created_at)), 0)::BIGINT AS oldest_seconds
+        FROM webhook_deliveries
+        WHERE status = 'pending'
+        "#,
+    )
+    .map(|row: sqlx::postgres::PgRow| {
+        (
+            row.get::<i64, _>("pending"),
+            row.get::<i64, _>("oldest_seconds"),
+        )
+    })
+    .fetch_one(pool)
+    .await?;
+
+    Ok(backlog)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn count_unsent_notifications(pool: &DbPool) -> AppResult<i64> {
+    let count = sqlx::query("SELECT COUNT(*) AS count FROM notifications WHERE sent_at IS NULL")
+        .map(|row: sqlx::postgres::PgRow| row.get::<i64, _>("count"))
+        .fetch_one(pool)
+        .await?;
+
+    Ok(count)
+}
@@ -1,9 +1,39 @@
+pub mod api_keys;
+pub mod audit_log;
+pub mod jobs;
+pub mod login_attempts;
+pub mod metrics;
 pub mod multisigs;
+pub mod notifications;
+pub mod oidc;
+pub mod pagination;
+pub mod passkeys;
 pub mod pool;
+pub mod proposal_events;
 pub mod proposals;
+pub mod sessions;
+pub mod tokens;
+pub mod two_factor;
+pub mod user_tokens;
 pub mod users;
+pub mod webhooks;
 
+pub use api_keys::*;
+pub use audit_log::*;
+pub use jobs::*;
+pub use login_attempts::*;
+pub use metrics::*;
 pub use multisigs::*;
+pub use notifications::*;
+pub use oidc::*;
+pub use pagination::*;
+pub use passkeys::*;
 pub use pool::*;
+pub use proposal_events::*;
 pub use proposals::*;
+pub use sessions::*;
+pub use tokens::*;
+pub use two_factor::*;
+pub use user_tokens::*;
 pub use users::*;
+pub use webhooks::*;
@@ -1,9 +1,10 @@
-use crate::db::DbPool;
+use crate::db::{DbPool, push_keyset_pagination};
 use crate::errors::AppResult;
-use crate::models::{CreateMultisig, Multisig};
+use crate::models::{CreateMultisig, Cursor, Multisig, Page, PageRequest};
 use chrono::{DateTime, Utc};
-use sqlx::Row;
+use sqlx::{Postgres, QueryBuilder, Row};
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn create_multisig(
     pool: &DbPool,
     multisig_data: CreateMultisig,
@@ -11,9 +12,9 @@ pub async fn create_multisig(
 ) -> AppResult<Multisig> {
     let row = sqlx::query(
         r#"
-        INSERT INTO multisigs (name, description, created_by, owners, threshold)
-        VALUES ($1, $2, $3, $4, $5)
-        RETURNING id, name, description, created_by, owners, threshold, created_at::TIMESTAMPTZ as created_at
+        INSERT INTO multisigs (name, description, created_by, owners, threshold, require_step_up)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, name, description, created_by, owners, threshold, require_step_up, created_at::TIMESTAMPTZ as created_at
         "#,
     )
     .bind(&multisig_data.name)
@@ -21,6 +22,7 @@ pub async fn create_multisig(
     .bind(created_by)
     .bind(&multisig_data.owners)
     .bind(multisig_data.threshold)
+    .bind(multisig_data.require_step_up)
     .map(|row: sqlx::postgres::PgRow| {
         (
             row.get::<i64, _>("id"),
@@ -29,6 +31,7 @@ pub async fn create_multisig(
             row.get::<i64, _>("created_by"),
             row.get::<Vec<i64>, _>("owners"),
             row.get::<i32, _>("threshold"),
+            row.get::<bool, _>("require_step_up"),
             row.get::<DateTime<Utc>, _>("created_at"),
         )
     })
@@ -36,14 +39,15 @@ pub async fn create_multisig(
     .await?;
 
     Ok(Multisig::from_db(
-        row.0, row.1, row.2, row.3, row.4, row.5, row.6,
+        row.0, row.1, row.2, row.3, row.4, row.5, row.6, row.7,
     ))
 }
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn find_multisig_by_id(pool: &DbPool, multisig_id: i64) -> AppResult<Option<Multisig>> {
     let row = sqlx::query(
         r#"
-        SELECT id, name, description, created_by, owners, threshold, created_at::TIMESTAMPTZ as created_at
+        SELECT id, name, description, created_by, owners, threshold, require_step_up, created_at::TIMESTAMPTZ as created_at
         FROM multisigs
         WHERE id = $1
         "#,
@@ -57,43 +61,86 @@ pub async fn find_multisig_by_id(pool: &DbPool, multisig_id: i64) -> AppResult<O
             row.get::<i64, _>("created_by"),
             row.get::<Vec<i64>, _>("owners"),
             row.get::<i32, _>("threshold"),
+            row.get::<bool, _>("require_step_up"),
             row.get::<DateTime<Utc>, _>("created_at"),
         )
     })
     .fetch_optional(pool)
     .await?;
 
-    Ok(row.map(|r| Multisig::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6)))
+    Ok(row.map(|r| Multisig::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7)))
 }
 
-pub async fn list_user_multisigs(pool: &DbPool, user_id: i64) -> AppResult<Vec<Multisig>> {
-    let rows = sqlx::query(
+// Filters with `owners @> ARRAY[user]`; the GIN index on owners cannot serve `user = ANY(owners)`.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_user_multisigs(
+    pool: &DbPool,
+    user_id: i64,
+    page: &PageRequest,
+) -> AppResult<Page<Multisig>> {
+    let total_count = sqlx::query(
         r#"
-        SELECT id, name, description, created_by, owners, threshold, created_at::TIMESTAMPTZ as created_at
+        SELECT COUNT(*) as count
         FROM multisigs
-        WHERE $1 = ANY(owners)
-        ORDER BY created_at DESC
+        WHERE owners @> ARRAY[$1]::BIGINT[]
         "#,
     )
     .bind(user_id)
-    .map(|row: sqlx::postgres::PgRow| {
-        (
-            row.get::<i64, _>("id"),
-            row.get::<String, _>("name"),
-            row.get::<Option<String>, _>("description"),
-            row.get::<i64, _>("created_by"),
-            row.get::<Vec<i64>, _>("owners"),
-            row.get::<i32, _>("threshold"),
-            row.get::<DateTime<Utc>, _>("created_at"),
-        )
-    })
-    .fetch_all(pool)
+    .map(|row: sqlx::postgres::PgRow| row.get::<i64, _>("count"))
+    .fetch_one(pool)
     .await?;
 
+    let mut builder = QueryBuilder::<Postgres>::new(
+        r#"
+        SELECT id, name, description, created_by, owners, threshold, require_step_up, created_at::TIMESTAMPTZ as created_at
+        FROM multisigs
+        WHERE "#,
+    );
+    builder
+        .push("owners @> ARRAY[")
+        .push_bind(user_id)
+        .push("]::BIGINT[]");
+
+    push_keyset_pagination(&mut builder, page);
+
+    let rows = builder
+        .build()
+        .map(|row: sqlx::postgres::PgRow| {
+            (
+                row.get::<i64, _>("id"),
+                row.get::<String, _>("name"),
+                row.get::<Option<String>, _>("description"),
+                row.get::<i64, _>("created_by"),
+                row.get::<Vec<i64>, _>("owners"),
+                row.get::<i32, _>("threshold"),
+                row.get::<bool, _>("require_step_up"),
+                row.get::<DateTime<Utc>, _>("created_at"),
+            )
+        })
+        .fetch_all(pool)
+        .await?;
+
     let multisigs = rows
         .into_iter()
-        .map(|r| Multisig::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6))
+        .map(|r| Multisig::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7))
         .collect();
 
-    Ok(multisigs)
+    Ok(Page::from_rows(multisigs, page.limit, total_count, |m| {
+        Cursor::new(m.created_at, m.id)
+    }))
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn update_multisig_step_up(
+    pool: &DbPool,
+    multisig_id: i64,
+    require_step_up: bool,
+) -> AppResult<()> {
+    sqlx::query("UPDATE multisigs SET require_step_up = $2 WHERE id = $1")
+        .bind(multisig_id)
+        .bind(require_step_up)
+        .execute(pool)
+        .await?;
+
+    Ok(())
 }
@@ -0,0 +1,192 @@
+use crate::db::DbPool;
+use crate::errors::AppResult;
+use crate::models::{
+    DigestFrequency, NotificationKind, NotificationPreferences, PendingNotification, ProposalStatus,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, Row};
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_notification_preferences(
+    pool: &DbPool,
+    user_id: i64,
+) -> AppResult<Option<NotificationPreferences>> {
+    let row = sqlx::query(
+        r#"
+        SELECT user_id, email_enabled, notify_vote_needed, notify_threshold_reached,
+               digest_frequency, updated_at
+        FROM notification_preferences
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .map(|row: sqlx::postgres::PgRow| {
+        (
+            row.get::<i64, _>("user_id"),
+            row.get::<bool, _>("email_enabled"),
+            row.get::<bool, _>("notify_vote_needed"),
+            row.get::<bool, _>("notify_threshold_reached"),
+            row.get::<DigestFrequency, _>("digest_frequency"),
+            row.get::<DateTime<Utc>, _>("updated_at"),
+        )
+    })
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| NotificationPreferences::from_db(r.0, r.1, r.2, r.3, r.4, Some(r.5))))
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn upsert_notification_preferences(
+    pool: &DbPool,
+    preferences: &NotificationPreferences,
+) -> AppResult<NotificationPreferences> {
+    let row = sqlx::query(
+        r#"
+        INSERT INTO notification_preferences
+            (user_id, email_enabled, notify_vote_needed, notify_threshold_reached, digest_frequency)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (user_id) DO UPDATE
+        SET email_enabled = EXCLUDED.email_enabled,
+            notify_vote_needed = EXCLUDED.notify_vote_needed,
+            notify_threshold_reached = EXCLUDED.notify_threshold_reached,
+            digest_frequency = EXCLUDED.digest_frequency,
+            updated_at = NOW()
+        RETURNING user_id, email_enabled, notify_vote_needed, notify_threshold_reached,
+                  digest_frequency, updated_at
+        "#,
+    )
+    .bind(preferences.user_id)
+    .bind(preferences.email_enabled)
+    .bind(preferences.notify_vote_needed)
+    .bind(preferences.notify_threshold_reached)
+    .bind(preferences.digest_frequency)
+    .map(|row: sqlx::postgres::PgRow| {
+        (
+            row.get::<i64, _>("user_id"),
+            row.get::<bool, _>("email_enabled"),
+            row.get::<bool, _>("notify_vote_needed"),
+            row.get::<bool, _>("notify_threshold_reached"),
+            row.get::<DigestFrequency, _>("digest_frequency"),
+            row.get::<DateTime<Utc>, _>("updated_at"),
+        )
+    })
+    .fetch_one(pool)
+    .await?;
+
+    Ok(NotificationPreferences::from_db(
+        row.0,
+        row.1,
+        row.2,
+        row.3,
+        row.4,
+        Some(row.5),
+    ))
+}
+
+// Recipients are the multisig owners with a verified email whose preferences allow this kind
+// of email.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn enqueue_notifications(
+    conn: &mut PgConnection,
+    multisig_id: i64,
+    proposal_id: i64,
+    kind: NotificationKind,
+    exclude_user_id: Option<i64>,
+) -> AppResult<u64> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO notifications (user_id, proposal_id, kind)
+        SELECT u.id, $2, $3
+        FROM multisigs m
+        JOIN users u ON u.id = ANY(m.owners)
+        LEFT JOIN notification_preferences p ON p.user_id = u.id
+        WHERE m.id = $1
+          AND ($4::BIGINT IS NULL OR u.id <> $4)
+          AND u.email_verified_at IS NOT NULL
+          AND COALESCE(p.email_enabled, TRUE)
+          AND CASE $3
+                WHEN 'vote_needed'::notification_kind THEN COALESCE(p.notify_vote_needed, TRUE)
+                ELSE COALESCE(p.notify_threshold_reached, TRUE)
+              END
+        "#,
+    )
+    .bind(multisig_id)
+    .bind(proposal_id)
+    .bind(kind)
+    .bind(exclude_user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+// A user is due once their oldest unsent notification has waited out their digest window.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_users_with_due_notifications(pool: &DbPool, limit: i64) -> AppResult<Vec<i64>> {
+    let user_ids = sqlx::query(
+        r#"
+        SELECT n.user_id
+        FROM notifications n
+        LEFT JOIN notification_preferences p ON p.user_id = n.user_id
+        WHERE n.sent_at IS NULL
+        GROUP BY n.user_id, p.digest_frequency
+        HAVING MIN(n.created_at) <= NOW() - CASE COALESCE(p.digest_frequency, 'immediate')
+                WHEN 'hourly' THEN INTERVAL '1 hour'
+                WHEN 'daily' THEN INTERVAL '1 day'
+                ELSE INTERVAL '0 seconds'
+            END
+        ORDER BY MIN(n.created_at)
+        LIMIT $1
+        "#,
+    )
+    .bind(limit)
+    .map(|row: sqlx::postgres::PgRow| row.get::<i64, _>("user_id"))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(user_ids)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn claim_pending_notifications(
+    conn: &mut PgConnection,
+    user_id: i64,
+) -> AppResult<Vec<PendingNotification>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT n.id, u.email, n.kind, n.proposal_id, p.title, p.status, m.name
+        FROM notifications n
+        JOIN users u ON u.id = n.user_id
+        JOIN proposals p ON p.id = n.proposal_id
+        JOIN multisigs m ON m.id = p.multisig_id
+        WHERE n.user_id = $1 AND n.sent_at IS NULL
+        ORDER BY n.created_at, n.id
+        FOR UPDATE OF n SKIP LOCKED
+        "#,
+    )
+    .bind(user_id)
+    .map(|row: sqlx::postgres::PgRow| PendingNotification {
+        id: row.get("id"),
+        email: row.get("email"),
+        kind: row.get("kind"),
+        proposal_id: row.get("proposal_id"),
+        proposal_title: row.get("title"),
+        proposal_status: row.get::<ProposalStatus, _>("status"),
+        multisig_name: row.get("name"),
+    })
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn mark_notifications_sent(conn: &mut PgConnection, ids: &[i64]) -> AppResult<()> {
+    sqlx::query("UPDATE notifications SET sent_at = NOW() WHERE id = ANY($1)")
+        .bind(ids)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
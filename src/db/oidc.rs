@@ -0,0 +1,139 @@
+use crate::db::DbPool;
+use crate::errors::AppResult;
+use crate::models::{OidcLoginState, UserIdentity};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, Row};
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn create_oidc_login_state(
+    pool: &DbPool,
+    state: &str,
+    nonce: &str,
+    code_verifier: &str,
+    expires_at: DateTime<Utc>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO oidc_login_states (state, nonce, code_verifier, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(state)
+    .bind(nonce)
+    .bind(code_verifier)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Marks the state used in the same statement that reads it, so a replayed callback finds nothing.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn consume_oidc_login_state(
+    pool: &DbPool,
+    state: &str,
+) -> AppResult<Option<OidcLoginState>> {
+    let login_state = sqlx::query(
+        r#"
+        UPDATE oidc_login_states
+        SET consumed_at = NOW()
+        WHERE state = $1 AND consumed_at IS NULL AND expires_at > NOW()
+        RETURNING nonce, code_verifier
+        "#,
+    )
+    .bind(state)
+    .map(|row: sqlx::postgres::PgRow| OidcLoginState {
+        nonce: row.get("nonce"),
+        code_verifier: row.get("code_verifier"),
+    })
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(login_state)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_user_identity(
+    pool: &DbPool,
+    issuer: &str,
+    subject: &str,
+) -> AppResult<Option<UserIdentity>> {
+    let identity = sqlx::query(
+        r#"
+        SELECT id, user_id
+        FROM user_identities
+        WHERE issuer = $1 AND subject = $2
+        "#,
+    )
+    .bind(issuer)
+    .bind(subject)
+    .map(|row: sqlx::postgres::PgRow| UserIdentity {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+    })
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(identity)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn create_user_identity(
+    conn: &mut PgConnection,
+    user_id: i64,
+    issuer: &str,
+    subject: &str,
+    email: Option<&str>,
+) -> AppResult<i64> {
+    let id = sqlx::query(
+        r#"
+        INSERT INTO user_identities (user_id, issuer, subject, email, last_login_at)
+        VALUES ($1, $2, $3, $4, NOW())
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(issuer)
+    .bind(subject)
+    .bind(email)
+    .map(|row: sqlx::postgres::PgRow| row.get::<i64, _>("id"))
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(id)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn record_identity_login(
+    pool: &DbPool,
+    identity_id: i64,
+    email: Option<&str>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE user_identities
+        SET last_login_at = NOW(), email = COALESCE($2, email)
+        WHERE id = $1
+        "#,
+    )
+    .bind(identity_id)
+    .bind(email)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn delete_expired_oidc_login_states(pool: &DbPool) -> AppResult<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM oidc_login_states WHERE expires_at < NOW()
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,30 @@
+use crate::models::PageRequest;
+use sqlx::{Postgres, QueryBuilder};
+
+// Appends the keyset condition, ordering and limit for a query that already has a WHERE clause.
+// One extra row is fetched so `Page::from_rows` can tell whether another page exists.
+pub fn push_keyset_pagination(builder: &mut QueryBuilder<'_, Postgres>, page: &PageRequest) {
+    if let Some(cursor) = page.cursor {
+        let comparison = if page.sort.is_descending() {
+            " < "
+        } else {
+            " > "
+        };
+        builder
+            .push(" AND (created_at, id)")
+            .push(comparison)
+            .push("(")
+            .push_bind(cursor.created_at)
+            .push(", ")
+            .push_bind(cursor.id)
+            .push(")");
+    }
+
+    if page.sort.is_descending() {
+        builder.push(" ORDER BY created_at DESC, id DESC");
+    } else {
+        builder.push(" ORDER BY created_at ASC, id ASC");
+    }
+
+    builder.push(" LIMIT ").push_bind(page.limit + 1);
+}
@@ -0,0 +1,206 @@
+use crate::db::DbPool;
+use crate::errors::AppResult;
+use crate::models::{
+    CreatePasskeyCredential, PasskeyCredential, WebauthnCeremony, WebauthnChallenge,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, Row};
+
+fn passkey_row(row: sqlx::postgres::PgRow) -> PasskeyCredential {
+    PasskeyCredential::from_db(
+        row.get::<i64, _>("id"),
+        row.get::<i64, _>("user_id"),
+        row.get::<String, _>("credential_id"),
+        row.get::<Vec<u8>, _>("public_key"),
+        row.get::<i64, _>("sign_count"),
+        row.get::<String, _>("name"),
+        row.get::<DateTime<Utc>, _>("created_at"),
+        row.get::<Option<DateTime<Utc>>, _>("last_used_at"),
+    )
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn create_passkey_credential(
+    pool: &DbPool,
+    credential: CreatePasskeyCredential,
+) -> AppResult<PasskeyCredential> {
+    let passkey = sqlx::query(
+        r#"
+        INSERT INTO webauthn_credentials
+            (user_id, credential_id, public_key, algorithm, sign_count, name)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, user_id, credential_id, public_key, sign_count, name, created_at, last_used_at
+        "#,
+    )
+    .bind(credential.user_id)
+    .bind(&credential.credential_id)
+    .bind(&credential.public_key)
+    .bind(credential.algorithm)
+    .bind(credential.sign_count)
+    .bind(&credential.name)
+    .map(passkey_row)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(passkey)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_user_passkeys(pool: &DbPool, user_id: i64) -> AppResult<Vec<PasskeyCredential>> {
+    let passkeys = sqlx::query(
+        r#"
+        SELECT id, user_id, credential_id, public_key, sign_count, name, created_at, last_used_at
+        FROM webauthn_credentials
+        WHERE user_id = $1
+        ORDER BY created_at, id
+        "#,
+    )
+    .bind(user_id)
+    .map(passkey_row)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(passkeys)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn passkey_credential_exists(pool: &DbPool, credential_id: &str) -> AppResult<bool> {
+    let exists = sqlx::query(
+        "SELECT EXISTS(SELECT 1 FROM webauthn_credentials WHERE credential_id = $1) AS exists",
+    )
+    .bind(credential_id)
+    .map(|row: sqlx::postgres::PgRow| row.get::<bool, _>("exists"))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(exists)
+}
+
+// Locks the credential so concurrent assertions cannot both pass the sign counter check.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_passkey_for_update(
+    conn: &mut PgConnection,
+    credential_id: &str,
+) -> AppResult<Option<PasskeyCredential>> {
+    let passkey = sqlx::query(
+        r#"
+        SELECT id, user_id, credential_id, public_key, sign_count, name, created_at, last_used_at
+        FROM webauthn_credentials
+        WHERE credential_id = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(credential_id)
+    .map(passkey_row)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(passkey)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn record_passkey_use(
+    conn: &mut PgConnection,
+    passkey_id: i64,
+    sign_count: i64,
+) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE webauthn_credentials SET sign_count = $2, last_used_at = NOW() WHERE id = $1",
+    )
+    .bind(passkey_id)
+    .bind(sign_count)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn delete_passkey(pool: &DbPool, user_id: i64, passkey_id: i64) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM webauthn_credentials WHERE id = $1 AND user_id = $2")
+        .bind(passkey_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn create_webauthn_challenge(
+    pool: &DbPool,
+    user_id: Option<i64>,
+    ceremony: WebauthnCeremony,
+    challenge: &str,
+    expires_at: DateTime<Utc>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO webauthn_challenges (user_id, ceremony, challenge, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(user_id)
+    .bind(ceremony)
+    .bind(challenge)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_webauthn_challenge_for_update(
+    conn: &mut PgConnection,
+    challenge: &str,
+) -> AppResult<Option<WebauthnChallenge>> {
+    let challenge = sqlx::query(
+        r#"
+        SELECT id, user_id, ceremony, expires_at, consumed_at
+        FROM webauthn_challenges
+        WHERE challenge = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(challenge)
+    .map(|row: sqlx::postgres::PgRow| {
+        WebauthnChallenge::from_db(
+            row.get::<i64, _>("id"),
+            row.get::<Option<i64>, _>("user_id"),
+            row.get::<WebauthnCeremony, _>("ceremony"),
+            row.get::<DateTime<Utc>, _>("expires_at"),
+            row.get::<Option<DateTime<Utc>>, _>("consumed_at"),
+        )
+    })
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(challenge)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn consume_webauthn_challenge(
+    conn: &mut PgConnection,
+    challenge_id: i64,
+) -> AppResult<()> {
+    sqlx::query("UPDATE webauthn_challenges SET consumed_at = NOW() WHERE id = $1")
+        .bind(challenge_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn delete_expired_webauthn_challenges(pool: &DbPool) -> AppResult<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM webauthn_challenges WHERE expires_at < NOW()
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
@@ -1,4 +1,24 @@
 use sqlx::PgPool;
 
+use crate::errors::AppResult;
+
 pub type DbPool = PgPool;
 
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn ping(pool: &DbPool) -> AppResult<()> {
+    sqlx::query("SELECT 1").execute(pool).await?;
+    Ok(())
+}
+
+// Tests run against the database in DATABASE_URL, migrated to the current schema.
+#[cfg(test)]
+pub async fn test_pool() -> DbPool {
+    let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set to run the tests");
+    let pool = PgPool::connect(&url)
+        .await
+        .expect("cannot connect to DATABASE_URL");
+    crate::migrations::run_migrations(&pool)
+        .await
+        .expect("migrations failed");
+    pool
+}
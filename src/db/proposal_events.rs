@@ -0,0 +1,101 @@
+use crate::db::DbPool;
+use crate::errors::{AppError, AppResult};
+use crate::models::{
+    CreateProposalEvent, PROPOSAL_EVENTS_CHANNEL, ProposalEvent, ProposalEventNotification,
+    ProposalEventType, ProposalStatus,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, Row};
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn record_proposal_event(
+    conn: &mut PgConnection,
+    event_data: CreateProposalEvent,
+) -> AppResult<ProposalEvent> {
+    let row = sqlx::query(
+        r#"
+        INSERT INTO proposal_events (proposal_id, event_type, actor_id, old_status, new_status, metadata)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, proposal_id, event_type, actor_id, old_status, new_status, metadata, created_at
+        "#,
+    )
+    .bind(event_data.proposal_id)
+    .bind(event_data.event_type)
+    .bind(event_data.actor_id)
+    .bind(event_data.old_status)
+    .bind(event_data.new_status)
+    .bind(&event_data.metadata)
+    .map(|row: sqlx::postgres::PgRow| {
+        (
+            row.get::<i64, _>("id"),
+            row.get::<i64, _>("proposal_id"),
+            row.get::<ProposalEventType, _>("event_type"),
+            row.get::<Option<i64>, _>("actor_id"),
+            row.get::<Option<ProposalStatus>, _>("old_status"),
+            row.get::<Option<ProposalStatus>, _>("new_status"),
+            row.get::<serde_json::Value, _>("metadata"),
+            row.get::<DateTime<Utc>, _>("created_at"),
+        )
+    })
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(ProposalEvent::from_db(
+        row.0, row.1, row.2, row.3, row.4, row.5, row.6, row.7,
+    ))
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_proposal_events(
+    pool: &DbPool,
+    proposal_id: i64,
+) -> AppResult<Vec<ProposalEvent>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, proposal_id, event_type, actor_id, old_status, new_status, metadata, created_at
+        FROM proposal_events
+        WHERE proposal_id = $1
+        ORDER BY created_at ASC, id ASC
+        "#,
+    )
+    .bind(proposal_id)
+    .map(|row: sqlx::postgres::PgRow| {
+        (
+            row.get::<i64, _>("id"),
+            row.get::<i64, _>("proposal_id"),
+            row.get::<ProposalEventType, _>("event_type"),
+            row.get::<Option<i64>, _>("actor_id"),
+            row.get::<Option<ProposalStatus>, _>("old_status"),
+            row.get::<Option<ProposalStatus>, _>("new_status"),
+            row.get::<serde_json::Value, _>("metadata"),
+            row.get::<DateTime<Utc>, _>("created_at"),
+        )
+    })
+    .fetch_all(pool)
+    .await?;
+
+    let events = rows
+        .into_iter()
+        .map(|r| ProposalEvent::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7))
+        .collect();
+
+    Ok(events)
+}
+
+// NOTIFY is transactional: listeners only see the event once the caller's transaction commits.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn notify_proposal_event(
+    conn: &mut PgConnection,
+    notification: &ProposalEventNotification,
+) -> AppResult<()> {
+    let payload = serde_json::to_string(notification)
+        .map_err(|e| AppError::Internal(format!("Failed to encode notification: {}", e)))?;
+
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(PROPOSAL_EVENTS_CHANNEL)
+        .bind(payload)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
@@ -1,25 +1,27 @@
-use crate::db::DbPool;
+use crate::db::{DbPool, push_keyset_pagination};
 use crate::errors::{AppError, AppResult};
 use crate::models::{
-    CreateProposal, Proposal, ProposalApproval, ProposalStatus, UpdateProposalStatus,
+    CreateProposal, Cursor, InboxAction, InboxItem, Page, PageRequest, Proposal, ProposalApproval,
+    ProposalFilter, ProposalStatus, UpdateProposalStatus,
 };
 use chrono::{DateTime, Utc};
-use sqlx::Row;
+use sqlx::{PgConnection, Postgres, QueryBuilder, Row};
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn create_proposal(
-    pool: &DbPool,
+    conn: &mut PgConnection,
     proposal_data: CreateProposal,
     multisig_id: i64,
     created_by: i64,
 ) -> AppResult<Proposal> {
     let row = sqlx::query(
         r#"
-        INSERT INTO proposals (multisig_id, title, description, status, created_by, transaction_data)
-        VALUES ($1, $2, $3, $4, $5, $6)
+        INSERT INTO proposals (multisig_id, title, description, status, created_by, transaction_data, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
         RETURNING id, multisig_id, title, description, status, created_by, 
                  created_at::TIMESTAMPTZ as created_at, 
                  executed_at::TIMESTAMPTZ as executed_at, 
-                 transaction_data
+                 transaction_data, expires_at
         "#,
     )
     .bind(multisig_id)
@@ -28,6 +30,7 @@ pub async fn create_proposal(
     .bind(ProposalStatus::Draft as ProposalStatus)
     .bind(created_by)
     .bind(&proposal_data.transaction_data)
+    .bind(proposal_data.expires_at)
     .map(|row: sqlx::postgres::PgRow| {
         (
             row.get::<i64, _>("id"),
@@ -39,23 +42,25 @@ pub async fn create_proposal(
             row.get::<DateTime<Utc>, _>("created_at"),
             row.get::<Option<DateTime<Utc>>, _>("executed_at"),
             row.get::<Option<String>, _>("transaction_data"),
+            row.get::<Option<DateTime<Utc>>, _>("expires_at"),
         )
     })
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
     .await?;
 
     Ok(Proposal::from_db(
-        row.0, row.1, row.2, row.3, row.4, row.5, row.6, row.7, row.8,
+        row.0, row.1, row.2, row.3, row.4, row.5, row.6, row.7, row.8, row.9,
     ))
 }
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn find_proposal_by_id(pool: &DbPool, proposal_id: i64) -> AppResult<Option<Proposal>> {
     let row = sqlx::query(
         r#"
         SELECT id, multisig_id, title, description, status, created_by, 
                created_at::TIMESTAMPTZ as created_at, 
                executed_at::TIMESTAMPTZ as executed_at, 
-               transaction_data
+               transaction_data, expires_at
         FROM proposals
         WHERE id = $1
         "#,
@@ -72,27 +77,34 @@ pub async fn find_proposal_by_id(pool: &DbPool, proposal_id: i64) -> AppResult<O
             row.get::<DateTime<Utc>, _>("created_at"),
             row.get::<Option<DateTime<Utc>>, _>("executed_at"),
             row.get::<Option<String>, _>("transaction_data"),
+            row.get::<Option<DateTime<Utc>>, _>("expires_at"),
         )
     })
     .fetch_optional(pool)
     .await?;
 
-    Ok(row.map(|r| Proposal::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7, r.8)))
+    Ok(row.map(|r| Proposal::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7, r.8, r.9)))
 }
 
-pub async fn list_multisig_proposals(pool: &DbPool, multisig_id: i64) -> AppResult<Vec<Proposal>> {
-    let rows = sqlx::query(
+// Approvals take this lock so that counting against the threshold and the status change that
+// follows cannot interleave with another approval, a rejection or expiry.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_proposal_for_update(
+    conn: &mut PgConnection,
+    proposal_id: i64,
+) -> AppResult<Option<Proposal>> {
+    let row = sqlx::query(
         r#"
-        SELECT id, multisig_id, title, description, status, created_by, 
-               created_at::TIMESTAMPTZ as created_at, 
-               executed_at::TIMESTAMPTZ as executed_at, 
-               transaction_data
+        SELECT id, multisig_id, title, description, status, created_by,
+               created_at::TIMESTAMPTZ as created_at,
+               executed_at::TIMESTAMPTZ as executed_at,
+               transaction_data, expires_at
         FROM proposals
-        WHERE multisig_id = $1
-        ORDER BY created_at DESC
+        WHERE id = $1
+        FOR UPDATE
         "#,
     )
-    .bind(multisig_id)
+    .bind(proposal_id)
     .map(|row: sqlx::postgres::PgRow| {
         (
             row.get::<i64, _>("id"),
@@ -104,32 +116,177 @@ pub async fn list_multisig_proposals(pool: &DbPool, multisig_id: i64) -> AppResu
             row.get::<DateTime<Utc>, _>("created_at"),
             row.get::<Option<DateTime<Utc>>, _>("executed_at"),
             row.get::<Option<String>, _>("transaction_data"),
+            row.get::<Option<DateTime<Utc>>, _>("expires_at"),
         )
     })
-    .fetch_all(pool)
+    .fetch_optional(&mut *conn)
     .await?;
 
+    Ok(row.map(|r| Proposal::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7, r.8, r.9)))
+}
+
+fn push_proposal_filters(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    multisig_id: i64,
+    filter: &ProposalFilter,
+) {
+    builder.push(" WHERE multisig_id = ").push_bind(multisig_id);
+
+    if let Some(status) = filter.status {
+        builder.push(" AND status = ").push_bind(status);
+    }
+
+    if let Some(created_by) = filter.created_by {
+        builder.push(" AND created_by = ").push_bind(created_by);
+    }
+
+    if let Some(created_after) = filter.created_after {
+        builder.push(" AND created_at >= ").push_bind(created_after);
+    }
+
+    if let Some(created_before) = filter.created_before {
+        builder.push(" AND created_at < ").push_bind(created_before);
+    }
+
+    if let Some(user_id) = filter.awaiting_approval_by {
+        builder
+            .push(" AND status = ")
+            .push_bind(ProposalStatus::Active)
+            .push(
+                " AND NOT EXISTS (SELECT 1 FROM proposal_approvals pa WHERE pa.proposal_id = proposals.id AND pa.user_id = ",
+            )
+            .push_bind(user_id)
+            .push(")");
+    }
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_multisig_proposals(
+    pool: &DbPool,
+    multisig_id: i64,
+    filter: &ProposalFilter,
+    page: &PageRequest,
+) -> AppResult<Page<Proposal>> {
+    let mut count_builder =
+        QueryBuilder::<Postgres>::new("SELECT COUNT(*) as count FROM proposals");
+    push_proposal_filters(&mut count_builder, multisig_id, filter);
+
+    let total_count = count_builder
+        .build()
+        .map(|row: sqlx::postgres::PgRow| row.get::<i64, _>("count"))
+        .fetch_one(pool)
+        .await?;
+
+    let mut builder = QueryBuilder::<Postgres>::new(
+        r#"
+        SELECT id, multisig_id, title, description, status, created_by, 
+               created_at::TIMESTAMPTZ as created_at, 
+               executed_at::TIMESTAMPTZ as executed_at, 
+               transaction_data, expires_at
+        FROM proposals"#,
+    );
+    push_proposal_filters(&mut builder, multisig_id, filter);
+
+    push_keyset_pagination(&mut builder, page);
+
+    let rows = builder
+        .build()
+        .map(|row: sqlx::postgres::PgRow| {
+            (
+                row.get::<i64, _>("id"),
+                row.get::<i64, _>("multisig_id"),
+                row.get::<String, _>("title"),
+                row.get::<Option<String>, _>("description"),
+                row.get::<ProposalStatus, _>("status"),
+                row.get::<i64, _>("created_by"),
+                row.get::<DateTime<Utc>, _>("created_at"),
+                row.get::<Option<DateTime<Utc>>, _>("executed_at"),
+                row.get::<Option<String>, _>("transaction_data"),
+                row.get::<Option<DateTime<Utc>>, _>("expires_at"),
+            )
+        })
+        .fetch_all(pool)
+        .await?;
+
     let proposals = rows
         .into_iter()
-        .map(|r| Proposal::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7, r.8))
+        .map(|r| Proposal::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7, r.8, r.9))
         .collect();
 
-    Ok(proposals)
+    Ok(Page::from_rows(proposals, page.limit, total_count, |p| {
+        Cursor::new(p.created_at, p.id)
+    }))
 }
 
+// Owner lookup uses `@>` rather than `= ANY(owners)` so the GIN index on owners applies.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_user_inbox(pool: &DbPool, user_id: i64) -> AppResult<Vec<InboxItem>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT p.id, p.multisig_id, m.name as multisig_name, p.title, p.status, p.created_by,
+               p.created_at::TIMESTAMPTZ as created_at,
+               p.expires_at,
+               m.threshold,
+               (SELECT COUNT(*) FROM proposal_approvals pa WHERE pa.proposal_id = p.id) as approval_count
+        FROM proposals p
+        JOIN multisigs m ON m.id = p.multisig_id
+        WHERE m.owners @> ARRAY[$1]::BIGINT[]
+          AND (
+                (p.status = 'active'
+                 AND (p.expires_at IS NULL OR p.expires_at > NOW())
+                 AND NOT EXISTS (
+                     SELECT 1 FROM proposal_approvals pa
+                     WHERE pa.proposal_id = p.id AND pa.user_id = $1
+                 ))
+             OR p.status = 'approved'
+          )
+        ORDER BY p.expires_at ASC NULLS LAST, p.created_at DESC, p.id DESC
+        "#,
+    )
+    .bind(user_id)
+    .map(|row: sqlx::postgres::PgRow| {
+        let status = row.get::<ProposalStatus, _>("status");
+        let action = if status == ProposalStatus::Approved {
+            InboxAction::Execute
+        } else {
+            InboxAction::Approve
+        };
+
+        InboxItem {
+            proposal_id: row.get::<i64, _>("id"),
+            multisig_id: row.get::<i64, _>("multisig_id"),
+            multisig_name: row.get::<String, _>("multisig_name"),
+            title: row.get::<String, _>("title"),
+            status,
+            created_by: row.get::<i64, _>("created_by"),
+            created_at: row.get::<DateTime<Utc>, _>("created_at"),
+            expires_at: row.get::<Option<DateTime<Utc>>, _>("expires_at"),
+            approval_count: row.get::<i64, _>("approval_count"),
+            threshold: row.get::<i32, _>("threshold"),
+            action,
+        }
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+// Locks the proposal row for the rest of the transaction and returns the status it moved from.
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn update_proposal_status(
-    pool: &DbPool,
+    conn: &mut PgConnection,
     proposal_id: i64,
     status_update: UpdateProposalStatus,
-) -> AppResult<()> {
+) -> AppResult<ProposalStatus> {
     let current_status = sqlx::query(
         r#"
-        SELECT status FROM proposals WHERE id = $1
+        SELECT status FROM proposals WHERE id = $1 FOR UPDATE
         "#,
     )
     .bind(proposal_id)
     .map(|row: sqlx::postgres::PgRow| row.get::<ProposalStatus, _>("status"))
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await?
     .ok_or_else(|| AppError::NotFound("Proposal not found".to_string()))?;
 
@@ -150,14 +307,15 @@ pub async fn update_proposal_status(
     .bind(proposal_id)
     .bind(status_update.status as ProposalStatus)
     .bind(status_update.executed_at)
-    .execute(pool)
+    .execute(&mut *conn)
     .await?;
 
-    Ok(())
+    Ok(current_status)
 }
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn approve_proposal(
-    pool: &DbPool,
+    conn: &mut PgConnection,
     proposal_id: i64,
     user_id: i64,
 ) -> AppResult<ProposalApproval> {
@@ -169,7 +327,7 @@ pub async fn approve_proposal(
     )
     .bind(proposal_id)
     .bind(user_id)
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await?;
 
     if existing.is_some() {
@@ -195,12 +353,39 @@ pub async fn approve_proposal(
             row.get::<DateTime<Utc>, _>("approved_at"),
         )
     })
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
     .await?;
 
     Ok(ProposalApproval::from_db(row.0, row.1, row.2, row.3))
 }
 
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn revoke_proposal_approval(
+    conn: &mut PgConnection,
+    proposal_id: i64,
+    user_id: i64,
+) -> AppResult<()> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM proposal_approvals
+        WHERE proposal_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(proposal_id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(
+            "User has not approved this proposal".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn get_proposal_approvals(
     pool: &DbPool,
     proposal_id: i64,
@@ -233,7 +418,8 @@ pub async fn get_proposal_approvals(
     Ok(approvals)
 }
 
-pub async fn count_proposal_approvals(pool: &DbPool, proposal_id: i64) -> AppResult<i64> {
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn count_proposal_approvals(conn: &mut PgConnection, proposal_id: i64) -> AppResult<i64> {
     let count = sqlx::query(
         r#"
         SELECT COUNT(*) as count
@@ -243,7 +429,7 @@ pub async fn count_proposal_approvals(pool: &DbPool, proposal_id: i64) -> AppRes
     )
     .bind(proposal_id)
     .map(|row: sqlx::postgres::PgRow| row.get::<i64, _>("count"))
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
     .await?;
 
     Ok(count)
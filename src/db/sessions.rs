@@ -0,0 +1,183 @@
+use crate::db::DbPool;
+use crate::errors::AppResult;
+use crate::models::{Session, UpdateUserLogin};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, Row};
+
+type SessionRow = (
+    i64,
+    i64,
+    Option<String>,
+    Option<String>,
+    DateTime<Utc>,
+    DateTime<Utc>,
+    Option<DateTime<Utc>>,
+    Option<DateTime<Utc>>,
+);
+
+fn session_row(row: sqlx::postgres::PgRow) -> SessionRow {
+    (
+        row.get::<i64, _>("id"),
+        row.get::<i64, _>("user_id"),
+        row.get::<Option<String>, _>("user_agent"),
+        row.get::<Option<String>, _>("ip_address"),
+        row.get::<DateTime<Utc>, _>("created_at"),
+        row.get::<DateTime<Utc>, _>("last_seen_at"),
+        row.get::<Option<DateTime<Utc>>, _>("revoked_at"),
+        row.get::<Option<DateTime<Utc>>, _>("step_up_at"),
+    )
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn create_session(
+    conn: &mut PgConnection,
+    user_id: i64,
+    login_data: &UpdateUserLogin,
+) -> AppResult<Session> {
+    let row = sqlx::query(
+        r#"
+        INSERT INTO sessions (user_id, user_agent, ip_address, created_at, last_seen_at)
+        VALUES ($1, $2, $3, $4, $4)
+        RETURNING id, user_id, user_agent, ip_address, created_at, last_seen_at, revoked_at, step_up_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(&login_data.user_agent)
+    .bind(&login_data.ip_address)
+    .bind(login_data.last_login_at)
+    .map(session_row)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Session::from_db(
+        row.0, row.1, row.2, row.3, row.4, row.5, row.6, row.7,
+    ))
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_session_by_id(pool: &DbPool, session_id: i64) -> AppResult<Option<Session>> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, user_id, user_agent, ip_address, created_at, last_seen_at, revoked_at, step_up_at
+        FROM sessions
+        WHERE id = $1
+        "#,
+    )
+    .bind(session_id)
+    .map(session_row)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| Session::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7)))
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_user_sessions(pool: &DbPool, user_id: i64) -> AppResult<Vec<Session>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, user_id, user_agent, ip_address, created_at, last_seen_at, revoked_at, step_up_at
+        FROM sessions
+        WHERE user_id = $1 AND revoked_at IS NULL
+        ORDER BY last_seen_at DESC, id DESC
+        "#,
+    )
+    .bind(user_id)
+    .map(session_row)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| Session::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7))
+        .collect())
+}
+
+// Revoking a session also revokes its refresh tokens; access tokens are rejected via the session.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn revoke_session(conn: &mut PgConnection, session_id: i64) -> AppResult<()> {
+    sqlx::query("UPDATE sessions SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL")
+        .bind(session_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE refresh_tokens
+        SET revoked_at = NOW()
+        WHERE session_id = $1 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(session_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// `keep_session` leaves one session signed in, e.g. the one that just changed the password.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn revoke_user_sessions(
+    conn: &mut PgConnection,
+    user_id: i64,
+    keep_session: Option<i64>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE sessions
+        SET revoked_at = NOW()
+        WHERE user_id = $1 AND revoked_at IS NULL AND id IS DISTINCT FROM $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(keep_session)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE refresh_tokens
+        SET revoked_at = NOW()
+        WHERE user_id = $1 AND revoked_at IS NULL AND session_id IS DISTINCT FROM $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(keep_session)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// Returns whether the session is still active. `last_seen_at` is only written once a minute
+// so authenticated requests do not each cause a row update.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn touch_session(pool: &DbPool, session_id: i64) -> AppResult<bool> {
+    sqlx::query(
+        r#"
+        UPDATE sessions
+        SET last_seen_at = NOW()
+        WHERE id = $1 AND revoked_at IS NULL AND last_seen_at < NOW() - INTERVAL '1 minute'
+        "#,
+    )
+    .bind(session_id)
+    .execute(pool)
+    .await?;
+
+    let active = sqlx::query("SELECT revoked_at IS NULL AS active FROM sessions WHERE id = $1")
+        .bind(session_id)
+        .map(|row: sqlx::postgres::PgRow| row.get::<bool, _>("active"))
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(active.unwrap_or(false))
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn record_session_step_up(pool: &DbPool, session_id: i64) -> AppResult<()> {
+    sqlx::query("UPDATE sessions SET step_up_at = NOW() WHERE id = $1 AND revoked_at IS NULL")
+        .bind(session_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
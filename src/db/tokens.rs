@@ -0,0 +1,182 @@
+use crate::db::DbPool;
+use crate::errors::AppResult;
+use crate::models::{CreateRefreshToken, RefreshToken};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, Row};
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn create_refresh_token(
+    conn: &mut PgConnection,
+    token_data: CreateRefreshToken,
+) -> AppResult<i64> {
+    let id = sqlx::query(
+        r#"
+        INSERT INTO refresh_tokens
+            (user_id, family_id, parent_id, token_hash, access_jti, expires_at, session_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        "#,
+    )
+    .bind(token_data.user_id)
+    .bind(&token_data.family_id)
+    .bind(token_data.parent_id)
+    .bind(&token_data.token_hash)
+    .bind(&token_data.access_jti)
+    .bind(token_data.expires_at)
+    .bind(token_data.session_id)
+    .map(|row: sqlx::postgres::PgRow| row.get::<i64, _>("id"))
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(id)
+}
+
+// Locks the row so two concurrent refreshes with the same token cannot both succeed.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_refresh_token_for_update(
+    conn: &mut PgConnection,
+    token_hash: &str,
+) -> AppResult<Option<RefreshToken>> {
+    let token = sqlx::query(
+        r#"
+        SELECT id, user_id, family_id, session_id, expires_at, used_at, revoked_at
+        FROM refresh_tokens
+        WHERE token_hash = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(token_hash)
+    .map(|row: sqlx::postgres::PgRow| RefreshToken {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        family_id: row.get("family_id"),
+        session_id: row.get("session_id"),
+        expires_at: row.get("expires_at"),
+        used_at: row.get("used_at"),
+        revoked_at: row.get("revoked_at"),
+    })
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(token)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn mark_refresh_token_used(conn: &mut PgConnection, token_id: i64) -> AppResult<()> {
+    sqlx::query("UPDATE refresh_tokens SET used_at = NOW() WHERE id = $1")
+        .bind(token_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_token_family_by_access_jti(
+    conn: &mut PgConnection,
+    access_jti: &str,
+) -> AppResult<Option<String>> {
+    let family_id = sqlx::query("SELECT family_id FROM refresh_tokens WHERE access_jti = $1")
+        .bind(access_jti)
+        .map(|row: sqlx::postgres::PgRow| row.get::<String, _>("family_id"))
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    Ok(family_id)
+}
+
+// Revokes every refresh token in the family and blocks the access tokens issued with them.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn revoke_token_family(
+    conn: &mut PgConnection,
+    family_id: &str,
+    access_expires_at: DateTime<Utc>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE refresh_tokens
+        SET revoked_at = NOW()
+        WHERE family_id = $1 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(family_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO revoked_tokens (jti, user_id, expires_at)
+        SELECT access_jti, user_id, $2
+        FROM refresh_tokens
+        WHERE family_id = $1
+        ON CONFLICT (jti) DO NOTHING
+        "#,
+    )
+    .bind(family_id)
+    .bind(access_expires_at)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn revoke_access_token(
+    conn: &mut PgConnection,
+    jti: &str,
+    user_id: i64,
+    expires_at: DateTime<Utc>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO revoked_tokens (jti, user_id, expires_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (jti) DO NOTHING
+        "#,
+    )
+    .bind(jti)
+    .bind(user_id)
+    .bind(expires_at)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn is_token_revoked(pool: &DbPool, jti: &str) -> AppResult<bool> {
+    let revoked = sqlx::query("SELECT EXISTS(SELECT 1 FROM revoked_tokens WHERE jti = $1)")
+        .bind(jti)
+        .map(|row: sqlx::postgres::PgRow| row.get::<bool, _>(0))
+        .fetch_one(pool)
+        .await?;
+
+    Ok(revoked)
+}
+
+// Used tokens are kept until they expire so that replaying one still revokes its family.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn delete_expired_refresh_tokens(pool: &DbPool) -> AppResult<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM refresh_tokens WHERE expires_at < NOW()
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn delete_expired_revoked_tokens(pool: &DbPool) -> AppResult<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM revoked_tokens WHERE expires_at < NOW()
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
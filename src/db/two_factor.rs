@@ -0,0 +1,265 @@
+use crate::db::DbPool;
+use crate::errors::AppResult;
+use crate::models::{LoginChallenge, UserTotp};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, Row};
+
+fn user_totp_row(row: sqlx::postgres::PgRow) -> UserTotp {
+    UserTotp::from_db(
+        row.get::<i64, _>("user_id"),
+        row.get::<String, _>("secret"),
+        row.get::<Option<DateTime<Utc>>, _>("confirmed_at"),
+        row.get::<Option<i64>, _>("last_used_step"),
+    )
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_user_totp(pool: &DbPool, user_id: i64) -> AppResult<Option<UserTotp>> {
+    let totp = sqlx::query(
+        r#"
+        SELECT user_id, secret, confirmed_at, last_used_step
+        FROM user_totp
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .map(user_totp_row)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(totp)
+}
+
+// Locks the row so concurrent requests cannot both accept the same time step.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_user_totp_for_update(
+    conn: &mut PgConnection,
+    user_id: i64,
+) -> AppResult<Option<UserTotp>> {
+    let totp = sqlx::query(
+        r#"
+        SELECT user_id, secret, confirmed_at, last_used_step
+        FROM user_totp
+        WHERE user_id = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(user_id)
+    .map(user_totp_row)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(totp)
+}
+
+// Starts (or restarts) an enrollment. A confirmed secret is never overwritten.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn upsert_pending_totp(pool: &DbPool, user_id: i64, secret: &str) -> AppResult<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO user_totp (user_id, secret)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE
+        SET secret = EXCLUDED.secret, last_used_step = NULL, created_at = NOW()
+        WHERE user_totp.confirmed_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .bind(secret)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn update_totp_last_used_step(
+    conn: &mut PgConnection,
+    user_id: i64,
+    step: i64,
+) -> AppResult<()> {
+    sqlx::query("UPDATE user_totp SET last_used_step = $2 WHERE user_id = $1")
+        .bind(user_id)
+        .bind(step)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn confirm_user_totp(conn: &mut PgConnection, user_id: i64) -> AppResult<()> {
+    sqlx::query("UPDATE user_totp SET confirmed_at = NOW() WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn delete_user_totp(conn: &mut PgConnection, user_id: i64) -> AppResult<()> {
+    sqlx::query("DELETE FROM user_totp WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn replace_recovery_codes(
+    conn: &mut PgConnection,
+    user_id: i64,
+    code_hashes: &[String],
+) -> AppResult<()> {
+    sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO recovery_codes (user_id, code_hash)
+        SELECT $1, UNNEST($2::TEXT[])
+        "#,
+    )
+    .bind(user_id)
+    .bind(code_hashes)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// Marks a matching unused code as spent; returns whether one was found.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn use_recovery_code(
+    conn: &mut PgConnection,
+    user_id: i64,
+    code_hash: &str,
+) -> AppResult<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE recovery_codes
+        SET used_at = NOW()
+        WHERE id = (
+            SELECT id FROM recovery_codes
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            LIMIT 1
+            FOR UPDATE
+        )
+        "#,
+    )
+    .bind(user_id)
+    .bind(code_hash)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn count_unused_recovery_codes(pool: &DbPool, user_id: i64) -> AppResult<i64> {
+    let count = sqlx::query(
+        "SELECT COUNT(*) AS count FROM recovery_codes WHERE user_id = $1 AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .map(|row: sqlx::postgres::PgRow| row.get::<i64, _>("count"))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn create_login_challenge(
+    pool: &DbPool,
+    user_id: i64,
+    token_hash: &str,
+    expires_at: DateTime<Utc>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO login_challenges (user_id, token_hash, expires_at)
+        VALUES ($1, $2, $3)
+        "#,
+    )
+    .bind(user_id)
+    .bind(token_hash)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_login_challenge_for_update(
+    conn: &mut PgConnection,
+    token_hash: &str,
+) -> AppResult<Option<LoginChallenge>> {
+    let challenge = sqlx::query(
+        r#"
+        SELECT id, user_id, attempts, expires_at, consumed_at
+        FROM login_challenges
+        WHERE token_hash = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(token_hash)
+    .map(|row: sqlx::postgres::PgRow| {
+        LoginChallenge::from_db(
+            row.get::<i64, _>("id"),
+            row.get::<i64, _>("user_id"),
+            row.get::<i32, _>("attempts"),
+            row.get::<DateTime<Utc>, _>("expires_at"),
+            row.get::<Option<DateTime<Utc>>, _>("consumed_at"),
+        )
+    })
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(challenge)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn record_login_challenge_attempt(
+    conn: &mut PgConnection,
+    challenge_id: i64,
+) -> AppResult<()> {
+    sqlx::query("UPDATE login_challenges SET attempts = attempts + 1 WHERE id = $1")
+        .bind(challenge_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn consume_login_challenge(conn: &mut PgConnection, challenge_id: i64) -> AppResult<()> {
+    sqlx::query("UPDATE login_challenges SET consumed_at = NOW() WHERE id = $1")
+        .bind(challenge_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn delete_expired_login_challenges(pool: &DbPool) -> AppResult<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM login_challenges WHERE expires_at < NOW()
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,104 @@
+use crate::db::DbPool;
+use crate::errors::AppResult;
+use crate::models::{UserToken, UserTokenPurpose};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, Row};
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn create_user_token(
+    conn: &mut PgConnection,
+    user_id: i64,
+    purpose: UserTokenPurpose,
+    token_hash: &str,
+    expires_at: DateTime<Utc>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(user_id)
+    .bind(purpose)
+    .bind(token_hash)
+    .bind(expires_at)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// Only the most recently issued token of a purpose stays usable.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn invalidate_user_tokens(
+    conn: &mut PgConnection,
+    user_id: i64,
+    purpose: UserTokenPurpose,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE user_tokens
+        SET used_at = NOW()
+        WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .bind(purpose)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_user_token_for_update(
+    conn: &mut PgConnection,
+    token_hash: &str,
+    purpose: UserTokenPurpose,
+) -> AppResult<Option<UserToken>> {
+    let token = sqlx::query(
+        r#"
+        SELECT id, user_id, expires_at, used_at
+        FROM user_tokens
+        WHERE token_hash = $1 AND purpose = $2
+        FOR UPDATE
+        "#,
+    )
+    .bind(token_hash)
+    .bind(purpose)
+    .map(|row: sqlx::postgres::PgRow| {
+        UserToken::from_db(
+            row.get::<i64, _>("id"),
+            row.get::<i64, _>("user_id"),
+            row.get::<DateTime<Utc>, _>("expires_at"),
+            row.get::<Option<DateTime<Utc>>, _>("used_at"),
+        )
+    })
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(token)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn mark_user_token_used(conn: &mut PgConnection, token_id: i64) -> AppResult<()> {
+    sqlx::query("UPDATE user_tokens SET used_at = NOW() WHERE id = $1")
+        .bind(token_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn delete_expired_user_tokens(pool: &DbPool) -> AppResult<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM user_tokens WHERE expires_at < NOW()
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
@@ -1,17 +1,19 @@
-use crate::db::DbPool;
+use crate::db::{DbPool, create_session};
 use crate::errors::AppResult;
-use crate::models::{CreateUser, UpdateUserLogin, User};
+use crate::models::{CreateUser, Session, UpdateUserLogin, User};
 use chrono::{DateTime, Utc};
-use sqlx::Row;
+use sqlx::{PgConnection, Row};
 
-pub async fn create_user(pool: &DbPool, user_data: CreateUser) -> AppResult<User> {
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn create_user(conn: &mut PgConnection, user_data: CreateUser) -> AppResult<User> {
     let row = sqlx::query(
         r#"
         INSERT INTO users (email, password_hash)
         VALUES ($1, $2)
         RETURNING id, email, 
                  created_at::TIMESTAMPTZ as created_at,
-                 last_login_at::TIMESTAMPTZ as last_login_at
+                 last_login_at::TIMESTAMPTZ as last_login_at,
+               email_verified_at
         "#,
     )
     .bind(&user_data.email)
@@ -22,20 +24,23 @@ pub async fn create_user(pool: &DbPool, user_data: CreateUser) -> AppResult<User
             row.get::<String, _>("email"),
             row.get::<DateTime<Utc>, _>("created_at"),
             row.get::<Option<DateTime<Utc>>, _>("last_login_at"),
+            row.get::<Option<DateTime<Utc>>, _>("email_verified_at"),
         )
     })
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
     .await?;
 
-    Ok(User::from_db(row.0, row.1, row.2, row.3))
+    Ok(User::from_db(row.0, row.1, row.2, row.3, row.4))
 }
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn find_user_by_email(pool: &DbPool, email: &str) -> AppResult<Option<User>> {
     let row = sqlx::query(
         r#"
         SELECT id, email, 
                created_at::TIMESTAMPTZ as created_at,
-               last_login_at::TIMESTAMPTZ as last_login_at
+               last_login_at::TIMESTAMPTZ as last_login_at,
+               email_verified_at
         FROM users
         WHERE email = $1
         "#,
@@ -47,20 +52,23 @@ pub async fn find_user_by_email(pool: &DbPool, email: &str) -> AppResult<Option<
             row.get::<String, _>("email"),
             row.get::<DateTime<Utc>, _>("created_at"),
             row.get::<Option<DateTime<Utc>>, _>("last_login_at"),
+            row.get::<Option<DateTime<Utc>>, _>("email_verified_at"),
         )
     })
     .fetch_optional(pool)
     .await?;
 
-    Ok(row.map(|r| User::from_db(r.0, r.1, r.2, r.3)))
+    Ok(row.map(|r| User::from_db(r.0, r.1, r.2, r.3, r.4)))
 }
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn find_user_by_id(pool: &DbPool, user_id: i64) -> AppResult<Option<User>> {
     let row = sqlx::query(
         r#"
         SELECT id, email,
                created_at::TIMESTAMPTZ as created_at,
-               last_login_at::TIMESTAMPTZ as last_login_at
+               last_login_at::TIMESTAMPTZ as last_login_at,
+               email_verified_at
         FROM users
         WHERE id = $1
         "#,
@@ -72,19 +80,22 @@ pub async fn find_user_by_id(pool: &DbPool, user_id: i64) -> AppResult<Option<Us
             row.get::<String, _>("email"),
             row.get::<DateTime<Utc>, _>("created_at"),
             row.get::<Option<DateTime<Utc>>, _>("last_login_at"),
+            row.get::<Option<DateTime<Utc>>, _>("email_verified_at"),
         )
     })
     .fetch_optional(pool)
     .await?;
 
-    Ok(row.map(|r| User::from_db(r.0, r.1, r.2, r.3)))
+    Ok(row.map(|r| User::from_db(r.0, r.1, r.2, r.3, r.4)))
 }
 
+// Each login opens a new session; the caller issues tokens bound to it in the same transaction.
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn update_user_login(
-    pool: &DbPool,
+    conn: &mut PgConnection,
     user_id: i64,
     login_data: UpdateUserLogin,
-) -> AppResult<()> {
+) -> AppResult<Session> {
     sqlx::query(
         r#"
         UPDATE users
@@ -94,12 +105,40 @@ pub async fn update_user_login(
     )
     .bind(user_id)
     .bind(login_data.last_login_at)
-    .execute(pool)
+    .execute(&mut *conn)
+    .await?;
+
+    create_session(conn, user_id, &login_data).await
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn mark_email_verified(conn: &mut PgConnection, user_id: i64) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW()) WHERE id = $1",
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
     .await?;
 
     Ok(())
 }
 
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn update_user_password(
+    conn: &mut PgConnection,
+    user_id: i64,
+    password_hash: &str,
+) -> AppResult<()> {
+    sqlx::query("UPDATE users SET password_hash = $2 WHERE id = $1")
+        .bind(user_id)
+        .bind(password_hash)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn get_user_password_hash(
     pool: &DbPool,
     email: &str,
@@ -117,3 +156,38 @@ pub async fn get_user_password_hash(
 
     Ok(row.map(|r| (r.id, r.password_hash)))
 }
+
+// Locks the row so concurrent password changes for the same user are applied one at a time.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn get_user_credentials_for_update(
+    conn: &mut PgConnection,
+    user_id: i64,
+) -> AppResult<Option<(String, String)>> {
+    let row = sqlx::query("SELECT email, password_hash FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .map(|row: sqlx::postgres::PgRow| {
+            (
+                row.get::<String, _>("email"),
+                row.get::<String, _>("password_hash"),
+            )
+        })
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    Ok(row)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn is_user_admin(pool: &DbPool, user_id: i64) -> AppResult<bool> {
+    let is_admin = sqlx::query(
+        r#"
+        SELECT is_admin FROM users WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .map(|row: sqlx::postgres::PgRow| row.get::<bool, _>("is_admin"))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(is_admin.unwrap_or(false))
+}
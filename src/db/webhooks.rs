@@ -0,0 +1,316 @@
+use crate::db::DbPool;
+use crate::errors::AppResult;
+use crate::models::{
+    ClaimedWebhookDelivery, CreateWebhook, DeliveryOutcome, Webhook, WebhookDelivery,
+    WebhookDeliveryAttempt, WebhookDeliveryStatus,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, Row};
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn create_webhook(
+    pool: &DbPool,
+    webhook_data: CreateWebhook,
+    multisig_id: i64,
+    secret: &str,
+    created_by: i64,
+) -> AppResult<Webhook> {
+    let event_types: Vec<String> = webhook_data
+        .event_types
+        .iter()
+        .map(|e| e.as_str().to_string())
+        .collect();
+
+    let row = sqlx::query(
+        r#"
+        INSERT INTO webhooks (multisig_id, url, secret, event_types, created_by)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, multisig_id, url, secret, event_types, is_active, created_by, created_at
+        "#,
+    )
+    .bind(multisig_id)
+    .bind(&webhook_data.url)
+    .bind(secret)
+    .bind(&event_types)
+    .bind(created_by)
+    .map(|row: sqlx::postgres::PgRow| {
+        (
+            row.get::<i64, _>("id"),
+            row.get::<i64, _>("multisig_id"),
+            row.get::<String, _>("url"),
+            row.get::<String, _>("secret"),
+            row.get::<Vec<String>, _>("event_types"),
+            row.get::<bool, _>("is_active"),
+            row.get::<i64, _>("created_by"),
+            row.get::<DateTime<Utc>, _>("created_at"),
+        )
+    })
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Webhook::from_db(
+        row.0, row.1, row.2, row.3, row.4, row.5, row.6, row.7,
+    ))
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn find_webhook_by_id(pool: &DbPool, webhook_id: i64) -> AppResult<Option<Webhook>> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, multisig_id, url, secret, event_types, is_active, created_by, created_at
+        FROM webhooks
+        WHERE id = $1
+        "#,
+    )
+    .bind(webhook_id)
+    .map(|row: sqlx::postgres::PgRow| {
+        (
+            row.get::<i64, _>("id"),
+            row.get::<i64, _>("multisig_id"),
+            row.get::<String, _>("url"),
+            row.get::<String, _>("secret"),
+            row.get::<Vec<String>, _>("event_types"),
+            row.get::<bool, _>("is_active"),
+            row.get::<i64, _>("created_by"),
+            row.get::<DateTime<Utc>, _>("created_at"),
+        )
+    })
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| Webhook::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7)))
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_multisig_webhooks(pool: &DbPool, multisig_id: i64) -> AppResult<Vec<Webhook>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, multisig_id, url, secret, event_types, is_active, created_by, created_at
+        FROM webhooks
+        WHERE multisig_id = $1
+        ORDER BY created_at ASC, id ASC
+        "#,
+    )
+    .bind(multisig_id)
+    .map(|row: sqlx::postgres::PgRow| {
+        (
+            row.get::<i64, _>("id"),
+            row.get::<i64, _>("multisig_id"),
+            row.get::<String, _>("url"),
+            row.get::<String, _>("secret"),
+            row.get::<Vec<String>, _>("event_types"),
+            row.get::<bool, _>("is_active"),
+            row.get::<i64, _>("created_by"),
+            row.get::<DateTime<Utc>, _>("created_at"),
+        )
+    })
+    .fetch_all(pool)
+    .await?;
+
+    let webhooks = rows
+        .into_iter()
+        .map(|r| Webhook::from_db(r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7))
+        .collect();
+
+    Ok(webhooks)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn delete_webhook(pool: &DbPool, webhook_id: i64) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        DELETE FROM webhooks WHERE id = $1
+        "#,
+    )
+    .bind(webhook_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Written inside the caller's transaction so a delivery exists if and only if the event committed.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn enqueue_webhook_deliveries(
+    conn: &mut PgConnection,
+    multisig_id: i64,
+    event_type: &str,
+    payload: &serde_json::Value,
+) -> AppResult<u64> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO webhook_deliveries (webhook_id, event_type, payload)
+        SELECT id, $2, $3
+        FROM webhooks
+        WHERE multisig_id = $1
+          AND is_active
+          AND (cardinality(event_types) = 0 OR $2 = ANY(event_types))
+        "#,
+    )
+    .bind(multisig_id)
+    .bind(event_type)
+    .bind(payload)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+// Claimed rows are leased by pushing `next_attempt_at` forward, so a crashed worker's deliveries
+// become due again once the lease expires.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn claim_due_webhook_deliveries(
+    pool: &DbPool,
+    limit: i64,
+    lease_seconds: i64,
+) -> AppResult<Vec<ClaimedWebhookDelivery>> {
+    let rows = sqlx::query(
+        r#"
+        WITH due AS (
+            SELECT id FROM webhook_deliveries
+            WHERE status = 'pending' AND next_attempt_at <= NOW()
+            ORDER BY next_attempt_at ASC
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        ),
+        claimed AS (
+            UPDATE webhook_deliveries d
+            SET next_attempt_at = NOW() + make_interval(secs => $2)
+            FROM due
+            WHERE d.id = due.id
+            RETURNING d.id, d.webhook_id, d.event_type, d.payload, d.attempts
+        )
+        SELECT claimed.id, claimed.webhook_id, claimed.event_type, claimed.payload, claimed.attempts,
+               w.url, w.secret
+        FROM claimed
+        JOIN webhooks w ON w.id = claimed.webhook_id
+        "#,
+    )
+    .bind(limit)
+    .bind(lease_seconds as f64)
+    .map(|row: sqlx::postgres::PgRow| ClaimedWebhookDelivery {
+        id: row.get::<i64, _>("id"),
+        webhook_id: row.get::<i64, _>("webhook_id"),
+        event_type: row.get::<String, _>("event_type"),
+        payload: row.get::<serde_json::Value, _>("payload"),
+        attempts: row.get::<i32, _>("attempts"),
+        url: row.get::<String, _>("url"),
+        secret: row.get::<String, _>("secret"),
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn record_webhook_attempt(
+    conn: &mut PgConnection,
+    delivery_id: i64,
+    outcome: &DeliveryOutcome,
+    status: WebhookDeliveryStatus,
+    next_attempt_at: DateTime<Utc>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO webhook_delivery_attempts (delivery_id, status_code, error, duration_ms)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(delivery_id)
+    .bind(outcome.status_code)
+    .bind(&outcome.error)
+    .bind(outcome.duration_ms)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE webhook_deliveries
+        SET status = $2,
+            attempts = attempts + 1,
+            next_attempt_at = $3,
+            last_status_code = $4,
+            last_error = $5,
+            delivered_at = CASE WHEN $2 = 'delivered'::webhook_delivery_status THEN NOW() ELSE NULL END
+        WHERE id = $1
+        "#,
+    )
+    .bind(delivery_id)
+    .bind(status)
+    .bind(next_attempt_at)
+    .bind(outcome.status_code)
+    .bind(&outcome.error)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_webhook_deliveries(
+    pool: &DbPool,
+    webhook_id: i64,
+    limit: i64,
+) -> AppResult<Vec<WebhookDelivery>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, webhook_id, event_type, payload, status, attempts, next_attempt_at,
+               last_status_code, last_error, created_at, delivered_at
+        FROM webhook_deliveries
+        WHERE webhook_id = $1
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(webhook_id)
+    .bind(limit)
+    .map(|row: sqlx::postgres::PgRow| WebhookDelivery {
+        id: row.get::<i64, _>("id"),
+        webhook_id: row.get::<i64, _>("webhook_id"),
+        event_type: row.get::<String, _>("event_type"),
+        payload: row.get::<serde_json::Value, _>("payload"),
+        status: row.get::<WebhookDeliveryStatus, _>("status"),
+        attempts: row.get::<i32, _>("attempts"),
+        next_attempt_at: row.get::<DateTime<Utc>, _>("next_attempt_at"),
+        last_status_code: row.get::<Option<i32>, _>("last_status_code"),
+        last_error: row.get::<Option<String>, _>("last_error"),
+        created_at: row.get::<DateTime<Utc>, _>("created_at"),
+        delivered_at: row.get::<Option<DateTime<Utc>>, _>("delivered_at"),
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_webhook_delivery_attempts(
+    pool: &DbPool,
+    webhook_id: i64,
+    delivery_id: i64,
+) -> AppResult<Vec<WebhookDeliveryAttempt>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT a.id, a.delivery_id, a.attempted_at, a.status_code, a.error, a.duration_ms
+        FROM webhook_delivery_attempts a
+        JOIN webhook_deliveries d ON d.id = a.delivery_id
+        WHERE a.delivery_id = $1 AND d.webhook_id = $2
+        ORDER BY a.attempted_at ASC, a.id ASC
+        "#,
+    )
+    .bind(delivery_id)
+    .bind(webhook_id)
+    .map(|row: sqlx::postgres::PgRow| WebhookDeliveryAttempt {
+        id: row.get::<i64, _>("id"),
+        delivery_id: row.get::<i64, _>("delivery_id"),
+        attempted_at: row.get::<DateTime<Utc>, _>("attempted_at"),
+        status_code: row.get::<Option<i32>, _>("status_code"),
+        error: row.get::<Option<String>, _>("error"),
+        duration_ms: row.get::<i64, _>("duration_ms"),
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
@@ -2,6 +2,8 @@ use actix_web::{HttpResponse, ResponseError};
 use serde::Serialize;
 use std::fmt;
 
+use crate::models::LoginThrottle;
+
 #[derive(Debug)]
 pub enum AppError {
     Database(sqlx::Error),
@@ -10,6 +12,7 @@ pub enum AppError {
     Authorization(String),
     NotFound(String),
     Conflict(String),
+    TooManyRequests(LoginThrottle),
     Internal(String),
 }
 
@@ -22,6 +25,12 @@ impl fmt::Display for AppError {
             AppError::Authorization(msg) => write!(f, "Authorization error: {}", msg),
             AppError::NotFound(msg) => write!(f, "Not found: {}", msg),
             AppError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            AppError::TooManyRequests(throttle) => write!(
+                f,
+                "{}; retry in {} seconds",
+                throttle.message(),
+                throttle.retry_after
+            ),
             AppError::Internal(msg) => write!(f, "Internal error: {}", msg),
         }
     }
@@ -33,18 +42,38 @@ impl std::error::Error for AppError {}
 struct ErrorResponse {
     error: String,
     code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
 }
 
 impl ResponseError for AppError {
     fn error_response(&self) -> HttpResponse {
         let (status, error_code) = match self {
-            AppError::Database(_) => (actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "database_error"),
-            AppError::Validation(_) => (actix_web::http::StatusCode::BAD_REQUEST, "validation_error"),
-            AppError::Authentication(_) => (actix_web::http::StatusCode::UNAUTHORIZED, "authentication_error"),
-            AppError::Authorization(_) => (actix_web::http::StatusCode::FORBIDDEN, "authorization_error"),
+            AppError::Database(_) => (
+                actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+                "database_error",
+            ),
+            AppError::Validation(_) => {
+                (actix_web::http::StatusCode::BAD_REQUEST, "validation_error")
+            }
+            AppError::Authentication(_) => (
+                actix_web::http::StatusCode::UNAUTHORIZED,
+                "authentication_error",
+            ),
+            AppError::Authorization(_) => (
+                actix_web::http::StatusCode::FORBIDDEN,
+                "authorization_error",
+            ),
             AppError::NotFound(_) => (actix_web::http::StatusCode::NOT_FOUND, "not_found"),
             AppError::Conflict(_) => (actix_web::http::StatusCode::CONFLICT, "conflict"),
-            AppError::Internal(_) => (actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+            AppError::TooManyRequests(_) => (
+                actix_web::http::StatusCode::TOO_MANY_REQUESTS,
+                "too_many_requests",
+            ),
+            AppError::Internal(_) => (
+                actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+                "internal_error",
+            ),
         };
 
         let error_msg = match self {
@@ -53,9 +82,15 @@ impl ResponseError for AppError {
             _ => self.to_string(),
         };
 
-        HttpResponse::build(status).json(ErrorResponse {
+        let mut response = HttpResponse::build(status);
+        if let AppError::TooManyRequests(throttle) = self {
+            response.insert_header(("Retry-After", throttle.retry_after.to_string()));
+        }
+
+        response.json(ErrorResponse {
             error: error_msg,
             code: error_code.to_string(),
+            request_id: crate::telemetry::current_request_id(),
         })
     }
 }
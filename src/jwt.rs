@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::fs;
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::Duration;
+use ed25519_dalek::{SigningKey, pkcs8::DecodePrivateKey};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode,
+};
+use rsa::RsaPrivateKey;
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::traits::PublicKeyParts;
+
+use crate::config::AuthConfig;
+use crate::errors::{AppError, AppResult};
+use crate::models::Claims;
+
+const HMAC_KEY_ID: &str = "hs256";
+
+struct VerificationKey {
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+// Loaded once at startup. Tokens are signed with a single key and verified against every key in
+// the set, so a retired key stays valid (and published) until its tokens have expired.
+pub struct JwtKeySet {
+    signing_kid: String,
+    signing_algorithm: Algorithm,
+    signing_key: EncodingKey,
+    verification_keys: HashMap<String, VerificationKey>,
+    public_jwks: JwkSet,
+    pub issuer: String,
+    pub audience: String,
+    pub access_token_ttl: Duration,
+    pub refresh_token_ttl: Duration,
+}
+
+impl JwtKeySet {
+    // `jwt_signing_key` points to a PKCS#8 PEM private key for `jwt_algorithm` (EdDSA or RS256).
+    // `jwt_verification_keys` may point to a JWKS file with additional (rotated out) public keys.
+    // Without a signing key tokens fall back to HS256 with `jwt_secret`, which is not published.
+    pub fn from_config(config: &AuthConfig) -> AppResult<Self> {
+        let mut key_set = match &config.jwt_signing_key {
+            Some(path) => {
+                let algorithm = match config.jwt_algorithm.as_str() {
+                    "RS256" => Algorithm::RS256,
+                    "EdDSA" => Algorithm::EdDSA,
+                    other => {
+                        return Err(AppError::Internal(format!(
+                            "Unsupported JWT algorithm '{}', expected EdDSA or RS256",
+                            other
+                        )));
+                    }
+                };
+                let kid = config
+                    .jwt_signing_key_id
+                    .as_deref()
+                    .ok_or_else(|| AppError::Internal("JWT signing key id must be set".into()))?;
+                let pem = fs::read_to_string(path).map_err(|e| {
+                    AppError::Internal(format!("Failed to read JWT signing key {}: {}", path, e))
+                })?;
+
+                Self::from_private_pem(kid, algorithm, &pem, config)?
+            }
+            None => {
+                let secret = config.jwt_secret.as_deref().ok_or_else(|| {
+                    AppError::Internal("Either a JWT signing key or JWT_SECRET must be set".into())
+                })?;
+                Self::from_secret(secret.as_bytes(), config)
+            }
+        };
+
+        if let Some(path) = &config.jwt_verification_keys {
+            let contents = fs::read_to_string(path).map_err(|e| {
+                AppError::Internal(format!(
+                    "Failed to read JWT verification keys {}: {}",
+                    path, e
+                ))
+            })?;
+            let jwks: JwkSet = serde_json::from_str(&contents)
+                .map_err(|e| AppError::Internal(format!("Invalid JWKS in {}: {}", path, e)))?;
+
+            for jwk in jwks.keys {
+                key_set.add_verification_jwk(jwk)?;
+            }
+        }
+
+        Ok(key_set)
+    }
+
+    fn from_secret(secret: &[u8], config: &AuthConfig) -> Self {
+        let mut verification_keys = HashMap::new();
+        verification_keys.insert(
+            HMAC_KEY_ID.to_string(),
+            VerificationKey {
+                algorithm: Algorithm::HS256,
+                key: DecodingKey::from_secret(secret),
+            },
+        );
+
+        Self {
+            signing_kid: HMAC_KEY_ID.to_string(),
+            signing_algorithm: Algorithm::HS256,
+            signing_key: EncodingKey::from_secret(secret),
+            verification_keys,
+            public_jwks: JwkSet { keys: Vec::new() },
+            issuer: config.jwt_issuer.clone(),
+            audience: config.jwt_audience.clone(),
+            access_token_ttl: Duration::seconds(config.access_token_ttl_seconds),
+            refresh_token_ttl: Duration::seconds(config.refresh_token_ttl_seconds),
+        }
+    }
+
+    fn from_private_pem(
+        kid: &str,
+        algorithm: Algorithm,
+        pem: &str,
+        config: &AuthConfig,
+    ) -> AppResult<Self> {
+        let invalid_key =
+            |e: &dyn std::fmt::Display| AppError::Internal(format!("Invalid signing key: {}", e));
+
+        let (signing_key, parameters) = match algorithm {
+            Algorithm::EdDSA => {
+                let key = SigningKey::from_pkcs8_pem(pem).map_err(|e| invalid_key(&e))?;
+                let x = URL_SAFE_NO_PAD.encode(key.verifying_key().as_bytes());
+                (
+                    EncodingKey::from_ed_pem(pem.as_bytes())?,
+                    AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                        key_type: OctetKeyPairType::OctetKeyPair,
+                        curve: EllipticCurve::Ed25519,
+                        x,
+                    }),
+                )
+            }
+            _ => {
+                let key = RsaPrivateKey::from_pkcs8_pem(pem)
+                    .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+                    .map_err(|e| invalid_key(&e))?;
+                (
+                    EncodingKey::from_rsa_pem(pem.as_bytes())?,
+                    AlgorithmParameters::RSA(RSAKeyParameters {
+                        key_type: RSAKeyType::RSA,
+                        n: URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+                        e: URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+                    }),
+                )
+            }
+        };
+
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(match algorithm {
+                    Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+                    _ => KeyAlgorithm::RS256,
+                }),
+                key_id: Some(kid.to_string()),
+                ..Default::default()
+            },
+            algorithm: parameters,
+        };
+
+        let mut key_set = Self {
+            signing_kid: kid.to_string(),
+            signing_algorithm: algorithm,
+            signing_key,
+            verification_keys: HashMap::new(),
+            public_jwks: JwkSet { keys: Vec::new() },
+            issuer: config.jwt_issuer.clone(),
+            audience: config.jwt_audience.clone(),
+            access_token_ttl: Duration::seconds(config.access_token_ttl_seconds),
+            refresh_token_ttl: Duration::seconds(config.refresh_token_ttl_seconds),
+        };
+        key_set.add_verification_jwk(jwk)?;
+
+        Ok(key_set)
+    }
+
+    fn add_verification_jwk(&mut self, jwk: Jwk) -> AppResult<()> {
+        let kid = jwk
+            .common
+            .key_id
+            .clone()
+            .ok_or_else(|| AppError::Internal("Verification keys must have a kid".into()))?;
+        let algorithm = match jwk.common.key_algorithm {
+            Some(KeyAlgorithm::EdDSA) => Algorithm::EdDSA,
+            Some(KeyAlgorithm::RS256) => Algorithm::RS256,
+            _ => {
+                return Err(AppError::Internal(format!(
+                    "Key {} must declare alg EdDSA or RS256",
+                    kid
+                )));
+            }
+        };
+
+        let key = DecodingKey::from_jwk(&jwk)?;
+        self.verification_keys
+            .insert(kid, VerificationKey { algorithm, key });
+        self.public_jwks.keys.push(jwk);
+
+        Ok(())
+    }
+
+    pub fn jwks(&self) -> &JwkSet {
+        &self.public_jwks
+    }
+
+    pub fn encode(&self, claims: &Claims) -> AppResult<String> {
+        let mut header = Header::new(self.signing_algorithm);
+        header.kid = Some(self.signing_kid.clone());
+
+        Ok(encode(&header, claims, &self.signing_key)?)
+    }
+
+    pub fn decode(&self, token: &str) -> AppResult<Claims> {
+        let header = decode_header(token)?;
+        let kid = header
+            .kid
+            .ok_or_else(|| AppError::Authentication("Token has no key id".to_string()))?;
+        let verification_key = self
+            .verification_keys
+            .get(&kid)
+            .ok_or_else(|| AppError::Authentication("Unknown signing key".to_string()))?;
+
+        // Pinning the algorithm to the key prevents algorithm confusion attacks.
+        let mut validation = Validation::new(verification_key.algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+
+        Ok(decode::<Claims>(token, &verification_key.key, &validation)?.claims)
+    }
+}
@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+
+use futures_util::future::BoxFuture;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+
+use crate::errors::{AppError, AppResult};
+use crate::mailer::{EmailMessage, Mailer};
+
+// Appends every message to a local file; handy for development without an SMTP server.
+pub struct FileMailer {
+    path: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send<'a>(&'a self, message: &'a EmailMessage) -> BoxFuture<'a, AppResult<()>> {
+        Box::pin(async move {
+            let entry = format!(
+                "To: {}\nSubject: {}\nDate: {}\n\n{}\n----------\n",
+                message.to,
+                message.subject,
+                chrono::Utc::now().to_rfc2822(),
+                message.body
+            );
+
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await
+                .map_err(|e| AppError::Internal(format!("Failed to open mail file: {}", e)))?;
+
+            file.write_all(entry.as_bytes())
+                .await
+                .map_err(|e| AppError::Internal(format!("Failed to write mail file: {}", e)))
+        })
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use futures_util::future::BoxFuture;
+
+use crate::errors::AppResult;
+use crate::mailer::{EmailMessage, Mailer};
+
+const MAX_KEPT_MESSAGES: usize = 100;
+
+// Keeps the most recent messages in memory so tests can assert on them; older ones are dropped.
+#[derive(Default)]
+pub struct MemoryMailer {
+    messages: Mutex<VecDeque<EmailMessage>>,
+}
+
+impl MemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(test)]
+    pub fn sent(&self) -> Vec<EmailMessage> {
+        self.messages.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl Mailer for MemoryMailer {
+    fn send<'a>(&'a self, message: &'a EmailMessage) -> BoxFuture<'a, AppResult<()>> {
+        let mut messages = self.messages.lock().unwrap();
+        if messages.len() == MAX_KEPT_MESSAGES {
+            messages.pop_front();
+        }
+        messages.push_back(message.clone());
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn keeps_only_the_most_recent_messages() {
+        let mailer = MemoryMailer::new();
+        for i in 0..MAX_KEPT_MESSAGES + 50 {
+            let message = EmailMessage {
+                to: format!("user{}@example.com", i),
+                subject: "Hello".to_string(),
+                body: String::new(),
+            };
+            mailer.send(&message).await.unwrap();
+        }
+
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), MAX_KEPT_MESSAGES);
+        assert_eq!(sent[0].to, "user50@example.com");
+    }
+}
@@ -0,0 +1,47 @@
+pub mod file;
+pub mod memory;
+pub mod smtp;
+pub mod templates;
+
+pub use file::*;
+pub use memory::*;
+pub use smtp::*;
+
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+
+use crate::config::MailerConfig;
+use crate::errors::{AppError, AppResult};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub trait Mailer: Send + Sync {
+    fn send<'a>(&'a self, message: &'a EmailMessage) -> BoxFuture<'a, AppResult<()>>;
+}
+
+pub type SharedMailer = Arc<dyn Mailer>;
+
+pub fn mailer_from_config(config: &MailerConfig) -> AppResult<SharedMailer> {
+    match config.transport.as_str() {
+        "smtp" => {
+            let (Some(url), Some(from)) = (&config.smtp_url, &config.from) else {
+                return Err(AppError::Internal(
+                    "mailer.smtp_url and mailer.from must be set for smtp".into(),
+                ));
+            };
+            Ok(Arc::new(SmtpMailer::new(url, from)?))
+        }
+        "file" => Ok(Arc::new(FileMailer::new(&config.file))),
+        "memory" => Ok(Arc::new(MemoryMailer::new())),
+        other => Err(AppError::Internal(format!(
+            "Unknown mailer transport '{}'",
+            other
+        ))),
+    }
+}
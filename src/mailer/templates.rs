@@ -0,0 +1,170 @@
+use crate::mailer::EmailMessage;
+use crate::models::{NotificationKind, PendingNotification};
+
+const VOTE_NEEDED_SUBJECT: &str = "Your approval is needed: {title}";
+const VOTE_NEEDED_BODY: &str = "\
+Proposal \"{title}\" in multisig \"{multisig}\" is waiting for your vote.
+
+Review and approve it here:
+{link}
+";
+
+const THRESHOLD_REACHED_SUBJECT: &str = "Ready to execute: {title}";
+const THRESHOLD_REACHED_BODY: &str = "\
+Proposal \"{title}\" in multisig \"{multisig}\" has reached its approval threshold and can now be executed.
+
+{link}
+";
+
+const DIGEST_SUBJECT: &str = "{count} multisig updates";
+const DIGEST_LINE: &str = "- {summary}: \"{title}\" in \"{multisig}\"\n  {link}\n";
+
+const VERIFY_EMAIL_SUBJECT: &str = "Confirm your email address";
+const VERIFY_EMAIL_BODY: &str = "\
+Confirm that this address belongs to your multisig account by opening the link below.
+The link expires in 24 hours.
+
+{link}
+
+If you did not create an account you can ignore this email.
+";
+
+const PASSWORD_RESET_SUBJECT: &str = "Reset your password";
+const PASSWORD_RESET_BODY: &str = "\
+Someone asked to reset the password of your multisig account. Open the link below to choose a
+new one. The link expires in 1 hour and signs out all existing sessions once used.
+
+{link}
+
+If you did not ask for this you can ignore this email; your password stays unchanged.
+";
+
+const FOOTER: &str = "
+You are receiving this because you own this multisig. Update your notification preferences with
+PUT /me/notification-preferences.
+";
+
+// Single pass so values (e.g. a proposal title containing `{link}`) are never re-expanded.
+fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        let replacement = after.find('}').and_then(|end| {
+            values
+                .iter()
+                .find(|(key, _)| *key == &after[..end])
+                .map(|(_, value)| (end, *value))
+        });
+
+        match replacement {
+            Some((end, value)) => {
+                output.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                output.push('{');
+                rest = after;
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+fn proposal_link(base_url: &str, proposal_id: i64) -> String {
+    format!(
+        "{}/proposals/{}",
+        base_url.trim_end_matches('/'),
+        proposal_id
+    )
+}
+
+fn token_link(base_url: &str, path: &str, token: &str) -> String {
+    format!(
+        "{}/{}?token={}",
+        base_url.trim_end_matches('/'),
+        path,
+        token
+    )
+}
+
+pub fn render_email_verification(base_url: &str, to: &str, token: &str) -> EmailMessage {
+    let link = token_link(base_url, "verify-email", token);
+
+    EmailMessage {
+        to: to.to_string(),
+        subject: VERIFY_EMAIL_SUBJECT.to_string(),
+        body: render(VERIFY_EMAIL_BODY, &[("link", link.as_str())]),
+    }
+}
+
+pub fn render_password_reset(base_url: &str, to: &str, token: &str) -> EmailMessage {
+    let link = token_link(base_url, "reset-password", token);
+
+    EmailMessage {
+        to: to.to_string(),
+        subject: PASSWORD_RESET_SUBJECT.to_string(),
+        body: render(PASSWORD_RESET_BODY, &[("link", link.as_str())]),
+    }
+}
+
+// One notification gets its own message; several are combined into a digest.
+pub fn render_notifications(
+    base_url: &str,
+    notifications: &[PendingNotification],
+) -> Option<EmailMessage> {
+    let first = notifications.first()?;
+
+    if let [single] = notifications {
+        let link = proposal_link(base_url, single.proposal_id);
+        let values = [
+            ("title", single.proposal_title.as_str()),
+            ("multisig", single.multisig_name.as_str()),
+            ("link", link.as_str()),
+        ];
+        let (subject, body) = match single.kind {
+            NotificationKind::VoteNeeded => (VOTE_NEEDED_SUBJECT, VOTE_NEEDED_BODY),
+            NotificationKind::ThresholdReached => {
+                (THRESHOLD_REACHED_SUBJECT, THRESHOLD_REACHED_BODY)
+            }
+        };
+
+        return Some(EmailMessage {
+            to: single.email.clone(),
+            subject: render(subject, &values),
+            body: render(body, &values) + FOOTER,
+        });
+    }
+
+    let mut body = String::new();
+    for notification in notifications {
+        let link = proposal_link(base_url, notification.proposal_id);
+        let summary = match notification.kind {
+            NotificationKind::VoteNeeded => "Needs your vote",
+            NotificationKind::ThresholdReached => "Ready to execute",
+        };
+        body.push_str(&render(
+            DIGEST_LINE,
+            &[
+                ("summary", summary),
+                ("title", notification.proposal_title.as_str()),
+                ("multisig", notification.multisig_name.as_str()),
+                ("link", link.as_str()),
+            ],
+        ));
+    }
+
+    Some(EmailMessage {
+        to: first.email.clone(),
+        subject: render(
+            DIGEST_SUBJECT,
+            &[("count", notifications.len().to_string().as_str())],
+        ),
+        body: body + FOOTER,
+    })
+}
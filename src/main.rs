@@ -1,53 +1,294 @@
-use actix_web::{App, HttpServer, web};
+use actix_cors::Cors;
+use actix_web::{App, HttpServer, middleware, web};
 use dotenvy::dotenv;
 use sqlx::postgres::PgPoolOptions;
 use std::env;
+use tokio::signal::unix::{SignalKind, signal};
+use tracing::{error, info};
 
 mod auth_middleware;
+mod cli;
+mod config;
 mod db;
 mod errors;
+mod jwt;
+mod mailer;
+mod metrics;
+mod migrations;
 mod models;
+mod oidc;
+mod outbound;
+mod password;
 mod routes;
 mod services;
+mod solana;
+mod supervisor;
+mod telemetry;
+mod webauthn;
 
-use routes::auth::{login, me, register};
-use routes::multisig::{create_multisig, get_multisig, list_multisigs};
+use config::DatabaseConfig;
+use db::DbPool;
+use models::AuditSigningKey;
+use routes::admin::unlock_user_login;
+use routes::api_key::{create_api_key, get_api_key, list_api_keys, revoke_api_key, update_api_key};
+use routes::audit::{get_audit_checkpoint, list_audit_entries, verify_audit_log};
+use routes::auth::{
+    change_password, forgot_password, list_sessions, login, logout, me, oidc_callback, oidc_login,
+    refresh, register, resend_verification_email, reset_password, revoke_session, verify_email,
+};
+use routes::health::{healthz, readyz, version};
+use routes::job::{cancel_job, get_job, list_jobs, retry_job};
+use routes::me::{get_inbox, get_notification_preferences, update_notification_preferences};
+use routes::metrics::get_metrics;
+use routes::multisig::{
+    create_multisig, get_multisig, list_multisigs, stream_multisig_events, update_step_up_policy,
+};
+use routes::passkey::{
+    delete_passkey, list_passkeys, passkey_login, passkey_login_options,
+    passkey_registration_options, passkey_step_up, passkey_step_up_options, register_passkey,
+};
 use routes::proposal::{
     activate_proposal, approve_proposal, create_proposal, execute_proposal, get_proposal,
-    get_proposal_approvals, list_proposals, reject_proposal,
+    get_proposal_approvals, get_proposal_history, list_proposals, reject_proposal, revoke_approval,
+};
+use routes::two_factor::{
+    complete_login, confirm_totp, disable_totp, enroll_totp, get_two_factor_status,
+    regenerate_recovery_codes, step_up,
+};
+use routes::webhook::{
+    create_webhook, delete_webhook, list_webhook_deliveries, list_webhook_delivery_attempts,
+    list_webhooks,
 };
+use routes::well_known::get_jwks;
+use services::{
+    CLEANUP_WORKER, CleanupService, DELIVERY_WORKER, DIGEST_WORKER, EventBroadcaster, JOB_WORKER,
+    JobService, LISTENER_WORKER, NotificationService, WebhookService, WorkerMonitor,
+};
+use supervisor::WorkerSupervisor;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
 
-    let database_url = env::var("DATABASE_URL").unwrap();
-    let pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&database_url)
-        .await
-        .unwrap();
+    let args: Vec<String> = env::args().skip(1).collect();
+    if cli::is_migrate_command(&args) {
+        let database = match config::Config::load_database() {
+            Ok(database) => database,
+            Err(e) => {
+                eprint!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        let pool = connect(&database).await;
+        std::process::exit(cli::run_migrate(&pool, &args).await);
+    }
+
+    let config = match config::Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprint!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let _telemetry = telemetry::init(&config.logging, &config.tracing);
+
+    let pool = connect(&config.database).await;
+
+    info!("Connected to database!");
+
+    let audit_key = AuditSigningKey::new(&config.audit.signing_key);
 
-    println!("Connected to database!");
+    if !args.is_empty() {
+        std::process::exit(cli::run(&pool, &audit_key, &args).await);
+    }
 
-    HttpServer::new(move || {
+    if config.database.run_migrations {
+        if let Err(e) = migrations::run_migrations(&pool).await {
+            error!("Failed to apply migrations: {}", e);
+            std::process::exit(1);
+        }
+        info!("Migrations applied");
+    }
+
+    let worker_monitor = WorkerMonitor::new();
+    let mut supervisor = WorkerSupervisor::new(worker_monitor.clone());
+    {
+        let (pool, monitor) = (pool.clone(), worker_monitor.clone());
+        let webhook_config = config.webhooks.clone();
+        supervisor.spawn(DELIVERY_WORKER, move |shutdown| {
+            WebhookService::run_delivery_worker(
+                pool.clone(),
+                webhook_config.clone(),
+                monitor.clone(),
+                shutdown,
+            )
+        });
+    }
+    {
+        let (pool, monitor) = (pool.clone(), worker_monitor.clone());
+        supervisor.spawn(JOB_WORKER, move |shutdown| {
+            JobService::run_worker(pool.clone(), monitor.clone(), shutdown)
+        });
+    }
+    {
+        let (pool, monitor) = (pool.clone(), worker_monitor.clone());
+        supervisor.spawn(CLEANUP_WORKER, move |shutdown| {
+            CleanupService::run_worker(pool.clone(), monitor.clone(), shutdown)
+        });
+    }
+
+    let jwt_keys = web::Data::new(or_exit(
+        jwt::JwtKeySet::from_config(&config.auth),
+        "Cannot load JWT keys",
+    ));
+    let relying_party = web::Data::new(webauthn::RelyingParty::from_config(&config.webauthn));
+    let password_policy = web::Data::new(or_exit(
+        password::PasswordPolicy::from_config(&config.password),
+        "Cannot load the password policy",
+    ));
+    let oidc_provider = web::Data::new(or_exit(
+        oidc::OidcProvider::from_config(&config.oidc, &config.server.app_base_url),
+        "Cannot set up the OIDC provider",
+    ));
+
+    let mailer = or_exit(
+        mailer::mailer_from_config(&config.mailer),
+        "Cannot set up the mailer",
+    );
+    {
+        let (pool, mailer, monitor) = (pool.clone(), mailer.clone(), worker_monitor.clone());
+        let base_url = config.server.app_base_url.clone();
+        supervisor.spawn(DIGEST_WORKER, move |shutdown| {
+            NotificationService::run_digest_worker(
+                pool.clone(),
+                mailer.clone(),
+                base_url.clone(),
+                monitor.clone(),
+                shutdown,
+            )
+        });
+    }
+
+    let broadcaster = EventBroadcaster::new();
+    {
+        let (broadcaster, pool, monitor) =
+            (broadcaster.clone(), pool.clone(), worker_monitor.clone());
+        supervisor.spawn(LISTENER_WORKER, move |shutdown| {
+            broadcaster
+                .clone()
+                .run_listener(pool.clone(), monitor.clone(), shutdown)
+        });
+    }
+
+    let audit_key = web::Data::new(audit_key);
+    let solana_rpc = web::Data::new(or_exit(
+        solana::SolanaRpc::from_config(&config.solana),
+        "Cannot set up the Solana RPC client",
+    ));
+    let worker_monitor = web::Data::new(worker_monitor);
+
+    let bind_address = config.server.bind_address.clone();
+    let workers = config.server.workers;
+    let shutdown_timeout = config.server.shutdown_timeout();
+    let config = web::Data::new(config);
+
+    let mut server = HttpServer::new(move || {
         App::new()
+            .wrap(middleware::from_fn(metrics::track_requests))
+            .wrap(cors(&config.server.cors_allowed_origins))
+            .wrap(middleware::from_fn(telemetry::request_span))
+            .app_data(config.clone())
             .app_data(web::Data::new(pool.clone()))
+            .app_data(web::Data::new(broadcaster.clone()))
+            .app_data(jwt_keys.clone())
+            .app_data(relying_party.clone())
+            .app_data(password_policy.clone())
+            .app_data(oidc_provider.clone())
+            .app_data(audit_key.clone())
+            .app_data(solana_rpc.clone())
+            .app_data(worker_monitor.clone())
+            .app_data(web::Data::new(mailer.clone()))
+            .service(healthz)
+            .service(readyz)
+            .service(version)
+            .service(get_metrics)
+            .service(web::scope("/.well-known").service(get_jwks))
             .service(
                 web::scope("/auth")
                     .service(register)
                     .service(login)
+                    .service(oidc_login)
+                    .service(oidc_callback)
+                    .service(refresh)
+                    .service(logout)
+                    .service(list_sessions)
+                    .service(revoke_session)
+                    .service(verify_email)
+                    .service(resend_verification_email)
+                    .service(forgot_password)
+                    .service(reset_password)
+                    .service(change_password)
+                    .service(get_two_factor_status)
+                    .service(enroll_totp)
+                    .service(confirm_totp)
+                    .service(disable_totp)
+                    .service(regenerate_recovery_codes)
+                    .service(complete_login)
+                    .service(step_up)
+                    .service(passkey_registration_options)
+                    .service(register_passkey)
+                    .service(list_passkeys)
+                    .service(delete_passkey)
+                    .service(passkey_login_options)
+                    .service(passkey_login)
+                    .service(passkey_step_up_options)
+                    .service(passkey_step_up)
+                    .service(create_api_key)
+                    .service(list_api_keys)
+                    .service(get_api_key)
+                    .service(update_api_key)
+                    .service(revoke_api_key)
                     .service(me),
             )
+            .service(
+                web::scope("/me")
+                    .service(get_inbox)
+                    .service(get_notification_preferences)
+                    .service(update_notification_preferences),
+            )
+            .service(
+                web::scope("/admin")
+                    .service(unlock_user_login)
+                    .service(list_jobs)
+                    .service(get_job)
+                    .service(retry_job)
+                    .service(cancel_job),
+            )
+            .service(
+                web::scope("/audit")
+                    .service(list_audit_entries)
+                    .service(verify_audit_log)
+                    .service(get_audit_checkpoint),
+            )
             .service(
                 web::scope("/multisigs")
                     .service(create_multisig)
                     .service(list_multisigs)
                     .service(get_multisig)
+                    .service(update_step_up_policy)
+                    .service(stream_multisig_events)
                     .service(
                         web::scope("/{multisig_id}/proposals")
                             .service(create_proposal)
                             .service(list_proposals),
+                    )
+                    .service(
+                        web::scope("/{multisig_id}/webhooks")
+                            .service(create_webhook)
+                            .service(list_webhooks)
+                            .service(delete_webhook)
+                            .service(list_webhook_deliveries)
+                            .service(list_webhook_delivery_attempts),
                     ),
             )
             .service(
@@ -55,12 +296,90 @@ async fn main() -> std::io::Result<()> {
                     .service(get_proposal)
                     .service(activate_proposal)
                     .service(approve_proposal)
+                    .service(revoke_approval)
                     .service(execute_proposal)
                     .service(reject_proposal)
-                    .service(get_proposal_approvals),
+                    .service(get_proposal_approvals)
+                    .service(get_proposal_history),
             )
-    })
-    .bind(("127.0.0.1", 8080))?
-    .run()
-    .await
+    });
+
+    if let Some(workers) = workers {
+        server = server.workers(workers);
+    }
+
+    info!("Listening on {}", bind_address);
+    let mut server = server
+        .bind(bind_address)?
+        .disable_signals()
+        .shutdown_timeout(shutdown_timeout.as_secs())
+        .run();
+    let server_handle = server.handle();
+
+    tokio::select! {
+        result = &mut server => {
+            supervisor.shutdown(shutdown_timeout).await;
+            return result;
+        }
+        () = shutdown_signal() => {}
+    }
+
+    // Workers wind down while actix stops accepting connections and drains the ones in flight.
+    info!("Shutting down");
+    // The stop only completes while the server future itself is being polled.
+    let ((), result, ()) = tokio::join!(
+        server_handle.stop(true),
+        server,
+        supervisor.shutdown(shutdown_timeout),
+    );
+    info!("Shutdown complete");
+    result
+}
+
+async fn connect(database: &DatabaseConfig) -> DbPool {
+    or_exit(
+        PgPoolOptions::new()
+            .max_connections(database.max_connections)
+            .min_connections(database.min_connections)
+            .acquire_timeout(database.acquire_timeout())
+            .idle_timeout(database.idle_timeout())
+            .max_lifetime(database.max_lifetime())
+            .connect(&database.url)
+            .await,
+        "Cannot connect to the database",
+    )
+}
+
+// Startup failures after configuration loads end the process the same way an invalid
+// configuration does: a message on stderr and exit code 1.
+fn or_exit<T, E: std::fmt::Display>(result: Result<T, E>, context: &str) -> T {
+    match result {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!("{}: {}", context, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate()).expect("SIGTERM handler can be installed");
+    tokio::select! {
+        _ = terminate.recv() => info!("Received SIGTERM"),
+        _ = tokio::signal::ctrl_c() => info!("Received SIGINT"),
+    }
+}
+
+// An empty allow-list adds no CORS headers, so browsers keep enforcing same-origin.
+fn cors(allowed_origins: &[String]) -> Cors {
+    if allowed_origins.iter().any(|origin| origin == "*") {
+        return Cors::permissive();
+    }
+
+    allowed_origins
+        .iter()
+        .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+        .allow_any_method()
+        .allow_any_header()
+        .max_age(3600)
 }
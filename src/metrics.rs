@@ -0,0 +1,189 @@
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use tracing::error;
+
+// Process-wide so services can record without threading a handle through every call. Gauges
+// derived from the database are refreshed when `/metrics` is scraped.
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests: IntCounterVec,
+    pub http_request_duration: HistogramVec,
+    pub db_pool_connections: IntGaugeVec,
+    pub db_pool_max_connections: IntGauge,
+    pub proposals: IntGaugeVec,
+    pub proposal_approvals: IntCounter,
+    pub rpc_request_duration: HistogramVec,
+    pub rpc_errors: IntCounterVec,
+    pub webhook_backlog: IntGauge,
+    pub webhook_oldest_pending_seconds: IntGauge,
+    pub notification_backlog: IntGauge,
+    pub login_failures: IntCounterVec,
+    pub login_throttled: IntCounterVec,
+    pub worker_restarts: IntCounterVec,
+    pub jobs: IntGaugeVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("multisig".to_string()), None)
+            .expect("metrics prefix is valid");
+
+        let metrics = Self {
+            http_requests: IntCounterVec::new(
+                Opts::new("http_requests_total", "HTTP requests by route and status"),
+                &["method", "route", "status"],
+            )
+            .unwrap(),
+            http_request_duration: HistogramVec::new(
+                HistogramOpts::new("http_request_duration_seconds", "HTTP request latency")
+                    .buckets(LATENCY_BUCKETS.to_vec()),
+                &["method", "route", "status"],
+            )
+            .unwrap(),
+            db_pool_connections: IntGaugeVec::new(
+                Opts::new("db_pool_connections", "Database pool connections by state"),
+                &["state"],
+            )
+            .unwrap(),
+            db_pool_max_connections: IntGauge::new(
+                "db_pool_max_connections",
+                "Configured database pool size",
+            )
+            .unwrap(),
+            proposals: IntGaugeVec::new(Opts::new("proposals", "Proposals by status"), &["status"])
+                .unwrap(),
+            proposal_approvals: IntCounter::new(
+                "proposal_approvals_total",
+                "Approvals recorded; use rate() for approvals per minute",
+            )
+            .unwrap(),
+            rpc_request_duration: HistogramVec::new(
+                HistogramOpts::new("solana_rpc_request_duration_seconds", "Solana RPC latency")
+                    .buckets(LATENCY_BUCKETS.to_vec()),
+                &["method"],
+            )
+            .unwrap(),
+            rpc_errors: IntCounterVec::new(
+                Opts::new("solana_rpc_errors_total", "Failed Solana RPC calls"),
+                &["method"],
+            )
+            .unwrap(),
+            webhook_backlog: IntGauge::new(
+                "webhook_deliveries_pending",
+                "Webhook deliveries waiting to be sent",
+            )
+            .unwrap(),
+            webhook_oldest_pending_seconds: IntGauge::new(
+                "webhook_oldest_pending_seconds",
+                "Age of the oldest pending webhook delivery",
+            )
+            .unwrap(),
+            notification_backlog: IntGauge::new(
+                "notifications_pending",
+                "Notifications not yet included in a digest email",
+            )
+            .unwrap(),
+            login_failures: IntCounterVec::new(
+                Opts::new(
+                    "login_failures_total",
+                    "Failed password and second-factor checks",
+                ),
+                &["reason"],
+            )
+            .unwrap(),
+            login_throttled: IntCounterVec::new(
+                Opts::new("login_throttled_total", "Logins refused by rate limiting"),
+                &["reason"],
+            )
+            .unwrap(),
+            worker_restarts: IntCounterVec::new(
+                Opts::new(
+                    "worker_restarts_total",
+                    "Background worker restarts after a crash",
+                ),
+                &["worker"],
+            )
+            .unwrap(),
+            jobs: IntGaugeVec::new(Opts::new("jobs", "Queued jobs by status"), &["status"])
+                .unwrap(),
+            registry,
+        };
+
+        let collectors: Vec<Box<dyn prometheus::core::Collector>> = vec![
+            Box::new(metrics.http_requests.clone()),
+            Box::new(metrics.http_request_duration.clone()),
+            Box::new(metrics.db_pool_connections.clone()),
+            Box::new(metrics.db_pool_max_connections.clone()),
+            Box::new(metrics.proposals.clone()),
+            Box::new(metrics.proposal_approvals.clone()),
+            Box::new(metrics.rpc_request_duration.clone()),
+            Box::new(metrics.rpc_errors.clone()),
+            Box::new(metrics.webhook_backlog.clone()),
+            Box::new(metrics.webhook_oldest_pending_seconds.clone()),
+            Box::new(metrics.notification_backlog.clone()),
+            Box::new(metrics.login_failures.clone()),
+            Box::new(metrics.login_throttled.clone()),
+            Box::new(metrics.worker_restarts.clone()),
+            Box::new(metrics.jobs.clone()),
+        ];
+        for collector in collectors {
+            metrics
+                .registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+
+        metrics
+    }
+
+    /// Prometheus text exposition of everything registered.
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+// Labels use the matched route pattern (`/proposals/{id}`), never the raw path, so cardinality stays
+// bounded; requests that match no route share one label.
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.call(req).await;
+
+    let status = match &response {
+        Ok(response) => response.status().as_u16().to_string(),
+        Err(e) => e.as_response_error().status_code().as_u16().to_string(),
+    };
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    METRICS.http_requests.with_label_values(&labels).inc();
+    METRICS
+        .http_request_duration
+        .with_label_values(&labels)
+        .observe(started.elapsed().as_secs_f64());
+
+    response
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
+
+use crate::db::DbPool;
+use crate::errors::{AppError, AppResult};
+
+/// Every file in `migrations/`, compiled into the binary.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the file has been edited since.
+    ChecksumMismatch,
+    /// Started and failed part way; the database needs manual repair.
+    Failed,
+    /// Recorded in the database but no longer shipped with this binary.
+    Unknown,
+}
+
+impl MigrationState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::ChecksumMismatch => "checksum_mismatch",
+            MigrationState::Failed => "failed",
+            MigrationState::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+impl MigrationStatus {
+    pub fn is_drift(&self) -> bool {
+        matches!(
+            self.state,
+            MigrationState::ChecksumMismatch | MigrationState::Failed | MigrationState::Unknown
+        )
+    }
+}
+
+fn migrate_error(e: MigrateError) -> AppError {
+    AppError::Database(e.into())
+}
+
+/// Compares the embedded migrations with the ones recorded in `_sqlx_migrations`.
+pub async fn migration_status(pool: &DbPool) -> AppResult<Vec<MigrationStatus>> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table()
+        .await
+        .map_err(migrate_error)?;
+
+    let dirty_version = conn.dirty_version().await.map_err(migrate_error)?;
+    let mut applied: HashMap<i64, Vec<u8>> = conn
+        .list_applied_migrations()
+        .await
+        .map_err(migrate_error)?
+        .into_iter()
+        .map(|migration| (migration.version, migration.checksum.into_owned()))
+        .collect();
+
+    let mut statuses: Vec<MigrationStatus> = MIGRATOR
+        .iter()
+        .map(|migration| {
+            let state = match applied.remove(&migration.version) {
+                _ if dirty_version == Some(migration.version) => MigrationState::Failed,
+                Some(checksum) if checksum == *migration.checksum => MigrationState::Applied,
+                Some(_) => MigrationState::ChecksumMismatch,
+                None => MigrationState::Pending,
+            };
+
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                state,
+            }
+        })
+        .collect();
+
+    statuses.extend(applied.into_keys().map(|version| MigrationStatus {
+        version,
+        description: String::new(),
+        state: if dirty_version == Some(version) {
+            MigrationState::Failed
+        } else {
+            MigrationState::Unknown
+        },
+    }));
+    statuses.sort_by_key(|status| status.version);
+
+    Ok(statuses)
+}
+
+/// Applies pending migrations in order. Refuses to run when an applied migration has drifted.
+pub async fn run_migrations(pool: &DbPool) -> AppResult<()> {
+    MIGRATOR.run(pool).await.map_err(migrate_error)
+}
@@ -0,0 +1,262 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::models::ProposalEvent;
+
+pub const AUDIT_GENESIS_HASH: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    UserRegistered,
+    LoginSucceeded,
+    LoginFailed,
+    LoginUnlocked,
+    Logout,
+    RefreshTokenReused,
+    SessionRevoked,
+    EmailVerified,
+    PasswordResetRequested,
+    PasswordReset,
+    PasswordChanged,
+    ApiKeyCreated,
+    ApiKeyUpdated,
+    ApiKeyRevoked,
+    OidcIdentityLinked,
+    TwoFactorEnabled,
+    TwoFactorDisabled,
+    RecoveryCodesRegenerated,
+    RecoveryCodeUsed,
+    StepUpVerified,
+    PasskeyRegistered,
+    PasskeyRemoved,
+    MultisigCreated,
+    MultisigStepUpChanged,
+    ProposalTransition,
+    CheckpointExported,
+    WebhookCreated,
+    WebhookDeleted,
+    JobRetried,
+    JobCancelled,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::UserRegistered => "auth.register",
+            AuditAction::LoginSucceeded => "auth.login",
+            AuditAction::LoginFailed => "auth.login_failed",
+            AuditAction::LoginUnlocked => "auth.login_unlocked",
+            AuditAction::Logout => "auth.logout",
+            AuditAction::RefreshTokenReused => "auth.refresh_token_reused",
+            AuditAction::SessionRevoked => "auth.session_revoked",
+            AuditAction::EmailVerified => "auth.email_verified",
+            AuditAction::PasswordResetRequested => "auth.password_reset_requested",
+            AuditAction::PasswordReset => "auth.password_reset",
+            AuditAction::PasswordChanged => "auth.password_changed",
+            AuditAction::ApiKeyCreated => "auth.api_key_created",
+            AuditAction::ApiKeyUpdated => "auth.api_key_updated",
+            AuditAction::ApiKeyRevoked => "auth.api_key_revoked",
+            AuditAction::OidcIdentityLinked => "auth.oidc_identity_linked",
+            AuditAction::TwoFactorEnabled => "auth.2fa_enabled",
+            AuditAction::TwoFactorDisabled => "auth.2fa_disabled",
+            AuditAction::RecoveryCodesRegenerated => "auth.recovery_codes_regenerated",
+            AuditAction::RecoveryCodeUsed => "auth.recovery_code_used",
+            AuditAction::StepUpVerified => "auth.step_up",
+            AuditAction::PasskeyRegistered => "auth.passkey_registered",
+            AuditAction::PasskeyRemoved => "auth.passkey_removed",
+            AuditAction::MultisigCreated => "multisig.created",
+            AuditAction::MultisigStepUpChanged => "multisig.step_up_changed",
+            AuditAction::ProposalTransition => "proposal.transition",
+            AuditAction::CheckpointExported => "audit.checkpoint_exported",
+            AuditAction::WebhookCreated => "webhook.created",
+            AuditAction::WebhookDeleted => "webhook.deleted",
+            AuditAction::JobRetried => "job.retried",
+            AuditAction::JobCancelled => "job.cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub occurred_at: DateTime<Utc>,
+    pub actor_id: Option<i64>,
+    pub action: String,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<i64>,
+    pub details: serde_json::Value,
+    pub prev_hash: String,
+    pub entry_hash: String,
+}
+
+impl AuditEntry {
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_db(
+        id: i64,
+        occurred_at: DateTime<Utc>,
+        actor_id: Option<i64>,
+        action: String,
+        entity_type: Option<String>,
+        entity_id: Option<i64>,
+        details: serde_json::Value,
+        prev_hash: String,
+        entry_hash: String,
+    ) -> Self {
+        Self {
+            id,
+            occurred_at,
+            actor_id,
+            action,
+            entity_type,
+            entity_id,
+            details,
+            prev_hash,
+            entry_hash,
+        }
+    }
+
+    // `details` is hashed through `serde_json::Value`, whose maps serialize with sorted keys, so
+    // the digest is stable across the JSONB round trip.
+    #[allow(clippy::too_many_arguments)]
+    pub fn compute_hash(
+        prev_hash: &str,
+        id: i64,
+        occurred_at: DateTime<Utc>,
+        actor_id: Option<i64>,
+        action: &str,
+        entity_type: Option<&str>,
+        entity_id: Option<i64>,
+        details: &serde_json::Value,
+    ) -> String {
+        let canonical = format!(
+            "{}|{}|{}|{}|{}|{}|{}|{}",
+            prev_hash,
+            id,
+            occurred_at.timestamp_micros(),
+            actor_id.map(|v| v.to_string()).unwrap_or_default(),
+            action,
+            entity_type.unwrap_or_default(),
+            entity_id.map(|v| v.to_string()).unwrap_or_default(),
+            details
+        );
+
+        hex::encode(Sha256::digest(canonical.as_bytes()))
+    }
+
+    pub fn recompute_hash(&self) -> String {
+        Self::compute_hash(
+            &self.prev_hash,
+            self.id,
+            self.occurred_at,
+            self.actor_id,
+            &self.action,
+            self.entity_type.as_deref(),
+            self.entity_id,
+            &self.details,
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateAuditEntry {
+    pub actor_id: Option<i64>,
+    pub action: AuditAction,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<i64>,
+    pub details: serde_json::Value,
+}
+
+impl CreateAuditEntry {
+    pub fn new(action: AuditAction, actor_id: Option<i64>) -> Self {
+        Self {
+            actor_id,
+            action,
+            entity_type: None,
+            entity_id: None,
+            details: serde_json::json!({}),
+        }
+    }
+
+    pub fn with_entity(mut self, entity_type: &str, entity_id: i64) -> Self {
+        self.entity_type = Some(entity_type.to_string());
+        self.entity_id = Some(entity_id);
+        self
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+impl From<&ProposalEvent> for CreateAuditEntry {
+    fn from(event: &ProposalEvent) -> Self {
+        CreateAuditEntry::new(AuditAction::ProposalTransition, event.actor_id)
+            .with_entity("proposal", event.proposal_id)
+            .with_details(serde_json::json!({
+                "event_id": event.id,
+                "event_type": event.event_type,
+                "old_status": event.old_status,
+                "new_status": event.new_status,
+                "metadata": event.metadata,
+            }))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditVerification {
+    pub valid: bool,
+    pub entries_checked: i64,
+    pub last_entry_id: Option<i64>,
+    pub last_entry_hash: String,
+    pub broken_at_id: Option<i64>,
+    pub reason: Option<String>,
+}
+
+/// HMAC key for signed checkpoints, loaded from `audit.signing_key` at startup.
+#[derive(Clone)]
+pub struct AuditSigningKey(Vec<u8>);
+
+impl AuditSigningKey {
+    pub fn new(key: &str) -> Self {
+        Self(key.as_bytes().to_vec())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditCheckpoint {
+    pub last_entry_id: Option<i64>,
+    pub last_entry_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub signature: String,
+}
+
+impl AuditCheckpoint {
+    pub fn sign(
+        last_entry_id: Option<i64>,
+        last_entry_hash: String,
+        created_at: DateTime<Utc>,
+        key: &AuditSigningKey,
+    ) -> Result<Self, String> {
+        let message = format!(
+            "{}:{}:{}",
+            last_entry_id.unwrap_or(0),
+            last_entry_hash,
+            created_at.to_rfc3339()
+        );
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(&key.0)
+            .map_err(|_| "Invalid audit signing key".to_string())?;
+        mac.update(message.as_bytes());
+
+        Ok(Self {
+            last_entry_id,
+            last_entry_hash,
+            created_at,
+            signature: hex::encode(mac.finalize().into_bytes()),
+        })
+    }
+}
@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+pub const JOB_DEFAULT_MAX_ATTEMPTS: i32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "job_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Succeeded,
+    /// Out of attempts, or failed in a way retrying cannot fix.
+    Dead,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub const ALL: [JobStatus; 5] = [
+        JobStatus::Pending,
+        JobStatus::Running,
+        JobStatus::Succeeded,
+        JobStatus::Dead,
+        JobStatus::Cancelled,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Pending => "pending",
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Dead => "dead",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn can_retry(&self) -> bool {
+        matches!(self, JobStatus::Dead | JobStatus::Cancelled)
+    }
+
+    pub fn can_cancel(&self) -> bool {
+        matches!(self, JobStatus::Pending | JobStatus::Running)
+    }
+}
+
+/// A job's payload type. `KIND` is stored next to the JSON so the runner knows what to decode into.
+pub trait JobPayload: Serialize + DeserializeOwned {
+    const KIND: &'static str;
+    const MAX_ATTEMPTS: i32 = JOB_DEFAULT_MAX_ATTEMPTS;
+}
+
+/// Moves an active proposal to `expired` once its deadline passes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpireProposalJob {
+    pub proposal_id: i64,
+}
+
+impl JobPayload for ExpireProposalJob {
+    const KIND: &'static str = "expire_proposal";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: i64,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub status: JobStatus,
+    pub unique_key: Option<String>,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl Job {
+    pub fn decode<P: JobPayload>(&self) -> Result<P, String> {
+        serde_json::from_value(self.payload.clone())
+            .map_err(|e| format!("Invalid {} payload: {}", self.kind, e))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewJob {
+    pub kind: &'static str,
+    pub payload: serde_json::Value,
+    pub max_attempts: i32,
+    pub run_at: DateTime<Utc>,
+    pub unique_key: Option<String>,
+}
+
+impl NewJob {
+    pub fn new<P: JobPayload>(payload: &P) -> Result<Self, String> {
+        let payload = serde_json::to_value(payload)
+            .map_err(|e| format!("Failed to encode {} payload: {}", P::KIND, e))?;
+
+        Ok(Self {
+            kind: P::KIND,
+            payload,
+            max_attempts: P::MAX_ATTEMPTS,
+            run_at: Utc::now(),
+            unique_key: None,
+        })
+    }
+
+    pub fn run_at(mut self, run_at: DateTime<Utc>) -> Self {
+        self.run_at = run_at;
+        self
+    }
+
+    /// While a job with the same kind and key is pending or running, enqueueing this one is a no-op.
+    pub fn unique_key(mut self, unique_key: impl Into<String>) -> Self {
+        self.unique_key = Some(unique_key.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct JobFilter {
+    pub status: Option<JobStatus>,
+    pub kind: Option<String>,
+}
+
+/// Why a job attempt failed. Permanent failures go straight to `dead` without using up retries.
+#[derive(Debug, Clone)]
+pub enum JobFailure {
+    Transient(String),
+    Permanent(String),
+}
+
+impl JobFailure {
+    pub fn message(&self) -> &str {
+        match self {
+            JobFailure::Transient(message) | JobFailure::Permanent(message) => message,
+        }
+    }
+}
+
+// Retry delay doubles from 10s and is capped at one hour.
+pub fn job_retry_delay(attempts: i32) -> chrono::Duration {
+    let exponent = attempts.clamp(0, 9) as u32;
+    let seconds = (10_i64 * 2_i64.pow(exponent)).min(3600);
+    chrono::Duration::seconds(seconds)
+}
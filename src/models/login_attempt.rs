@@ -0,0 +1,180 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+pub const LOGIN_ATTEMPT_WINDOW_SECONDS: i64 = 15 * 60;
+/// Failures on one account before each further attempt has to wait.
+pub const ACCOUNT_DELAY_AFTER_FAILURES: i64 = 3;
+pub const ACCOUNT_MAX_DELAY_SECONDS: i64 = 60;
+pub const ACCOUNT_LOCKOUT_FAILURES: i64 = 10;
+pub const ACCOUNT_LOCKOUT_SECONDS: i64 = 15 * 60;
+pub const IP_MAX_FAILURES: i64 = 50;
+
+#[derive(Debug, Clone)]
+pub struct CreateLoginAttempt {
+    pub email: String,
+    pub user_id: Option<i64>,
+    pub ip_address: Option<String>,
+    pub succeeded: bool,
+}
+
+/// Uncleared failures inside the sliding window for the account and the client address.
+#[derive(Debug, Clone, Default)]
+pub struct LoginAttemptStats {
+    pub account_failures: i64,
+    pub last_account_failure: Option<DateTime<Utc>>,
+    pub ip_failures: i64,
+    pub oldest_ip_failure: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginThrottleReason {
+    AccountDelay,
+    AccountLocked,
+    IpRateLimited,
+}
+
+impl LoginThrottleReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginThrottleReason::AccountDelay => "account_delay",
+            LoginThrottleReason::AccountLocked => "account_locked",
+            LoginThrottleReason::IpRateLimited => "ip_rate_limited",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LoginThrottle {
+    pub reason: LoginThrottleReason,
+    pub retry_after: i64,
+}
+
+impl LoginThrottle {
+    pub fn message(&self) -> &'static str {
+        match self.reason {
+            LoginThrottleReason::AccountLocked => {
+                "Account temporarily locked after repeated failures"
+            }
+            LoginThrottleReason::AccountDelay | LoginThrottleReason::IpRateLimited => {
+                "Too many login attempts"
+            }
+        }
+    }
+}
+
+impl LoginAttemptStats {
+    /// Returns how long the client has to wait before another attempt, if at all.
+    pub fn throttle(&self, now: DateTime<Utc>) -> Option<LoginThrottle> {
+        let remaining = |until: DateTime<Utc>| (until - now).num_seconds() + 1;
+
+        if self.ip_failures >= IP_MAX_FAILURES
+            && let Some(oldest) = self.oldest_ip_failure
+        {
+            let until = oldest + Duration::seconds(LOGIN_ATTEMPT_WINDOW_SECONDS);
+            if until > now {
+                return Some(LoginThrottle {
+                    reason: LoginThrottleReason::IpRateLimited,
+                    retry_after: remaining(until),
+                });
+            }
+        }
+
+        let last = self.last_account_failure?;
+
+        if self.account_failures >= ACCOUNT_LOCKOUT_FAILURES {
+            let until = last + Duration::seconds(ACCOUNT_LOCKOUT_SECONDS);
+            return (until > now).then(|| LoginThrottle {
+                reason: LoginThrottleReason::AccountLocked,
+                retry_after: remaining(until),
+            });
+        }
+
+        if self.account_failures >= ACCOUNT_DELAY_AFTER_FAILURES {
+            // 1s, 2s, 4s, ... after the last failure, capped.
+            let exponent = (self.account_failures - ACCOUNT_DELAY_AFTER_FAILURES).min(16) as u32;
+            let delay = 2i64.pow(exponent).min(ACCOUNT_MAX_DELAY_SECONDS);
+            let until = last + Duration::seconds(delay);
+            return (until > now).then(|| LoginThrottle {
+                reason: LoginThrottleReason::AccountDelay,
+                retry_after: remaining(until),
+            });
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(failures: i64, last: DateTime<Utc>) -> LoginAttemptStats {
+        LoginAttemptStats {
+            account_failures: failures,
+            last_account_failure: Some(last),
+            ..Default::default()
+        }
+    }
+
+    fn reason_at(stats: &LoginAttemptStats, now: DateTime<Utc>) -> Option<LoginThrottleReason> {
+        stats.throttle(now).map(|throttle| throttle.reason)
+    }
+
+    #[test]
+    fn delays_double_then_lock_the_account() {
+        let last = Utc::now();
+        let after = |seconds| last + Duration::seconds(seconds);
+
+        assert_eq!(reason_at(&account(2, last), last), None);
+
+        let delayed = account(ACCOUNT_DELAY_AFTER_FAILURES, last);
+        assert_eq!(
+            reason_at(&delayed, last),
+            Some(LoginThrottleReason::AccountDelay)
+        );
+        assert_eq!(reason_at(&delayed, after(1)), None);
+
+        let delayed = account(ACCOUNT_DELAY_AFTER_FAILURES + 2, last);
+        assert_eq!(
+            reason_at(&delayed, after(3)),
+            Some(LoginThrottleReason::AccountDelay)
+        );
+        assert_eq!(reason_at(&delayed, after(4)), None);
+
+        let capped = account(ACCOUNT_LOCKOUT_FAILURES - 1, last);
+        assert_eq!(
+            capped.throttle(last).unwrap().retry_after,
+            ACCOUNT_MAX_DELAY_SECONDS + 1
+        );
+
+        let locked = account(ACCOUNT_LOCKOUT_FAILURES, last);
+        assert_eq!(
+            reason_at(&locked, after(ACCOUNT_LOCKOUT_SECONDS - 1)),
+            Some(LoginThrottleReason::AccountLocked)
+        );
+        assert_eq!(reason_at(&locked, after(ACCOUNT_LOCKOUT_SECONDS)), None);
+    }
+
+    #[test]
+    fn too_many_failures_from_one_address_are_rate_limited() {
+        let oldest = Utc::now();
+        let stats = LoginAttemptStats {
+            ip_failures: IP_MAX_FAILURES,
+            oldest_ip_failure: Some(oldest),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            reason_at(&stats, oldest),
+            Some(LoginThrottleReason::IpRateLimited)
+        );
+        assert_eq!(
+            reason_at(
+                &stats,
+                oldest + Duration::seconds(LOGIN_ATTEMPT_WINDOW_SECONDS)
+            ),
+            None
+        );
+    }
+}
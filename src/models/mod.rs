@@ -1,7 +1,37 @@
+pub mod api_key;
+pub mod audit;
+pub mod health;
+pub mod job;
+pub mod login_attempt;
 pub mod multisig;
+pub mod notification;
+pub mod oidc;
+pub mod pagination;
+pub mod passkey;
 pub mod proposal;
+pub mod proposal_event;
+pub mod session;
+pub mod token;
+pub mod two_factor;
 pub mod user;
+pub mod user_token;
+pub mod webhook;
 
+pub use api_key::*;
+pub use audit::*;
+pub use health::*;
+pub use job::*;
+pub use login_attempt::*;
 pub use multisig::*;
+pub use notification::*;
+pub use oidc::*;
+pub use pagination::*;
+pub use passkey::*;
 pub use proposal::*;
+pub use proposal_event::*;
+pub use session::*;
+pub use token::*;
+pub use two_factor::*;
 pub use user::*;
+pub use user_token::*;
+pub use webhook::*;
@@ -9,10 +9,12 @@ pub struct Multisig {
     pub created_by: i64,
     pub owners: Vec<i64>,
     pub threshold: i32,
+    pub require_step_up: bool,
     pub created_at: DateTime<Utc>,
 }
 
 impl Multisig {
+    #[allow(clippy::too_many_arguments)]
     pub fn from_db(
         id: i64,
         name: String,
@@ -20,6 +22,7 @@ impl Multisig {
         created_by: i64,
         owners: Vec<i64>,
         threshold: i32,
+        require_step_up: bool,
         created_at: DateTime<Utc>,
     ) -> Self {
         Self {
@@ -29,6 +32,7 @@ impl Multisig {
             created_by,
             owners,
             threshold,
+            require_step_up,
             created_at,
         }
     }
@@ -48,6 +52,7 @@ pub struct CreateMultisig {
     pub description: Option<String>,
     pub owners: Vec<i64>,
     pub threshold: i32,
+    pub require_step_up: bool,
 }
 
 impl CreateMultisig {
@@ -56,12 +61,14 @@ impl CreateMultisig {
         description: Option<String>,
         owners: Vec<i64>,
         threshold: i32,
+        require_step_up: bool,
     ) -> Self {
         Self {
             name,
             description,
             owners,
             threshold,
+            require_step_up,
         }
     }
 
@@ -0,0 +1,111 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::ProposalStatus;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "digest_frequency", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DigestFrequency {
+    #[default]
+    Immediate,
+    Hourly,
+    Daily,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "notification_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    VoteNeeded,
+    ThresholdReached,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationPreferences {
+    pub user_id: i64,
+    pub email_enabled: bool,
+    pub notify_vote_needed: bool,
+    pub notify_threshold_reached: bool,
+    pub digest_frequency: DigestFrequency,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl NotificationPreferences {
+    pub fn from_db(
+        user_id: i64,
+        email_enabled: bool,
+        notify_vote_needed: bool,
+        notify_threshold_reached: bool,
+        digest_frequency: DigestFrequency,
+        updated_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            user_id,
+            email_enabled,
+            notify_vote_needed,
+            notify_threshold_reached,
+            digest_frequency,
+            updated_at,
+        }
+    }
+
+    // Matches the column defaults for users who never saved preferences.
+    pub fn default_for(user_id: i64) -> Self {
+        Self {
+            user_id,
+            email_enabled: true,
+            notify_vote_needed: true,
+            notify_threshold_reached: true,
+            digest_frequency: DigestFrequency::Immediate,
+            updated_at: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UpdateNotificationPreferences {
+    pub email_enabled: Option<bool>,
+    pub notify_vote_needed: Option<bool>,
+    pub notify_threshold_reached: Option<bool>,
+    pub digest_frequency: Option<DigestFrequency>,
+}
+
+impl UpdateNotificationPreferences {
+    pub fn apply(self, current: NotificationPreferences) -> NotificationPreferences {
+        NotificationPreferences {
+            user_id: current.user_id,
+            email_enabled: self.email_enabled.unwrap_or(current.email_enabled),
+            notify_vote_needed: self
+                .notify_vote_needed
+                .unwrap_or(current.notify_vote_needed),
+            notify_threshold_reached: self
+                .notify_threshold_reached
+                .unwrap_or(current.notify_threshold_reached),
+            digest_frequency: self.digest_frequency.unwrap_or(current.digest_frequency),
+            updated_at: current.updated_at,
+        }
+    }
+}
+
+// A queued notification joined with everything needed to render its email.
+#[derive(Debug, Clone)]
+pub struct PendingNotification {
+    pub id: i64,
+    pub email: String,
+    pub kind: NotificationKind,
+    pub proposal_id: i64,
+    pub proposal_title: String,
+    pub proposal_status: ProposalStatus,
+    pub multisig_name: String,
+}
+
+impl PendingNotification {
+    // A vote request is stale once the proposal has left Active, e.g. while waiting for a digest.
+    pub fn is_still_relevant(&self) -> bool {
+        match self.kind {
+            NotificationKind::VoteNeeded => self.proposal_status == ProposalStatus::Active,
+            NotificationKind::ThresholdReached => self.proposal_status == ProposalStatus::Approved,
+        }
+    }
+}
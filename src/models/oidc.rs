@@ -0,0 +1,15 @@
+pub const OIDC_LOGIN_STATE_TTL_SECONDS: i64 = 10 * 60;
+
+/// Link between an identity at an external OIDC provider and a local user.
+#[derive(Debug, Clone)]
+pub struct UserIdentity {
+    pub id: i64,
+    pub user_id: i64,
+}
+
+/// Secrets generated when redirecting to the provider and needed again on the callback.
+#[derive(Debug, Clone)]
+pub struct OidcLoginState {
+    pub nonce: String,
+    pub code_verifier: String,
+}
@@ -0,0 +1,123 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PAGE_LIMIT: i64 = 20;
+pub const MAX_PAGE_LIMIT: i64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    CreatedAtDesc,
+    CreatedAtAsc,
+}
+
+impl SortOrder {
+    pub fn is_descending(&self) -> bool {
+        matches!(self, SortOrder::CreatedAtDesc)
+    }
+}
+
+/// Keyset position of the last row on a page, encoded as an opaque string for clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: i64,
+}
+
+impl Cursor {
+    pub fn new(created_at: DateTime<Utc>, id: i64) -> Self {
+        Self { created_at, id }
+    }
+
+    pub fn encode(&self) -> String {
+        let raw = format!("{}:{}", self.created_at.timestamp_micros(), self.id);
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    pub fn decode(value: &str) -> Result<Self, String> {
+        let invalid = || "Invalid pagination cursor".to_string();
+
+        let bytes = URL_SAFE_NO_PAD.decode(value).map_err(|_| invalid())?;
+        let raw = String::from_utf8(bytes).map_err(|_| invalid())?;
+        let (micros, id) = raw.split_once(':').ok_or_else(invalid)?;
+
+        let micros = micros.parse::<i64>().map_err(|_| invalid())?;
+        let id = id.parse::<i64>().map_err(|_| invalid())?;
+        let created_at = DateTime::<Utc>::from_timestamp_micros(micros).ok_or_else(invalid)?;
+
+        Ok(Self { created_at, id })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PageRequest {
+    pub limit: i64,
+    pub cursor: Option<Cursor>,
+    pub sort: SortOrder,
+}
+
+impl PageRequest {
+    pub fn new(
+        limit: Option<i64>,
+        cursor: Option<&str>,
+        sort: Option<SortOrder>,
+    ) -> Result<Self, String> {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+
+        if limit <= 0 || limit > MAX_PAGE_LIMIT {
+            return Err(format!("Limit must be between 1 and {}", MAX_PAGE_LIMIT));
+        }
+
+        let cursor = cursor.map(Cursor::decode).transpose()?;
+
+        Ok(Self {
+            limit,
+            cursor,
+            sort: sort.unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub total_count: i64,
+}
+
+impl<T> Page<T> {
+    /// Builds a page from rows fetched with `limit + 1`, using the extra row to detect a next page.
+    pub fn from_rows(
+        mut rows: Vec<T>,
+        limit: i64,
+        total_count: i64,
+        cursor_of: impl Fn(&T) -> Cursor,
+    ) -> Self {
+        let has_more = rows.len() as i64 > limit;
+        if has_more {
+            rows.truncate(limit as usize);
+        }
+
+        let next_cursor = if has_more {
+            rows.last().map(|row| cursor_of(row).encode())
+        } else {
+            None
+        };
+
+        Self {
+            items: rows,
+            next_cursor,
+            total_count,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            total_count: self.total_count,
+        }
+    }
+}
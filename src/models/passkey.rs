@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const WEBAUTHN_CHALLENGE_TTL_SECONDS: i64 = 300;
+pub const MAX_PASSKEY_NAME_LENGTH: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "webauthn_ceremony", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum WebauthnCeremony {
+    Registration,
+    Authentication,
+    StepUp,
+}
+
+#[derive(Debug, Clone)]
+pub struct PasskeyCredential {
+    pub id: i64,
+    pub user_id: i64,
+    pub credential_id: String,
+    pub public_key: Vec<u8>,
+    pub sign_count: i64,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl PasskeyCredential {
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_db(
+        id: i64,
+        user_id: i64,
+        credential_id: String,
+        public_key: Vec<u8>,
+        sign_count: i64,
+        name: String,
+        created_at: DateTime<Utc>,
+        last_used_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            id,
+            user_id,
+            credential_id,
+            public_key,
+            sign_count,
+            name,
+            created_at,
+            last_used_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreatePasskeyCredential {
+    pub user_id: i64,
+    pub credential_id: String,
+    pub public_key: Vec<u8>,
+    pub algorithm: i32,
+    pub sign_count: i64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct WebauthnChallenge {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub ceremony: WebauthnCeremony,
+    pub expires_at: DateTime<Utc>,
+    pub consumed_at: Option<DateTime<Utc>>,
+}
+
+impl WebauthnChallenge {
+    pub fn from_db(
+        id: i64,
+        user_id: Option<i64>,
+        ceremony: WebauthnCeremony,
+        expires_at: DateTime<Utc>,
+        consumed_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            id,
+            user_id,
+            ceremony,
+            expires_at,
+            consumed_at,
+        }
+    }
+
+    pub fn is_usable(&self, ceremony: WebauthnCeremony) -> bool {
+        self.ceremony == ceremony && self.consumed_at.is_none() && self.expires_at > Utc::now()
+    }
+}
+
+/// `PublicKeyCredential` as serialized by the browser, with binary fields base64url encoded.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PublicKeyCredentialJson<R> {
+    pub id: String,
+    #[serde(rename = "rawId")]
+    pub raw_id: Option<String>,
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub response: R,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AttestationResponseJson {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssertionResponseJson {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(rename = "userHandle")]
+    pub user_handle: Option<String>,
+}
+
+pub type RegistrationCredential = PublicKeyCredentialJson<AttestationResponseJson>;
+pub type AssertionCredential = PublicKeyCredentialJson<AssertionResponseJson>;
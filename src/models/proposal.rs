@@ -13,6 +13,26 @@ pub enum ProposalStatus {
 }
 
 impl ProposalStatus {
+    pub const ALL: [ProposalStatus; 6] = [
+        ProposalStatus::Draft,
+        ProposalStatus::Active,
+        ProposalStatus::Approved,
+        ProposalStatus::Executed,
+        ProposalStatus::Expired,
+        ProposalStatus::Rejected,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProposalStatus::Draft => "draft",
+            ProposalStatus::Active => "active",
+            ProposalStatus::Approved => "approved",
+            ProposalStatus::Executed => "executed",
+            ProposalStatus::Expired => "expired",
+            ProposalStatus::Rejected => "rejected",
+        }
+    }
+
     pub fn valid_transitions(&self) -> Vec<ProposalStatus> {
         match self {
             ProposalStatus::Draft => vec![ProposalStatus::Active, ProposalStatus::Rejected],
@@ -44,9 +64,11 @@ pub struct Proposal {
     pub created_at: DateTime<Utc>,
     pub executed_at: Option<DateTime<Utc>>,
     pub transaction_data: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 impl Proposal {
+    #[allow(clippy::too_many_arguments)]
     pub fn from_db(
         id: i64,
         multisig_id: i64,
@@ -57,6 +79,7 @@ impl Proposal {
         created_at: DateTime<Utc>,
         executed_at: Option<DateTime<Utc>>,
         transaction_data: Option<String>,
+        expires_at: Option<DateTime<Utc>>,
     ) -> Self {
         Self {
             id,
@@ -68,18 +91,17 @@ impl Proposal {
             created_at,
             executed_at,
             transaction_data,
+            expires_at,
         }
     }
 
-    pub fn is_terminal(&self) -> bool {
-        matches!(
-            self.status,
-            ProposalStatus::Executed | ProposalStatus::Expired | ProposalStatus::Rejected
-        )
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
     }
 
     pub fn can_be_approved(&self) -> bool {
-        self.status == ProposalStatus::Active
+        self.status == ProposalStatus::Active && !self.is_expired()
     }
 }
 
@@ -107,6 +129,7 @@ pub struct CreateProposal {
     pub title: String,
     pub description: Option<String>,
     pub transaction_data: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 impl CreateProposal {
@@ -114,11 +137,13 @@ impl CreateProposal {
         title: String,
         description: Option<String>,
         transaction_data: Option<String>,
+        expires_at: Option<DateTime<Utc>>,
     ) -> Self {
         Self {
             title,
             description,
             transaction_data,
+            expires_at,
         }
     }
 
@@ -127,6 +152,13 @@ impl CreateProposal {
             return Err("Proposal title cannot be empty".to_string());
         }
 
+        if self
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+        {
+            return Err("Proposal expiry must be in the future".to_string());
+        }
+
         Ok(())
     }
 }
@@ -136,3 +168,46 @@ pub struct UpdateProposalStatus {
     pub status: ProposalStatus,
     pub executed_at: Option<DateTime<Utc>>,
 }
+
+#[derive(Debug, Clone, Default)]
+pub struct ProposalFilter {
+    pub status: Option<ProposalStatus>,
+    pub created_by: Option<i64>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub awaiting_approval_by: Option<i64>,
+}
+
+impl ProposalFilter {
+    pub fn validate(&self) -> Result<(), String> {
+        if let (Some(after), Some(before)) = (self.created_after, self.created_before)
+            && after > before
+        {
+            return Err("created_after must not be later than created_before".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InboxAction {
+    Approve,
+    Execute,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboxItem {
+    pub proposal_id: i64,
+    pub multisig_id: i64,
+    pub multisig_name: String,
+    pub title: String,
+    pub status: ProposalStatus,
+    pub created_by: i64,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub approval_count: i64,
+    pub threshold: i32,
+    pub action: InboxAction,
+}
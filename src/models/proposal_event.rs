@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::ProposalStatus;
+
+pub const PROPOSAL_EVENTS_CHANNEL: &str = "proposal_events";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "proposal_event_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalEventType {
+    Created,
+    Activated,
+    Approved,
+    Revoked,
+    Rejected,
+    Executed,
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProposalEvent {
+    pub id: i64,
+    pub proposal_id: i64,
+    pub event_type: ProposalEventType,
+    pub actor_id: Option<i64>,
+    pub old_status: Option<ProposalStatus>,
+    pub new_status: Option<ProposalStatus>,
+    pub metadata: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ProposalEvent {
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_db(
+        id: i64,
+        proposal_id: i64,
+        event_type: ProposalEventType,
+        actor_id: Option<i64>,
+        old_status: Option<ProposalStatus>,
+        new_status: Option<ProposalStatus>,
+        metadata: serde_json::Value,
+        created_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id,
+            proposal_id,
+            event_type,
+            actor_id,
+            old_status,
+            new_status,
+            metadata,
+            created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateProposalEvent {
+    pub proposal_id: i64,
+    pub event_type: ProposalEventType,
+    pub actor_id: Option<i64>,
+    pub old_status: Option<ProposalStatus>,
+    pub new_status: Option<ProposalStatus>,
+    pub metadata: serde_json::Value,
+}
+
+impl CreateProposalEvent {
+    pub fn new(
+        proposal_id: i64,
+        event_type: ProposalEventType,
+        actor_id: Option<i64>,
+        old_status: Option<ProposalStatus>,
+        new_status: Option<ProposalStatus>,
+    ) -> Self {
+        Self {
+            proposal_id,
+            event_type,
+            actor_id,
+            old_status,
+            new_status,
+            metadata: serde_json::json!({}),
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: serde_json::Value) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+// Sent through Postgres NOTIFY so every server instance can fan events out to its own streams.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProposalEventNotification {
+    pub multisig_id: i64,
+    pub event: ProposalEvent,
+}
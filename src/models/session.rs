@@ -0,0 +1,47 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How long a completed step-up verification authorizes sensitive proposal actions.
+pub const STEP_UP_TTL_SECONDS: i64 = 300;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: i64,
+    pub user_id: i64,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub step_up_at: Option<DateTime<Utc>>,
+}
+
+impl Session {
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_db(
+        id: i64,
+        user_id: i64,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+        created_at: DateTime<Utc>,
+        last_seen_at: DateTime<Utc>,
+        revoked_at: Option<DateTime<Utc>>,
+        step_up_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            id,
+            user_id,
+            user_agent,
+            ip_address,
+            created_at,
+            last_seen_at,
+            revoked_at,
+            step_up_at,
+        }
+    }
+
+    pub fn has_recent_step_up(&self) -> bool {
+        self.step_up_at
+            .is_some_and(|at| at > Utc::now() - Duration::seconds(STEP_UP_TTL_SECONDS))
+    }
+}
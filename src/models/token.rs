@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: i64,
+    pub iss: String,
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    pub jti: String,
+    pub sid: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenPair {
+    pub user_id: i64,
+    pub token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RefreshToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub family_id: String,
+    pub session_id: Option<i64>,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl RefreshToken {
+    // A token that was already exchanged or revoked being presented again means it leaked.
+    pub fn is_reused(&self) -> bool {
+        self.used_at.is_some() || self.revoked_at.is_some()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateRefreshToken {
+    pub user_id: i64,
+    pub session_id: i64,
+    pub family_id: String,
+    pub parent_id: Option<i64>,
+    pub token_hash: String,
+    pub access_jti: String,
+    pub expires_at: DateTime<Utc>,
+}
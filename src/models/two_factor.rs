@@ -0,0 +1,108 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const TOTP_ISSUER: &str = "Solana Multisig";
+pub const TOTP_DIGITS: usize = 6;
+pub const TOTP_STEP_SECONDS: u64 = 30;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+pub const LOGIN_CHALLENGE_TTL_SECONDS: i64 = 300;
+pub const MAX_LOGIN_CHALLENGE_ATTEMPTS: i32 = 5;
+
+#[derive(Debug, Clone)]
+pub struct UserTotp {
+    pub user_id: i64,
+    pub secret: String,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub last_used_step: Option<i64>,
+}
+
+impl UserTotp {
+    pub fn from_db(
+        user_id: i64,
+        secret: String,
+        confirmed_at: Option<DateTime<Utc>>,
+        last_used_step: Option<i64>,
+    ) -> Self {
+        Self {
+            user_id,
+            secret,
+            confirmed_at,
+            last_used_step,
+        }
+    }
+
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+}
+
+/// Shown once while enrolling so the user can add the secret to an authenticator app.
+#[derive(Debug, Clone, Serialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct LoginChallenge {
+    pub id: i64,
+    pub user_id: i64,
+    pub attempts: i32,
+    pub expires_at: DateTime<Utc>,
+    pub consumed_at: Option<DateTime<Utc>>,
+}
+
+impl LoginChallenge {
+    pub fn from_db(
+        id: i64,
+        user_id: i64,
+        attempts: i32,
+        expires_at: DateTime<Utc>,
+        consumed_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            id,
+            user_id,
+            attempts,
+            expires_at,
+            consumed_at,
+        }
+    }
+
+    pub fn is_usable(&self) -> bool {
+        self.consumed_at.is_none()
+            && self.expires_at > Utc::now()
+            && self.attempts < MAX_LOGIN_CHALLENGE_ATTEMPTS
+    }
+}
+
+/// Returned by `POST /auth/login` instead of tokens when a second factor is required.
+#[derive(Debug, Clone, Serialize)]
+pub struct MfaChallenge {
+    pub mfa_required: bool,
+    pub challenge_token: String,
+    pub methods: Vec<String>,
+    pub expires_in: i64,
+}
+
+/// Second factor submitted against a login challenge or for step-up.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SecondFactor {
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+impl SecondFactor {
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.code, &self.recovery_code) {
+            (Some(_), None) | (None, Some(_)) => Ok(()),
+            _ => Err("Provide exactly one of code or recovery_code".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TwoFactorStatus {
+    pub totp_enabled: bool,
+    pub recovery_codes_remaining: i64,
+}
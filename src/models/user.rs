@@ -7,6 +7,7 @@ pub struct User {
     pub email: String,
     pub created_at: DateTime<Utc>,
     pub last_login_at: Option<DateTime<Utc>>,
+    pub email_verified_at: Option<DateTime<Utc>>,
 }
 
 impl User {
@@ -15,14 +16,20 @@ impl User {
         email: String,
         created_at: DateTime<Utc>,
         last_login_at: Option<DateTime<Utc>>,
+        email_verified_at: Option<DateTime<Utc>>,
     ) -> Self {
         Self {
             id,
             email,
             created_at,
             last_login_at,
+            email_verified_at,
         }
     }
+
+    pub fn is_email_verified(&self) -> bool {
+        self.email_verified_at.is_some()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -32,15 +39,61 @@ pub struct CreateUser {
 }
 
 impl CreateUser {
-    pub fn new(email: String, password_hash: String) -> Self {
-        Self {
-            email: email.to_lowercase(),
+    pub fn new(email: String, password_hash: String) -> Result<Self, String> {
+        let email = email.trim().to_lowercase();
+        validate_email(&email)?;
+
+        Ok(Self {
+            email,
             password_hash,
-        }
+        })
     }
 }
 
+pub const MAX_EMAIL_LENGTH: usize = 254;
+
+// Deliberately loose: one `@`, a non-empty local part and a dotted domain. Whether the address
+// actually receives mail is settled by email verification.
+pub fn validate_email(email: &str) -> Result<(), String> {
+    if email.is_empty() {
+        return Err("Email cannot be empty".to_string());
+    }
+
+    if email.len() > MAX_EMAIL_LENGTH {
+        return Err(format!(
+            "Email cannot exceed {} characters",
+            MAX_EMAIL_LENGTH
+        ));
+    }
+
+    let Some((local, domain)) = email.split_once('@') else {
+        return Err("Email must contain '@'".to_string());
+    };
+
+    let domain_valid = domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+
+    if local.is_empty()
+        || local.len() > 64
+        || local
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || c == '@')
+        || !domain_valid
+    {
+        return Err("Email address is not valid".to_string());
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub struct UpdateUserLogin {
     pub last_login_at: DateTime<Utc>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
 }
@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const EMAIL_VERIFICATION_TTL_SECONDS: i64 = 24 * 60 * 60;
+pub const PASSWORD_RESET_TTL_SECONDS: i64 = 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "user_token_purpose", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum UserTokenPurpose {
+    EmailVerification,
+    PasswordReset,
+}
+
+impl UserTokenPurpose {
+    pub fn ttl_seconds(&self) -> i64 {
+        match self {
+            UserTokenPurpose::EmailVerification => EMAIL_VERIFICATION_TTL_SECONDS,
+            UserTokenPurpose::PasswordReset => PASSWORD_RESET_TTL_SECONDS,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UserToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+impl UserToken {
+    pub fn from_db(
+        id: i64,
+        user_id: i64,
+        expires_at: DateTime<Utc>,
+        used_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            id,
+            user_id,
+            expires_at,
+            used_at,
+        }
+    }
+
+    pub fn is_usable(&self) -> bool {
+        self.used_at.is_none() && self.expires_at > Utc::now()
+    }
+}
@@ -0,0 +1,206 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{ProposalEvent, ProposalEventType, ProposalStatus};
+
+pub const WEBHOOK_MAX_ATTEMPTS: i32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEventType {
+    #[serde(rename = "proposal.created")]
+    Created,
+    #[serde(rename = "proposal.activated")]
+    Activated,
+    #[serde(rename = "proposal.approved")]
+    Approved,
+    #[serde(rename = "proposal.revoked")]
+    Revoked,
+    #[serde(rename = "proposal.threshold_reached")]
+    ThresholdReached,
+    #[serde(rename = "proposal.executed")]
+    Executed,
+    #[serde(rename = "proposal.rejected")]
+    Rejected,
+    #[serde(rename = "proposal.expired")]
+    Expired,
+}
+
+impl WebhookEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventType::Created => "proposal.created",
+            WebhookEventType::Activated => "proposal.activated",
+            WebhookEventType::Approved => "proposal.approved",
+            WebhookEventType::Revoked => "proposal.revoked",
+            WebhookEventType::ThresholdReached => "proposal.threshold_reached",
+            WebhookEventType::Executed => "proposal.executed",
+            WebhookEventType::Rejected => "proposal.rejected",
+            WebhookEventType::Expired => "proposal.expired",
+        }
+    }
+
+    // An approval that moves the proposal to Approved also emits `threshold_reached`.
+    pub fn from_proposal_event(event: &ProposalEvent) -> Vec<WebhookEventType> {
+        match event.event_type {
+            ProposalEventType::Created => vec![WebhookEventType::Created],
+            ProposalEventType::Activated => vec![WebhookEventType::Activated],
+            ProposalEventType::Approved if event.new_status == Some(ProposalStatus::Approved) => {
+                vec![
+                    WebhookEventType::Approved,
+                    WebhookEventType::ThresholdReached,
+                ]
+            }
+            ProposalEventType::Approved => vec![WebhookEventType::Approved],
+            ProposalEventType::Revoked => vec![WebhookEventType::Revoked],
+            ProposalEventType::Rejected => vec![WebhookEventType::Rejected],
+            ProposalEventType::Executed => vec![WebhookEventType::Executed],
+            ProposalEventType::Expired => vec![WebhookEventType::Expired],
+        }
+    }
+
+    pub fn payload(&self, multisig_id: i64, event: &ProposalEvent) -> serde_json::Value {
+        serde_json::json!({
+            "event": self.as_str(),
+            "event_id": event.id,
+            "occurred_at": event.created_at,
+            "multisig_id": multisig_id,
+            "proposal_id": event.proposal_id,
+            "actor_id": event.actor_id,
+            "old_status": event.old_status,
+            "new_status": event.new_status,
+            "metadata": event.metadata,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: i64,
+    pub multisig_id: i64,
+    pub url: String,
+    pub secret: String,
+    pub event_types: Vec<String>,
+    pub is_active: bool,
+    pub created_by: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Webhook {
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_db(
+        id: i64,
+        multisig_id: i64,
+        url: String,
+        secret: String,
+        event_types: Vec<String>,
+        is_active: bool,
+        created_by: i64,
+        created_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id,
+            multisig_id,
+            url,
+            secret,
+            event_types,
+            is_active,
+            created_by,
+            created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateWebhook {
+    pub url: String,
+    pub event_types: Vec<WebhookEventType>,
+}
+
+impl CreateWebhook {
+    pub fn new(url: String, event_types: Vec<WebhookEventType>) -> Self {
+        Self {
+            url: url.trim().to_string(),
+            event_types,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.url.starts_with("https://") || self.url.starts_with("http://")) {
+            return Err("Webhook URL must start with http:// or https://".to_string());
+        }
+
+        if self.url.len() > 2048 {
+            return Err("Webhook URL is too long".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "webhook_delivery_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+// Retry delay doubles from 30s and is capped at one hour.
+pub fn webhook_retry_delay(attempts: i32) -> chrono::Duration {
+    let exponent = attempts.clamp(0, 7) as u32;
+    let seconds = (30_i64 * 2_i64.pow(exponent)).min(3600);
+    chrono::Duration::seconds(seconds)
+}
+
+#[derive(Debug, Clone)]
+pub struct ClaimedWebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDeliveryAttempt {
+    pub id: i64,
+    pub delivery_id: i64,
+    pub attempted_at: DateTime<Utc>,
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeliveryOutcome {
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+}
+
+impl DeliveryOutcome {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+            && self
+                .status_code
+                .is_some_and(|code| (200..300).contains(&code))
+    }
+}
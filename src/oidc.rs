@@ -0,0 +1,294 @@
+use std::time::{Duration, Instant};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+
+use crate::config::OidcConfig;
+use crate::errors::{AppError, AppResult};
+
+const DISCOVERY_TTL: Duration = Duration::from_secs(3600);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+const ID_TOKEN_LEEWAY_SECONDS: u64 = 60;
+
+/// Fields of the discovery document this server relies on.
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+    #[serde(default)]
+    code_challenge_methods_supported: Option<Vec<String>>,
+}
+
+struct CachedMetadata {
+    metadata: ProviderMetadata,
+    fetched_at: Instant,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: Option<bool>,
+    pub nonce: Option<String>,
+    pub azp: Option<String>,
+}
+
+// External identity provider for SSO logins, enabled by `oidc.issuer`.
+//
+// The discovery document is fetched from `{issuer}/.well-known/openid-configuration` and cached,
+// and the signing keys are refetched whenever an ID token names a key id that is not cached yet.
+pub struct OidcProvider {
+    issuer: String,
+    client_id: String,
+    client_secret: Option<String>,
+    redirect_uri: String,
+    scopes: String,
+    http: reqwest::Client,
+    metadata: RwLock<Option<CachedMetadata>>,
+    jwks: RwLock<JwkSet>,
+}
+
+impl OidcProvider {
+    pub fn from_config(config: &OidcConfig, app_base_url: &str) -> AppResult<Option<Self>> {
+        let Some(issuer) = config.issuer.clone() else {
+            return Ok(None);
+        };
+
+        let client_id = config.client_id.clone().ok_or_else(|| {
+            AppError::Internal("oidc.client_id must be set with oidc.issuer".into())
+        })?;
+        let redirect_uri = config
+            .redirect_uri
+            .clone()
+            .unwrap_or_else(|| format!("{}/auth/oidc/callback", app_base_url));
+        let http = reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to build OIDC HTTP client: {}", e)))?;
+
+        Ok(Some(Self {
+            issuer,
+            client_id,
+            client_secret: config.client_secret.clone(),
+            redirect_uri,
+            scopes: config.scopes.clone(),
+            http,
+            metadata: RwLock::new(None),
+            jwks: RwLock::new(JwkSet { keys: Vec::new() }),
+        }))
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// URL of the provider's authorization endpoint for an S256 PKCE login.
+    pub async fn authorization_url(
+        &self,
+        state: &str,
+        nonce: &str,
+        code_verifier: &str,
+    ) -> AppResult<String> {
+        let metadata = self.metadata().await?;
+
+        let url = reqwest::Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", self.client_id.as_str()),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("scope", self.scopes.as_str()),
+                ("state", state),
+                ("nonce", nonce),
+                ("code_challenge", &pkce_challenge(code_verifier)),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| AppError::Internal(format!("Invalid authorization endpoint: {}", e)))?;
+
+        Ok(url.to_string())
+    }
+
+    /// Redeems an authorization code and returns the raw ID token.
+    pub async fn exchange_code(&self, code: &str, code_verifier: &str) -> AppResult<String> {
+        let metadata = self.metadata().await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.redirect_uri.as_str()),
+            ("client_id", self.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+
+        let response = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| AppError::Internal(format!("OIDC token request failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(AppError::Authentication(format!(
+                "Identity provider rejected the authorization code ({})",
+                response.status()
+            )));
+        }
+
+        let tokens: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::Internal(format!("Invalid OIDC token response: {}", e)))?;
+
+        tokens.id_token.ok_or_else(|| {
+            AppError::Authentication("Identity provider did not return an ID token".to_string())
+        })
+    }
+
+    /// Checks signature, issuer, audience, expiry and nonce of an ID token.
+    pub async fn validate_id_token(&self, id_token: &str, nonce: &str) -> AppResult<IdTokenClaims> {
+        let invalid =
+            |reason: &str| AppError::Authentication(format!("Invalid ID token: {}", reason));
+
+        let header = decode_header(id_token).map_err(|_| invalid("malformed header"))?;
+        if !matches!(
+            header.alg,
+            Algorithm::RS256
+                | Algorithm::RS384
+                | Algorithm::RS512
+                | Algorithm::PS256
+                | Algorithm::PS384
+                | Algorithm::PS512
+                | Algorithm::ES256
+                | Algorithm::ES384
+                | Algorithm::EdDSA
+        ) {
+            return Err(invalid("unsupported signing algorithm"));
+        }
+
+        let kid = header.kid.ok_or_else(|| invalid("missing key id"))?;
+        let key = self.decoding_key(&kid).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_required_spec_claims(&["exp", "iat", "iss", "aud", "sub"]);
+        validation.leeway = ID_TOKEN_LEEWAY_SECONDS;
+
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|e| invalid(&e.to_string()))?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(invalid("nonce mismatch"));
+        }
+
+        if claims
+            .azp
+            .as_ref()
+            .is_some_and(|azp| *azp != self.client_id)
+        {
+            return Err(invalid("issued to a different client"));
+        }
+
+        Ok(claims)
+    }
+
+    async fn metadata(&self) -> AppResult<ProviderMetadata> {
+        if let Some(cached) = self.metadata.read().await.as_ref()
+            && cached.fetched_at.elapsed() < DISCOVERY_TTL
+        {
+            return Ok(cached.metadata.clone());
+        }
+
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.issuer.trim_end_matches('/')
+        );
+        let metadata: ProviderMetadata = self.fetch_json(&url).await?;
+
+        // The spec requires an exact match, trailing slash included, since ID tokens carry it too.
+        if metadata.issuer != self.issuer {
+            return Err(AppError::Internal(format!(
+                "OIDC discovery issuer {} does not match oidc.issuer",
+                metadata.issuer
+            )));
+        }
+
+        if let Some(methods) = &metadata.code_challenge_methods_supported
+            && !methods.iter().any(|m| m == "S256")
+        {
+            return Err(AppError::Internal(
+                "Identity provider does not support S256 PKCE".into(),
+            ));
+        }
+
+        *self.metadata.write().await = Some(CachedMetadata {
+            metadata: metadata.clone(),
+            fetched_at: Instant::now(),
+        });
+
+        Ok(metadata)
+    }
+
+    // Providers rotate keys by publishing the new one before signing with it, so an unknown key id
+    // triggers one refetch before the token is rejected.
+    async fn decoding_key(&self, kid: &str) -> AppResult<DecodingKey> {
+        if let Some(jwk) = self.jwks.read().await.find(kid) {
+            return DecodingKey::from_jwk(jwk)
+                .map_err(|e| AppError::Internal(format!("Unusable OIDC signing key: {}", e)));
+        }
+
+        let metadata = self.metadata().await?;
+        let jwks: JwkSet = self.fetch_json(&metadata.jwks_uri).await?;
+
+        let key = match jwks.find(kid) {
+            Some(jwk) => DecodingKey::from_jwk(jwk)
+                .map_err(|e| AppError::Internal(format!("Unusable OIDC signing key: {}", e)))?,
+            None => {
+                return Err(AppError::Authentication(
+                    "Invalid ID token: unknown signing key".to_string(),
+                ));
+            }
+        };
+
+        *self.jwks.write().await = jwks;
+
+        Ok(key)
+    }
+
+    async fn fetch_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> AppResult<T> {
+        let response = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| AppError::Internal(format!("Failed to fetch {}: {}", url, e)))?;
+
+        response
+            .json()
+            .await
+            .map_err(|e| AppError::Internal(format!("Invalid JSON from {}: {}", url, e)))
+    }
+}
+
+fn pkce_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
@@ -0,0 +1,167 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version, password_hash::rand_core::OsRng};
+use sha1::{Digest, Sha1};
+
+use crate::config::PasswordConfig;
+use crate::errors::{AppError, AppResult};
+
+const HASH_PREFIX_LENGTH: usize = 5;
+
+// Password rules and Argon2 settings from the `[password]` config section, built once at startup.
+// The breached list is indexed by 5 character hash prefix, the same k-anonymity split the Pwned
+// Passwords range API uses.
+pub struct PasswordPolicy {
+    min_length: usize,
+    max_length: usize,
+    breached: HashMap<String, HashSet<String>>,
+    params: Params,
+    dummy_hash: String,
+}
+
+impl PasswordPolicy {
+    pub fn from_config(config: &PasswordConfig) -> AppResult<Self> {
+        let params = Params::new(
+            config.argon2_memory_kib,
+            config.argon2_iterations,
+            config.argon2_parallelism,
+            None,
+        )
+        .map_err(|e| AppError::Internal(format!("Invalid Argon2 parameters: {}", e)))?;
+
+        let breached = match &config.breached_list {
+            Some(path) => load_breached_list(path)?,
+            None => HashMap::new(),
+        };
+
+        let mut policy = Self {
+            min_length: config.min_length,
+            max_length: config.max_length,
+            breached,
+            params,
+            dummy_hash: String::new(),
+        };
+        policy.dummy_hash = policy.hash("dummy password for unknown accounts")?;
+
+        Ok(policy)
+    }
+
+    pub fn validate(&self, password: &str, email: &str) -> Result<(), String> {
+        let length = password.chars().count();
+
+        if length < self.min_length {
+            return Err(format!(
+                "Password must be at least {} characters",
+                self.min_length
+            ));
+        }
+
+        if length > self.max_length {
+            return Err(format!(
+                "Password cannot exceed {} characters",
+                self.max_length
+            ));
+        }
+
+        if password.trim().eq_ignore_ascii_case(email.trim()) {
+            return Err("Password cannot be the same as the email address".to_string());
+        }
+
+        if self.is_breached(password) {
+            return Err(
+                "This password has appeared in a data breach; choose a different one".to_string(),
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn hash(&self, password: &str) -> AppResult<String> {
+        let salt = SaltString::generate(&mut OsRng);
+
+        self.argon2()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|_| AppError::Internal("Password hashing failed".to_string()))
+    }
+
+    // Verification reads the parameters from the stored hash, so old hashes keep working.
+    pub fn verify(&self, password: &str, password_hash: &str) -> AppResult<bool> {
+        let parsed = PasswordHash::new(password_hash)
+            .map_err(|_| AppError::Internal("Stored password hash is invalid".to_string()))?;
+
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok())
+    }
+
+    // Logins for unknown emails verify against this so they take as long as a wrong password.
+    pub fn verify_dummy(&self, password: &str) -> AppResult<bool> {
+        self.verify(password, &self.dummy_hash)
+    }
+
+    pub fn needs_rehash(&self, password_hash: &str) -> bool {
+        let Ok(parsed) = PasswordHash::new(password_hash) else {
+            return true;
+        };
+
+        if parsed.algorithm != Algorithm::Argon2id.ident()
+            || parsed.version != Some(Version::V0x13.into())
+        {
+            return true;
+        }
+
+        match Params::try_from(&parsed) {
+            Ok(params) => {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+
+    fn is_breached(&self, password: &str) -> bool {
+        if self.breached.is_empty() {
+            return false;
+        }
+
+        let digest = hex::encode_upper(Sha1::digest(password.as_bytes()));
+        let (prefix, suffix) = digest.split_at(HASH_PREFIX_LENGTH);
+
+        self.breached
+            .get(prefix)
+            .is_some_and(|suffixes| suffixes.contains(suffix))
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+}
+
+fn load_breached_list(path: &str) -> AppResult<HashMap<String, HashSet<String>>> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        AppError::Internal(format!(
+            "Failed to read password breached list {}: {}",
+            path, e
+        ))
+    })?;
+
+    let mut breached: HashMap<String, HashSet<String>> = HashMap::new();
+    for line in contents.lines() {
+        let hash = line.split(':').next().unwrap_or("").trim().to_uppercase();
+        if hash.len() != 40 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            continue;
+        }
+
+        let (prefix, suffix) = hash.split_at(HASH_PREFIX_LENGTH);
+        breached
+            .entry(prefix.to_string())
+            .or_default()
+            .insert(suffix.to_string());
+    }
+
+    Ok(breached)
+}
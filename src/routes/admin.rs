@@ -0,0 +1,22 @@
+use actix_web::{HttpResponse, Result as ActixResult, post, web};
+
+use crate::auth_middleware::AdminUser;
+use crate::db::DbPool;
+use crate::services::LoginThrottleService;
+
+#[post("/users/{id}/unlock")]
+pub async fn unlock_user_login(
+    pool: web::Data<DbPool>,
+    admin: AdminUser,
+    path: web::Path<i64>,
+) -> ActixResult<HttpResponse> {
+    let user_id = path.into_inner();
+
+    // Only a missing user is a 404; database and audit failures keep their own status.
+    let cleared = LoginThrottleService::unlock_user(&pool, admin.user_id, user_id).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "user_id": user_id,
+        "cleared_failures": cleared,
+    })))
+}
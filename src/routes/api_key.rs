@@ -0,0 +1,123 @@
+use actix_web::{HttpResponse, Responder, ResponseError, delete, get, patch, post, web};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::auth_middleware::SessionUser;
+use crate::models::{ApiKey, ApiKeyScope, CreateApiKey, UpdateApiKey};
+use crate::services::ApiKeyService;
+
+#[derive(Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub multisig_id: Option<i64>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateApiKeyRequest {
+    pub name: Option<String>,
+    pub scopes: Option<Vec<ApiKeyScope>>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct ApiKeyResponse {
+    pub id: i64,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub multisig_id: Option<i64>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            id: key.id,
+            name: key.name,
+            prefix: key.prefix,
+            scopes: key.scopes,
+            multisig_id: key.multisig_id,
+            expires_at: key.expires_at,
+            last_used_at: key.last_used_at,
+            created_at: key.created_at,
+        }
+    }
+}
+
+#[post("/api-keys")]
+pub async fn create_api_key(
+    pool: web::Data<PgPool>,
+    user: SessionUser,
+    body: web::Json<CreateApiKeyRequest>,
+) -> impl Responder {
+    let body = body.into_inner();
+    let key_data = CreateApiKey::new(body.name, body.scopes, body.multisig_id, body.expires_at);
+
+    match ApiKeyService::create_api_key(&pool, user.user_id, key_data).await {
+        Ok((api_key, secret)) => HttpResponse::Created().json(serde_json::json!({
+            "api_key": ApiKeyResponse::from(api_key),
+            "key": secret,
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[get("/api-keys")]
+pub async fn list_api_keys(pool: web::Data<PgPool>, user: SessionUser) -> impl Responder {
+    match ApiKeyService::list_api_keys(&pool, user.user_id).await {
+        Ok(api_keys) => {
+            let responses: Vec<ApiKeyResponse> =
+                api_keys.into_iter().map(ApiKeyResponse::from).collect();
+            HttpResponse::Ok().json(responses)
+        }
+        Err(e) => e.error_response(),
+    }
+}
+
+#[get("/api-keys/{id}")]
+pub async fn get_api_key(
+    pool: web::Data<PgPool>,
+    user: SessionUser,
+    path: web::Path<i64>,
+) -> impl Responder {
+    match ApiKeyService::get_api_key(&pool, user.user_id, path.into_inner()).await {
+        Ok(api_key) => HttpResponse::Ok().json(ApiKeyResponse::from(api_key)),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[patch("/api-keys/{id}")]
+pub async fn update_api_key(
+    pool: web::Data<PgPool>,
+    user: SessionUser,
+    path: web::Path<i64>,
+    body: web::Json<UpdateApiKeyRequest>,
+) -> impl Responder {
+    let body = body.into_inner();
+    let changes = UpdateApiKey {
+        name: body.name,
+        scopes: body.scopes,
+        expires_at: body.expires_at,
+    };
+
+    match ApiKeyService::update_api_key(&pool, user.user_id, path.into_inner(), changes).await {
+        Ok(api_key) => HttpResponse::Ok().json(ApiKeyResponse::from(api_key)),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[delete("/api-keys/{id}")]
+pub async fn revoke_api_key(
+    pool: web::Data<PgPool>,
+    user: SessionUser,
+    path: web::Path<i64>,
+) -> impl Responder {
+    match ApiKeyService::revoke_api_key(&pool, user.user_id, path.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => e.error_response(),
+    }
+}
@@ -0,0 +1,83 @@
+use actix_web::{HttpResponse, Result as ActixResult, get, web};
+use serde::{Deserialize, Serialize};
+
+use crate::auth_middleware::AdminUser;
+use crate::db::DbPool;
+use crate::models::AuditSigningKey;
+use crate::services::AuditService;
+
+#[derive(Deserialize)]
+pub struct ListAuditEntriesQuery {
+    pub after_id: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct AuditEntryResponse {
+    pub id: i64,
+    pub occurred_at: chrono::DateTime<chrono::Utc>,
+    pub actor_id: Option<i64>,
+    pub action: String,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<i64>,
+    pub details: serde_json::Value,
+    pub prev_hash: String,
+    pub entry_hash: String,
+}
+
+#[get("/entries")]
+pub async fn list_audit_entries(
+    pool: web::Data<DbPool>,
+    _admin: AdminUser,
+    query: web::Query<ListAuditEntriesQuery>,
+) -> ActixResult<HttpResponse> {
+    let entries = AuditService::list_entries(
+        &pool,
+        query.after_id.unwrap_or(0),
+        query.limit.unwrap_or(100),
+    )
+    .await
+    .map_err(actix_web::error::ErrorBadRequest)?;
+
+    let responses: Vec<AuditEntryResponse> = entries
+        .into_iter()
+        .map(|e| AuditEntryResponse {
+            id: e.id,
+            occurred_at: e.occurred_at,
+            actor_id: e.actor_id,
+            action: e.action,
+            entity_type: e.entity_type,
+            entity_id: e.entity_id,
+            details: e.details,
+            prev_hash: e.prev_hash,
+            entry_hash: e.entry_hash,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(responses))
+}
+
+#[get("/verify")]
+pub async fn verify_audit_log(
+    pool: web::Data<DbPool>,
+    _admin: AdminUser,
+) -> ActixResult<HttpResponse> {
+    let verification = AuditService::verify_chain(&pool)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(verification))
+}
+
+#[get("/checkpoint")]
+pub async fn get_audit_checkpoint(
+    pool: web::Data<DbPool>,
+    key: web::Data<AuditSigningKey>,
+    admin: AdminUser,
+) -> ActixResult<HttpResponse> {
+    let checkpoint = AuditService::create_checkpoint(&pool, &key, Some(admin.user_id))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(checkpoint))
+}
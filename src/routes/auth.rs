@@ -1,16 +1,22 @@
-use actix_web::{HttpResponse, Responder, get, post, web};
-use argon2::{
-    Argon2,
-    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
-};
-use jsonwebtoken::{EncodingKey, Header, encode};
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post, web};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlx::PgPool;
+use tracing::{error, warn};
 
-use crate::auth_middleware::AuthUser;
-use crate::db::{create_user, find_user_by_id, get_user_password_hash, update_user_login};
-use crate::models::{CreateUser, UpdateUserLogin};
+use crate::auth_middleware::SessionUser;
+use crate::config::Config;
+use crate::db::{find_user_by_id, get_user_password_hash};
+use crate::errors::{AppError, AppResult};
+use crate::jwt::JwtKeySet;
+use crate::mailer::SharedMailer;
+use crate::models::{AuditAction, CreateAuditEntry, CreateUser, LoginThrottle, UpdateUserLogin};
+use crate::oidc::OidcProvider;
+use crate::password::PasswordPolicy;
+use crate::services::{
+    AccountService, AuditService, LoginThrottleService, OidcService, SessionService, TokenService,
+    TwoFactorService,
+};
 use chrono::Utc;
 
 #[derive(Deserialize)]
@@ -25,51 +31,116 @@ pub struct LoginRequest {
     pub password: String,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct Claims {
-    pub sub: i64,
-    pub exp: usize,
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub new_password: String,
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+// Only read while the account has no second factor yet; afterwards a recent step-up is required.
+#[derive(Deserialize, Default)]
+pub struct ReauthenticateRequest {
+    pub current_password: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Serialize)]
+pub struct SessionResponse {
+    pub id: i64,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: chrono::DateTime<Utc>,
+    pub last_seen_at: chrono::DateTime<Utc>,
+    pub current: bool,
 }
 
 #[post("/register")]
-pub async fn register(pool: web::Data<PgPool>, body: web::Json<RegisterRequest>) -> impl Responder {
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    let password_hash = argon2
-        .hash_password(body.password.as_bytes(), &salt)
-        .map_err(|_| {
-            HttpResponse::InternalServerError().json(json!({"error": "Password hashing failed"}))
-        })
-        .unwrap()
-        .to_string();
-
-    let create_user_data = CreateUser::new(body.email.clone(), password_hash);
-
-    match create_user(&pool, create_user_data).await {
+pub async fn register(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    keys: web::Data<JwtKeySet>,
+    config: web::Data<Config>,
+    mailer: web::Data<SharedMailer>,
+    policy: web::Data<PasswordPolicy>,
+    body: web::Json<RegisterRequest>,
+) -> impl Responder {
+    if let Err(msg) = policy.validate(&body.password, &body.email) {
+        return HttpResponse::BadRequest().json(json!({ "error": msg }));
+    }
+
+    let password_hash = match policy.hash(&body.password) {
+        Ok(hash) => hash,
+        Err(_) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"error": "Password hashing failed"}));
+        }
+    };
+
+    let create_user_data = match CreateUser::new(body.email.clone(), password_hash) {
+        Ok(data) => data,
+        Err(msg) => return HttpResponse::BadRequest().json(json!({ "error": msg })),
+    };
+
+    match AccountService::register(&pool, create_user_data).await {
         Ok(user) => {
-            let expiration = Utc::now()
-                .checked_add_signed(chrono::Duration::hours(24))
-                .unwrap()
-                .timestamp() as usize;
-
-            let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET not set");
-            let token = encode(
-                &Header::default(),
-                &Claims {
-                    sub: user.id,
-                    exp: expiration,
-                },
-                &EncodingKey::from_secret(secret.as_bytes()),
+            if let Err(e) = AccountService::send_email_verification(
+                &pool,
+                &mailer,
+                &config.server.app_base_url,
+                user.id,
             )
-            .map_err(|_| {
-                HttpResponse::InternalServerError().json(json!({"error": "Token creation failed"}))
-            })
-            .unwrap();
+            .await
+            {
+                error!(
+                    "Failed to send verification email to user {}: {}",
+                    user.id, e
+                );
+            }
 
-            HttpResponse::Created().json(json!({
-                "user_id": user.id,
-                "token": token
-            }))
+            let audit_entry = CreateAuditEntry::new(AuditAction::LoginSucceeded, Some(user.id))
+                .with_entity("user", user.id)
+                .with_details(json!({ "method": "registration" }));
+            match TokenService::start_session(
+                &pool,
+                &keys,
+                user.id,
+                login_update(&req),
+                audit_entry,
+            )
+            .await
+            {
+                Ok(tokens) => HttpResponse::Created().json(tokens),
+                Err(_) => HttpResponse::InternalServerError()
+                    .json(json!({"error": "Token creation failed"})),
+            }
         }
         Err(crate::errors::AppError::Database(sqlx::Error::Database(db_err))) => {
             if db_err.constraint().is_some() {
@@ -83,78 +154,436 @@ pub async fn register(pool: web::Data<PgPool>, body: web::Json<RegisterRequest>)
 }
 
 #[post("/login")]
-pub async fn login(pool: web::Data<PgPool>, body: web::Json<LoginRequest>) -> impl Responder {
+pub async fn login(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    keys: web::Data<JwtKeySet>,
+    policy: web::Data<PasswordPolicy>,
+    body: web::Json<LoginRequest>,
+) -> impl Responder {
+    let ip_address = client_ip(&req);
+
     let user_data = match get_user_password_hash(&pool, &body.email).await {
-        Ok(Some((user_id, password_hash))) => (user_id, password_hash),
-        Ok(None) => {
-            return HttpResponse::Unauthorized()
-                .json(json!({"error": "Invalid email or password"}));
-        }
+        Ok(user_data) => user_data,
         Err(_) => {
             return HttpResponse::InternalServerError().json(json!({"error": "Server error"}));
         }
     };
+    let user_id = user_data.as_ref().map(|(user_id, _)| *user_id);
 
-    let parsed_hash = match PasswordHash::new(&user_data.1) {
-        Ok(hash) => hash,
-        Err(_) => {
+    // The attempt is counted as failed before the password is checked, so parallel guesses
+    // cannot all pass the throttle on the same count.
+    let attempt_id =
+        match LoginThrottleService::begin_attempt(&pool, &body.email, user_id, ip_address.clone())
+            .await
+        {
+            Ok(attempt_id) => attempt_id,
+            Err(AppError::TooManyRequests(throttle)) => return too_many_attempts(throttle),
+            Err(_) => {
+                return HttpResponse::InternalServerError().json(json!({"error": "Server error"}));
+            }
+        };
+
+    let verified = match &user_data {
+        Some((_, password_hash)) => policy.verify(&body.password, password_hash),
+        None => policy.verify_dummy(&body.password),
+    };
+    let user_data = match (verified, user_data) {
+        (Ok(true), Some(user_data)) => user_data,
+        (Ok(_), _) => {
+            return match record_failed_login(&pool, user_id, &body.email, ip_address.as_deref())
+                .await
+            {
+                Ok(()) => {
+                    HttpResponse::Unauthorized().json(json!({"error": "Invalid email or password"}))
+                }
+                Err(_) => {
+                    HttpResponse::InternalServerError().json(json!({"error": "Server error"}))
+                }
+            };
+        }
+        (Err(_), _) => {
             return HttpResponse::InternalServerError().json(json!({"error": "Server error"}));
         }
     };
 
-    if Argon2::default()
-        .verify_password(body.password.as_bytes(), &parsed_hash)
-        .is_err()
+    if let Err(e) = LoginThrottleService::attempt_succeeded(&pool, attempt_id).await {
+        error!(
+            "Failed to record login attempt of user {}: {}",
+            user_data.0, e
+        );
+    }
+
+    if policy.needs_rehash(&user_data.1)
+        && let Err(e) =
+            AccountService::rehash_password_if_needed(&pool, &policy, user_data.0, &body.password)
+                .await
     {
-        return HttpResponse::Unauthorized().json(json!({"error": "Invalid email or password"}));
+        error!("Failed to rehash password of user {}: {}", user_data.0, e);
     }
 
-    let login_update = UpdateUserLogin {
-        last_login_at: Utc::now(),
-    };
+    // With two-factor enabled the password only earns a challenge; tokens come from /login/2fa,
+    // which also clears the failed attempts once the second factor verifies.
+    match TwoFactorService::is_enabled(&pool, user_data.0).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return match TwoFactorService::begin_login_challenge(&pool, user_data.0).await {
+                Ok(challenge) => HttpResponse::Ok().json(challenge),
+                Err(_) => {
+                    HttpResponse::InternalServerError().json(json!({"error": "Server error"}))
+                }
+            };
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(json!({"error": "Server error"}));
+        }
+    }
+
+    if let Err(e) =
+        LoginThrottleService::record_success(&pool, &body.email, user_data.0, ip_address).await
+    {
+        error!("Failed to record login of user {}: {}", user_data.0, e);
+    }
 
-    if update_user_login(&pool, user_data.0, login_update)
+    let audit_entry = CreateAuditEntry::new(AuditAction::LoginSucceeded, Some(user_data.0))
+        .with_entity("user", user_data.0);
+    match TokenService::start_session(&pool, &keys, user_data.0, login_update(&req), audit_entry)
         .await
-        .is_err()
     {
-        eprintln!("Failed to update last_login_at for user {}", user_data.0);
-    }
-
-    let expiration = Utc::now()
-        .checked_add_signed(chrono::Duration::hours(24))
-        .unwrap()
-        .timestamp() as usize;
-
-    let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-    let token = match jsonwebtoken::encode(
-        &jsonwebtoken::Header::default(),
-        &Claims {
-            sub: user_data.0,
-            exp: expiration,
-        },
-        &jsonwebtoken::EncodingKey::from_secret(secret.as_bytes()),
-    ) {
-        Ok(token) => token,
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
         Err(_) => {
-            return HttpResponse::InternalServerError()
-                .json(json!({"error": "Token creation failed"}));
+            HttpResponse::InternalServerError().json(json!({"error": "Token creation failed"}))
+        }
+    }
+}
+
+#[get("/oidc/login")]
+pub async fn oidc_login(
+    pool: web::Data<PgPool>,
+    provider: web::Data<Option<OidcProvider>>,
+) -> impl Responder {
+    let Some(provider) = provider.as_ref() else {
+        return HttpResponse::NotFound().json(json!({"error": "SSO login is not configured"}));
+    };
+
+    match OidcService::begin_login(&pool, provider).await {
+        Ok(url) => HttpResponse::Found()
+            .insert_header(("Location", url))
+            .finish(),
+        Err(e) => {
+            error!("Failed to start OIDC login: {}", e);
+            HttpResponse::BadGateway().json(json!({"error": "Identity provider unavailable"}))
+        }
+    }
+}
+
+#[get("/oidc/callback")]
+pub async fn oidc_callback(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    keys: web::Data<JwtKeySet>,
+    policy: web::Data<PasswordPolicy>,
+    provider: web::Data<Option<OidcProvider>>,
+    query: web::Query<OidcCallbackQuery>,
+) -> impl Responder {
+    let Some(provider) = provider.as_ref() else {
+        return HttpResponse::NotFound().json(json!({"error": "SSO login is not configured"}));
+    };
+
+    if let Some(error) = &query.error {
+        return HttpResponse::Unauthorized().json(json!({
+            "error": "Identity provider denied the login",
+            "provider_error": error,
+            "provider_error_description": query.error_description,
+        }));
+    }
+
+    let (Some(code), Some(state)) = (&query.code, &query.state) else {
+        return HttpResponse::BadRequest().json(json!({"error": "Missing code or state"}));
+    };
+
+    let user_id = match OidcService::complete_login(&pool, provider, &policy, code, state).await {
+        Ok(user_id) => user_id,
+        Err(AppError::Authentication(msg)) => {
+            return HttpResponse::Unauthorized().json(json!({ "error": msg }));
+        }
+        Err(AppError::Validation(msg)) => {
+            return HttpResponse::BadRequest().json(json!({ "error": msg }));
         }
+        Err(AppError::Conflict(msg)) => {
+            return HttpResponse::Conflict().json(json!({ "error": msg }));
+        }
+        Err(e) => {
+            warn!("OIDC login failed: {}", e);
+            return HttpResponse::BadGateway()
+                .json(json!({"error": "Identity provider login failed"}));
+        }
+    };
+
+    // The provider authenticated the user, but a local second factor is still honoured.
+    match TwoFactorService::is_enabled(&pool, user_id).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return match TwoFactorService::begin_login_challenge(&pool, user_id).await {
+                Ok(challenge) => HttpResponse::Ok().json(challenge),
+                Err(_) => {
+                    HttpResponse::InternalServerError().json(json!({"error": "Server error"}))
+                }
+            };
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(json!({"error": "Server error"}));
+        }
+    }
+
+    let audit_entry = CreateAuditEntry::new(AuditAction::LoginSucceeded, Some(user_id))
+        .with_entity("user", user_id)
+        .with_details(json!({ "method": "oidc" }));
+    match TokenService::start_session(&pool, &keys, user_id, login_update(&req), audit_entry).await
+    {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(_) => {
+            HttpResponse::InternalServerError().json(json!({"error": "Token creation failed"}))
+        }
+    }
+}
+
+#[post("/refresh")]
+pub async fn refresh(
+    pool: web::Data<PgPool>,
+    keys: web::Data<JwtKeySet>,
+    body: web::Json<RefreshRequest>,
+) -> impl Responder {
+    match TokenService::refresh(&pool, &keys, &body.refresh_token).await {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(AppError::Authentication(msg)) => {
+            HttpResponse::Unauthorized().json(json!({ "error": msg }))
+        }
+        Err(_) => HttpResponse::InternalServerError().json(json!({"error": "Server error"})),
+    }
+}
+
+#[post("/logout")]
+pub async fn logout(
+    pool: web::Data<PgPool>,
+    keys: web::Data<JwtKeySet>,
+    user: SessionUser,
+) -> impl Responder {
+    match TokenService::logout(&pool, &keys, user.user_id, &user.jti, user.session_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(_) => HttpResponse::InternalServerError().json(json!({"error": "Server error"})),
+    }
+}
+
+#[get("/sessions")]
+pub async fn list_sessions(pool: web::Data<PgPool>, user: SessionUser) -> impl Responder {
+    match SessionService::list_sessions(&pool, user.user_id).await {
+        Ok(sessions) => {
+            let responses: Vec<SessionResponse> = sessions
+                .into_iter()
+                .map(|s| SessionResponse {
+                    id: s.id,
+                    user_agent: s.user_agent,
+                    ip_address: s.ip_address,
+                    created_at: s.created_at,
+                    last_seen_at: s.last_seen_at,
+                    current: s.id == user.session_id,
+                })
+                .collect();
+            HttpResponse::Ok().json(responses)
+        }
+        Err(_) => HttpResponse::InternalServerError().json(json!({"error": "Server error"})),
+    }
+}
+
+#[delete("/sessions/{id}")]
+pub async fn revoke_session(
+    pool: web::Data<PgPool>,
+    user: SessionUser,
+    path: web::Path<i64>,
+) -> impl Responder {
+    match SessionService::revoke_session(&pool, user.user_id, path.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(AppError::NotFound(msg)) => HttpResponse::NotFound().json(json!({ "error": msg })),
+        Err(_) => HttpResponse::InternalServerError().json(json!({"error": "Server error"})),
+    }
+}
+
+#[post("/verify-email")]
+pub async fn verify_email(
+    pool: web::Data<PgPool>,
+    body: web::Json<VerifyEmailRequest>,
+) -> impl Responder {
+    match AccountService::verify_email(&pool, &body.token).await {
+        Ok(()) => HttpResponse::Ok().json(json!({"email_verified": true})),
+        Err(AppError::Validation(msg)) => HttpResponse::BadRequest().json(json!({ "error": msg })),
+        Err(_) => HttpResponse::InternalServerError().json(json!({"error": "Server error"})),
+    }
+}
+
+#[post("/verify-email/resend")]
+pub async fn resend_verification_email(
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    mailer: web::Data<SharedMailer>,
+    user: SessionUser,
+) -> impl Responder {
+    match AccountService::send_email_verification(
+        &pool,
+        &mailer,
+        &config.server.app_base_url,
+        user.user_id,
+    )
+    .await
+    {
+        Ok(()) => HttpResponse::Accepted().json(json!({"status": "sent"})),
+        Err(AppError::Conflict(msg)) => HttpResponse::Conflict().json(json!({ "error": msg })),
+        Err(_) => HttpResponse::InternalServerError().json(json!({"error": "Server error"})),
+    }
+}
+
+#[post("/forgot-password")]
+pub async fn forgot_password(
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    mailer: web::Data<SharedMailer>,
+    body: web::Json<ForgotPasswordRequest>,
+) -> impl Responder {
+    // Same answer for unknown addresses so the endpoint cannot be used to probe for accounts.
+    match AccountService::request_password_reset(
+        &pool,
+        &mailer,
+        &config.server.app_base_url,
+        &body.email,
+    )
+    .await
+    {
+        Ok(()) => HttpResponse::Accepted().json(json!({
+            "status": "If an account exists for this email, a reset link has been sent"
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(json!({"error": "Server error"})),
+    }
+}
+
+#[post("/reset-password")]
+pub async fn reset_password(
+    pool: web::Data<PgPool>,
+    policy: web::Data<PasswordPolicy>,
+    body: web::Json<ResetPasswordRequest>,
+) -> impl Responder {
+    match AccountService::reset_password(&pool, &policy, &body.token, &body.new_password).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(AppError::Validation(msg)) => HttpResponse::BadRequest().json(json!({ "error": msg })),
+        Err(_) => HttpResponse::InternalServerError().json(json!({"error": "Server error"})),
+    }
+}
+
+#[post("/change-password")]
+pub async fn change_password(
+    pool: web::Data<PgPool>,
+    policy: web::Data<PasswordPolicy>,
+    user: SessionUser,
+    body: web::Json<ChangePasswordRequest>,
+) -> impl Responder {
+    match AccountService::change_password(
+        &pool,
+        &policy,
+        user.user_id,
+        user.session_id,
+        &body.current_password,
+        &body.new_password,
+    )
+    .await
+    {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(AppError::Validation(msg)) => HttpResponse::BadRequest().json(json!({ "error": msg })),
+        Err(AppError::Authentication(msg)) => {
+            HttpResponse::Forbidden().json(json!({ "error": msg }))
+        }
+        Err(_) => HttpResponse::InternalServerError().json(json!({"error": "Server error"})),
+    }
+}
+
+fn too_many_attempts(throttle: LoginThrottle) -> HttpResponse {
+    HttpResponse::TooManyRequests()
+        .insert_header(("Retry-After", throttle.retry_after.to_string()))
+        .json(json!({
+            "error": throttle.message(),
+            "reason": throttle.reason,
+            "retry_after": throttle.retry_after,
+        }))
+}
+
+// Forwarded headers are set by the client unless a trusted proxy rewrites them, so they are only
+// used for rate limiting when TRUST_PROXY_HEADERS=true.
+fn client_ip(req: &HttpRequest) -> Option<String> {
+    let trust_proxy_headers = req
+        .app_data::<web::Data<Config>>()
+        .is_some_and(|config| config.server.trust_proxy_headers);
+
+    if trust_proxy_headers {
+        return req
+            .connection_info()
+            .realip_remote_addr()
+            .map(str::to_string);
+    }
+
+    req.peer_addr().map(|addr| addr.ip().to_string())
+}
+
+pub(crate) fn login_update(req: &HttpRequest) -> UpdateUserLogin {
+    let user_agent = req
+        .headers()
+        .get("User-Agent")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.chars().take(512).collect());
+
+    UpdateUserLogin {
+        last_login_at: Utc::now(),
+        user_agent,
+        ip_address: client_ip(req),
+    }
+}
+
+// The attempt itself was already counted by `begin_attempt`. A failure that cannot be audited
+// fails the request rather than being lost. Guesses at unknown emails are only metered and
+// logged: they never reach the audit chain, so spraying addresses cannot grow it.
+async fn record_failed_login(
+    pool: &PgPool,
+    user_id: Option<i64>,
+    email: &str,
+    ip_address: Option<&str>,
+) -> AppResult<()> {
+    let Some(user_id) = user_id else {
+        LoginThrottleService::count_failure("unknown_account");
+        warn!(
+            "Failed login for unknown account from {}",
+            ip_address.unwrap_or("unknown address")
+        );
+        return Ok(());
     };
+    LoginThrottleService::count_failure("wrong_password");
+
+    let audit_entry = CreateAuditEntry::new(AuditAction::LoginFailed, None)
+        .with_entity("user", user_id)
+        .with_details(json!({ "email": email.to_lowercase() }));
+
+    AuditService::log(pool, audit_entry)
+        .await
+        .inspect_err(|e| error!("Failed to write audit entry for failed login: {}", e))?;
 
-    HttpResponse::Ok().json(json!({
-        "user_id": user_data.0,
-        "token": token
-    }))
+    Ok(())
 }
 
 #[get("/me")]
-pub async fn me(pool: web::Data<PgPool>, user: AuthUser) -> impl Responder {
+pub async fn me(pool: web::Data<PgPool>, user: SessionUser) -> impl Responder {
     match find_user_by_id(&pool, user.user_id).await {
         Ok(Some(user_data)) => HttpResponse::Ok().json(serde_json::json!({
             "id": user_data.id,
             "email": user_data.email,
             "created_at": user_data.created_at,
-            "last_login_at": user_data.last_login_at
+            "last_login_at": user_data.last_login_at,
+            "email_verified_at": user_data.email_verified_at
         })),
         Ok(None) => HttpResponse::NotFound().json(json!({"error": "User not found"})),
         Err(_) => HttpResponse::InternalServerError().json(json!({"error": "Server error"})),
@@ -0,0 +1,47 @@
+use actix_web::{HttpResponse, Result as ActixResult, get, web};
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::services::{HealthService, WorkerMonitor};
+use crate::solana::SolanaRpc;
+
+// Probes for the load balancer; none of these require authentication.
+
+#[get("/healthz")]
+pub async fn healthz() -> ActixResult<HttpResponse> {
+    Ok(HttpResponse::Ok().json(json!({"status": "ok"})))
+}
+
+#[get("/readyz")]
+pub async fn readyz(
+    pool: web::Data<PgPool>,
+    rpc: web::Data<Option<SolanaRpc>>,
+    workers: web::Data<WorkerMonitor>,
+) -> ActixResult<HttpResponse> {
+    let checks = HealthService::readiness(&pool, rpc.as_ref().as_ref(), &workers).await;
+    let ready = checks.iter().all(|check| check.is_ok());
+
+    let mut response = if ready {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+
+    Ok(response
+        .insert_header(("Cache-Control", "no-store"))
+        .json(json!({
+            "status": if ready { "ready" } else { "not_ready" },
+            "checks": checks,
+        })))
+}
+
+#[get("/version")]
+pub async fn version() -> ActixResult<HttpResponse> {
+    Ok(HttpResponse::Ok().json(json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_commit": env!("GIT_COMMIT"),
+        "build_profile": env!("BUILD_PROFILE"),
+        "rustc": env!("RUSTC_VERSION"),
+    })))
+}
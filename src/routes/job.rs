@@ -0,0 +1,113 @@
+use actix_web::{HttpResponse, Responder, ResponseError, get, post, web};
+use serde::{Deserialize, Serialize};
+
+use crate::auth_middleware::AdminUser;
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::models::{Job, JobFilter, JobStatus, PageRequest, SortOrder};
+use crate::services::JobService;
+
+#[derive(Deserialize)]
+pub struct ListJobsQuery {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    pub sort: Option<SortOrder>,
+    pub status: Option<JobStatus>,
+    pub kind: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct JobResponse {
+    pub id: i64,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub status: JobStatus,
+    pub unique_key: Option<String>,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: chrono::DateTime<chrono::Utc>,
+    pub locked_until: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl From<Job> for JobResponse {
+    fn from(job: Job) -> Self {
+        Self {
+            id: job.id,
+            kind: job.kind,
+            payload: job.payload,
+            status: job.status,
+            unique_key: job.unique_key,
+            attempts: job.attempts,
+            max_attempts: job.max_attempts,
+            run_at: job.run_at,
+            locked_until: job.locked_until,
+            last_error: job.last_error,
+            created_at: job.created_at,
+            updated_at: job.updated_at,
+            finished_at: job.finished_at,
+        }
+    }
+}
+
+#[get("/jobs")]
+pub async fn list_jobs(
+    pool: web::Data<DbPool>,
+    _admin: AdminUser,
+    query: web::Query<ListJobsQuery>,
+) -> impl Responder {
+    let query = query.into_inner();
+    let page = match PageRequest::new(query.limit, query.cursor.as_deref(), query.sort) {
+        Ok(page) => page,
+        Err(msg) => return AppError::Validation(msg).error_response(),
+    };
+    let filter = JobFilter {
+        status: query.status,
+        kind: query.kind,
+    };
+
+    match JobService::list_jobs(&pool, &filter, &page).await {
+        Ok(jobs) => HttpResponse::Ok().json(jobs.map(JobResponse::from)),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[get("/jobs/{id}")]
+pub async fn get_job(
+    pool: web::Data<DbPool>,
+    _admin: AdminUser,
+    path: web::Path<i64>,
+) -> impl Responder {
+    match JobService::get_job(&pool, path.into_inner()).await {
+        Ok(job) => HttpResponse::Ok().json(JobResponse::from(job)),
+        Err(e) => e.error_response(),
+    }
+}
+
+/// Requeues a dead or cancelled job to run now with a fresh set of attempts.
+#[post("/jobs/{id}/retry")]
+pub async fn retry_job(
+    pool: web::Data<DbPool>,
+    admin: AdminUser,
+    path: web::Path<i64>,
+) -> impl Responder {
+    match JobService::retry_job(&pool, admin.user_id, path.into_inner()).await {
+        Ok(job) => HttpResponse::Ok().json(JobResponse::from(job)),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[post("/jobs/{id}/cancel")]
+pub async fn cancel_job(
+    pool: web::Data<DbPool>,
+    admin: AdminUser,
+    path: web::Path<i64>,
+) -> impl Responder {
+    match JobService::cancel_job(&pool, admin.user_id, path.into_inner()).await {
+        Ok(job) => HttpResponse::Ok().json(JobResponse::from(job)),
+        Err(e) => e.error_response(),
+    }
+}
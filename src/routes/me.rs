@@ -0,0 +1,91 @@
+use actix_web::{HttpResponse, Result as ActixResult, get, put, web};
+use serde::{Deserialize, Serialize};
+
+use crate::auth_middleware::SessionUser;
+use crate::db::DbPool;
+use crate::models::{DigestFrequency, InboxAction, ProposalStatus, UpdateNotificationPreferences};
+use crate::services::{NotificationService, ProposalService};
+
+#[derive(Serialize)]
+pub struct InboxItemResponse {
+    pub proposal_id: i64,
+    pub multisig_id: i64,
+    pub multisig_name: String,
+    pub title: String,
+    pub status: ProposalStatus,
+    pub created_by: i64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub approval_count: i64,
+    pub threshold: i32,
+    pub progress: String,
+    pub action: InboxAction,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateNotificationPreferencesRequest {
+    pub email_enabled: Option<bool>,
+    pub notify_vote_needed: Option<bool>,
+    pub notify_threshold_reached: Option<bool>,
+    pub digest_frequency: Option<DigestFrequency>,
+}
+
+#[get("/inbox")]
+pub async fn get_inbox(pool: web::Data<DbPool>, user: SessionUser) -> ActixResult<HttpResponse> {
+    let items = ProposalService::get_user_inbox(&pool, user.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let responses: Vec<InboxItemResponse> = items
+        .into_iter()
+        .map(|item| InboxItemResponse {
+            proposal_id: item.proposal_id,
+            multisig_id: item.multisig_id,
+            multisig_name: item.multisig_name,
+            title: item.title,
+            status: item.status,
+            created_by: item.created_by,
+            created_at: item.created_at,
+            expires_at: item.expires_at,
+            approval_count: item.approval_count,
+            threshold: item.threshold,
+            progress: format!("{} / {}", item.approval_count, item.threshold),
+            action: item.action,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(responses))
+}
+
+#[get("/notification-preferences")]
+pub async fn get_notification_preferences(
+    pool: web::Data<DbPool>,
+    user: SessionUser,
+) -> ActixResult<HttpResponse> {
+    let preferences = NotificationService::get_preferences(&pool, user.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(preferences))
+}
+
+#[put("/notification-preferences")]
+pub async fn update_notification_preferences(
+    pool: web::Data<DbPool>,
+    user: SessionUser,
+    req: web::Json<UpdateNotificationPreferencesRequest>,
+) -> ActixResult<HttpResponse> {
+    let req = req.into_inner();
+    let update = UpdateNotificationPreferences {
+        email_enabled: req.email_enabled,
+        notify_vote_needed: req.notify_vote_needed,
+        notify_threshold_reached: req.notify_threshold_reached,
+        digest_frequency: req.digest_frequency,
+    };
+
+    let preferences = NotificationService::update_preferences(&pool, user.user_id, update)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(preferences))
+}
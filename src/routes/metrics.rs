@@ -0,0 +1,18 @@
+use actix_web::{HttpResponse, Result as ActixResult, get, web};
+use sqlx::PgPool;
+use tracing::warn;
+
+use crate::metrics::METRICS;
+use crate::services::MetricsService;
+
+#[get("/metrics")]
+pub async fn get_metrics(pool: web::Data<PgPool>) -> ActixResult<HttpResponse> {
+    // Serve what we have even if the database is down; the stale gauges are still informative.
+    if let Err(e) = MetricsService::refresh(&pool).await {
+        warn!("Failed to refresh metrics: {}", e);
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(METRICS.encode()))
+}
@@ -1,3 +1,14 @@
+pub mod admin;
+pub mod api_key;
+pub mod audit;
 pub mod auth;
+pub mod health;
+pub mod job;
+pub mod me;
+pub mod metrics;
 pub mod multisig;
+pub mod passkey;
 pub mod proposal;
+pub mod two_factor;
+pub mod webhook;
+pub mod well_known;
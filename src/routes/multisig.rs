@@ -1,10 +1,10 @@
-use actix_web::{HttpResponse, Result as ActixResult, get, post, web};
+use actix_web::{HttpResponse, Result as ActixResult, get, post, put, web};
 use serde::{Deserialize, Serialize};
 
-use crate::auth_middleware::AuthUser;
+use crate::auth_middleware::SessionUser;
 use crate::db::DbPool;
-use crate::models::CreateMultisig;
-use crate::services::MultisigService;
+use crate::models::{CreateMultisig, Page, PageRequest, SortOrder};
+use crate::services::{EventBroadcaster, MultisigService, StreamSession};
 
 #[derive(Deserialize)]
 pub struct CreateMultisigRequest {
@@ -12,6 +12,20 @@ pub struct CreateMultisigRequest {
     pub description: Option<String>,
     pub owners: Vec<i64>,
     pub threshold: i32,
+    #[serde(default)]
+    pub require_step_up: bool,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateStepUpPolicyRequest {
+    pub require_step_up: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ListMultisigsQuery {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    pub sort: Option<SortOrder>,
 }
 
 #[derive(Serialize)]
@@ -22,13 +36,14 @@ pub struct MultisigResponse {
     pub created_by: i64,
     pub owners: Vec<i64>,
     pub threshold: i32,
+    pub require_step_up: bool,
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
 #[post("")]
 pub async fn create_multisig(
     pool: web::Data<DbPool>,
-    user: AuthUser,
+    user: SessionUser,
     req: web::Json<CreateMultisigRequest>,
 ) -> ActixResult<HttpResponse> {
     let create_data = CreateMultisig::new(
@@ -36,6 +51,7 @@ pub async fn create_multisig(
         req.description.clone(),
         req.owners.clone(),
         req.threshold,
+        req.require_step_up,
     );
 
     let multisig = MultisigService::create_multisig(&pool, create_data, user.user_id)
@@ -49,6 +65,7 @@ pub async fn create_multisig(
         created_by: multisig.created_by,
         owners: multisig.owners,
         threshold: multisig.threshold,
+        require_step_up: multisig.require_step_up,
         created_at: multisig.created_at,
     };
 
@@ -56,31 +73,36 @@ pub async fn create_multisig(
 }
 
 #[get("")]
-pub async fn list_multisigs(pool: web::Data<DbPool>, user: AuthUser) -> ActixResult<HttpResponse> {
-    let multisigs = MultisigService::list_user_multisigs(&pool, user.user_id)
+pub async fn list_multisigs(
+    pool: web::Data<DbPool>,
+    user: SessionUser,
+    query: web::Query<ListMultisigsQuery>,
+) -> ActixResult<HttpResponse> {
+    let page = PageRequest::new(query.limit, query.cursor.as_deref(), query.sort)
+        .map_err(actix_web::error::ErrorBadRequest)?;
+
+    let multisigs = MultisigService::list_user_multisigs(&pool, user.user_id, &page)
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    let responses: Vec<MultisigResponse> = multisigs
-        .into_iter()
-        .map(|m| MultisigResponse {
-            id: m.id,
-            name: m.name,
-            description: m.description,
-            created_by: m.created_by,
-            owners: m.owners,
-            threshold: m.threshold,
-            created_at: m.created_at,
-        })
-        .collect();
-
-    Ok(HttpResponse::Ok().json(responses))
+    let response: Page<MultisigResponse> = multisigs.map(|m| MultisigResponse {
+        id: m.id,
+        name: m.name,
+        description: m.description,
+        created_by: m.created_by,
+        owners: m.owners,
+        threshold: m.threshold,
+        require_step_up: m.require_step_up,
+        created_at: m.created_at,
+    });
+
+    Ok(HttpResponse::Ok().json(response))
 }
 
 #[get("/{id}")]
 pub async fn get_multisig(
     pool: web::Data<DbPool>,
-    user: AuthUser,
+    user: SessionUser,
     path: web::Path<i64>,
 ) -> ActixResult<HttpResponse> {
     let multisig_id = path.into_inner();
@@ -96,8 +118,68 @@ pub async fn get_multisig(
         created_by: multisig.created_by,
         owners: multisig.owners,
         threshold: multisig.threshold,
+        require_step_up: multisig.require_step_up,
+        created_at: multisig.created_at,
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[put("/{id}/step-up")]
+pub async fn update_step_up_policy(
+    pool: web::Data<DbPool>,
+    user: SessionUser,
+    path: web::Path<i64>,
+    req: web::Json<UpdateStepUpPolicyRequest>,
+) -> ActixResult<HttpResponse> {
+    let multisig = MultisigService::update_step_up_policy(
+        &pool,
+        path.into_inner(),
+        user.user_id,
+        user.session_id,
+        req.require_step_up,
+    )
+    .await
+    .map_err(actix_web::error::ErrorForbidden)?;
+
+    let response = MultisigResponse {
+        id: multisig.id,
+        name: multisig.name,
+        description: multisig.description,
+        created_by: multisig.created_by,
+        owners: multisig.owners,
+        threshold: multisig.threshold,
+        require_step_up: multisig.require_step_up,
         created_at: multisig.created_at,
     };
 
     Ok(HttpResponse::Ok().json(response))
 }
+
+#[get("/{id}/events")]
+pub async fn stream_multisig_events(
+    pool: web::Data<DbPool>,
+    broadcaster: web::Data<EventBroadcaster>,
+    user: SessionUser,
+    path: web::Path<i64>,
+) -> ActixResult<HttpResponse> {
+    let multisig_id = path.into_inner();
+
+    MultisigService::check_user_is_owner(&pool, multisig_id, user.user_id)
+        .await
+        .map_err(actix_web::error::ErrorForbidden)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(broadcaster.multisig_stream(
+            pool.get_ref().clone(),
+            multisig_id,
+            StreamSession {
+                session_id: user.session_id,
+                jti: user.jti,
+                expires_at: user.expires_at,
+            },
+        )))
+}
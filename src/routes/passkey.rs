@@ -0,0 +1,187 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, ResponseError, delete, get, post, web};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::auth_middleware::SessionUser;
+use crate::jwt::JwtKeySet;
+use crate::models::{AssertionCredential, RegistrationCredential};
+use crate::password::PasswordPolicy;
+use crate::routes::auth::{ReauthenticateRequest, login_update};
+use crate::services::{PasskeyService, Reauthentication};
+use crate::webauthn::RelyingParty;
+
+#[derive(Deserialize)]
+pub struct RegisterPasskeyRequest {
+    pub name: Option<String>,
+    pub current_password: Option<String>,
+    pub credential: RegistrationCredential,
+}
+
+#[derive(Deserialize)]
+pub struct PasskeyLoginOptionsRequest {
+    pub email: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PasskeyAssertionRequest {
+    pub credential: AssertionCredential,
+}
+
+#[derive(Serialize)]
+pub struct PasskeyResponse {
+    pub id: i64,
+    pub name: String,
+    pub credential_id: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[post("/passkeys/register/options")]
+pub async fn passkey_registration_options(
+    pool: web::Data<PgPool>,
+    rp: web::Data<RelyingParty>,
+    policy: web::Data<PasswordPolicy>,
+    user: SessionUser,
+    body: Option<web::Json<ReauthenticateRequest>>,
+) -> impl Responder {
+    let body = body.map(web::Json::into_inner).unwrap_or_default();
+    let reauth = Reauthentication {
+        session_id: user.session_id,
+        current_password: body.current_password.as_deref(),
+        policy: &policy,
+    };
+
+    match PasskeyService::registration_options(&pool, &rp, user.user_id, &reauth).await {
+        Ok(options) => HttpResponse::Ok().json(options),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[post("/passkeys/register")]
+pub async fn register_passkey(
+    pool: web::Data<PgPool>,
+    rp: web::Data<RelyingParty>,
+    policy: web::Data<PasswordPolicy>,
+    user: SessionUser,
+    body: web::Json<RegisterPasskeyRequest>,
+) -> impl Responder {
+    let body = body.into_inner();
+    let reauth = Reauthentication {
+        session_id: user.session_id,
+        current_password: body.current_password.as_deref(),
+        policy: &policy,
+    };
+
+    match PasskeyService::register(
+        &pool,
+        &rp,
+        user.user_id,
+        &reauth,
+        body.name,
+        &body.credential,
+    )
+    .await
+    {
+        Ok(passkey) => HttpResponse::Created().json(PasskeyResponse {
+            id: passkey.id,
+            name: passkey.name,
+            credential_id: passkey.credential_id,
+            created_at: passkey.created_at,
+            last_used_at: passkey.last_used_at,
+        }),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[get("/passkeys")]
+pub async fn list_passkeys(pool: web::Data<PgPool>, user: SessionUser) -> impl Responder {
+    match PasskeyService::list_passkeys(&pool, user.user_id).await {
+        Ok(passkeys) => {
+            let responses: Vec<PasskeyResponse> = passkeys
+                .into_iter()
+                .map(|p| PasskeyResponse {
+                    id: p.id,
+                    name: p.name,
+                    credential_id: p.credential_id,
+                    created_at: p.created_at,
+                    last_used_at: p.last_used_at,
+                })
+                .collect();
+            HttpResponse::Ok().json(responses)
+        }
+        Err(e) => e.error_response(),
+    }
+}
+
+#[delete("/passkeys/{id}")]
+pub async fn delete_passkey(
+    pool: web::Data<PgPool>,
+    policy: web::Data<PasswordPolicy>,
+    user: SessionUser,
+    path: web::Path<i64>,
+    body: Option<web::Json<ReauthenticateRequest>>,
+) -> impl Responder {
+    let body = body.map(web::Json::into_inner).unwrap_or_default();
+    let reauth = Reauthentication {
+        session_id: user.session_id,
+        current_password: body.current_password.as_deref(),
+        policy: &policy,
+    };
+
+    match PasskeyService::delete_passkey(&pool, user.user_id, &reauth, path.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[post("/passkeys/login/options")]
+pub async fn passkey_login_options(
+    pool: web::Data<PgPool>,
+    rp: web::Data<RelyingParty>,
+    body: web::Json<PasskeyLoginOptionsRequest>,
+) -> impl Responder {
+    match PasskeyService::authentication_options(&pool, &rp, body.email.as_deref()).await {
+        Ok(options) => HttpResponse::Ok().json(options),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[post("/passkeys/login")]
+pub async fn passkey_login(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    rp: web::Data<RelyingParty>,
+    keys: web::Data<JwtKeySet>,
+    body: web::Json<PasskeyAssertionRequest>,
+) -> impl Responder {
+    match PasskeyService::login(&pool, &rp, &keys, &body.credential, login_update(&req)).await {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[post("/step-up/passkey/options")]
+pub async fn passkey_step_up_options(
+    pool: web::Data<PgPool>,
+    rp: web::Data<RelyingParty>,
+    user: SessionUser,
+) -> impl Responder {
+    match PasskeyService::step_up_options(&pool, &rp, user.user_id).await {
+        Ok(options) => HttpResponse::Ok().json(options),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[post("/step-up/passkey")]
+pub async fn passkey_step_up(
+    pool: web::Data<PgPool>,
+    rp: web::Data<RelyingParty>,
+    user: SessionUser,
+    body: web::Json<PasskeyAssertionRequest>,
+) -> impl Responder {
+    match PasskeyService::step_up(&pool, &rp, user.user_id, user.session_id, &body.credential).await
+    {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => e.error_response(),
+    }
+}
@@ -3,7 +3,10 @@ use serde::{Deserialize, Serialize};
 
 use crate::auth_middleware::AuthUser;
 use crate::db::DbPool;
-use crate::models::{CreateProposal, ProposalStatus};
+use crate::errors::AppError;
+use crate::models::{
+    CreateProposal, Page, PageRequest, ProposalEventType, ProposalFilter, ProposalStatus, SortOrder,
+};
 use crate::services::ProposalService;
 
 #[derive(Deserialize)]
@@ -11,6 +14,19 @@ pub struct CreateProposalRequest {
     pub title: String,
     pub description: Option<String>,
     pub transaction_data: Option<String>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct ListProposalsQuery {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    pub sort: Option<SortOrder>,
+    pub status: Option<ProposalStatus>,
+    pub created_by: Option<i64>,
+    pub created_after: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_before: Option<chrono::DateTime<chrono::Utc>>,
+    pub awaiting_my_approval: Option<bool>,
 }
 
 #[derive(Serialize)]
@@ -24,6 +40,19 @@ pub struct ProposalResponse {
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub executed_at: Option<chrono::DateTime<chrono::Utc>>,
     pub transaction_data: Option<String>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct ProposalEventResponse {
+    pub id: i64,
+    pub proposal_id: i64,
+    pub event_type: ProposalEventType,
+    pub actor_id: Option<i64>,
+    pub old_status: Option<ProposalStatus>,
+    pub new_status: Option<ProposalStatus>,
+    pub metadata: serde_json::Value,
+    pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(Serialize)]
@@ -47,11 +76,12 @@ pub async fn create_proposal(
         req.title.clone(),
         req.description.clone(),
         req.transaction_data.clone(),
+        req.expires_at,
     );
 
-    let proposal = ProposalService::create_proposal(&pool, create_data, multisig_id, user.user_id)
+    let proposal = ProposalService::create_proposal(&pool, create_data, multisig_id, &user.actor())
         .await
-        .map_err(actix_web::error::ErrorInternalServerError)?;
+        .map_err(|e| access_error(e, actix_web::error::ErrorInternalServerError))?;
 
     let response = ProposalResponse {
         id: proposal.id,
@@ -63,6 +93,7 @@ pub async fn create_proposal(
         created_at: proposal.created_at,
         executed_at: proposal.executed_at,
         transaction_data: proposal.transaction_data,
+        expires_at: proposal.expires_at,
     };
 
     Ok(HttpResponse::Created().json(response))
@@ -73,29 +104,43 @@ pub async fn list_proposals(
     pool: web::Data<DbPool>,
     user: AuthUser,
     path: web::Path<i64>,
+    query: web::Query<ListProposalsQuery>,
 ) -> ActixResult<HttpResponse> {
     let multisig_id = path.into_inner();
 
-    let proposals = ProposalService::list_multisig_proposals(&pool, multisig_id, user.user_id)
-        .await
-        .map_err(actix_web::error::ErrorForbidden)?;
+    let page = PageRequest::new(query.limit, query.cursor.as_deref(), query.sort)
+        .map_err(actix_web::error::ErrorBadRequest)?;
 
-    let responses: Vec<ProposalResponse> = proposals
-        .into_iter()
-        .map(|p| ProposalResponse {
-            id: p.id,
-            multisig_id: p.multisig_id,
-            title: p.title,
-            description: p.description,
-            status: p.status,
-            created_by: p.created_by,
-            created_at: p.created_at,
-            executed_at: p.executed_at,
-            transaction_data: p.transaction_data,
-        })
-        .collect();
+    let filter = ProposalFilter {
+        status: query.status,
+        created_by: query.created_by,
+        created_after: query.created_after,
+        created_before: query.created_before,
+        awaiting_approval_by: query
+            .awaiting_my_approval
+            .unwrap_or(false)
+            .then_some(user.user_id),
+    };
 
-    Ok(HttpResponse::Ok().json(responses))
+    let proposals =
+        ProposalService::list_multisig_proposals(&pool, multisig_id, &user.actor(), &filter, &page)
+            .await
+            .map_err(|e| access_error(e, actix_web::error::ErrorForbidden))?;
+
+    let response: Page<ProposalResponse> = proposals.map(|p| ProposalResponse {
+        id: p.id,
+        multisig_id: p.multisig_id,
+        title: p.title,
+        description: p.description,
+        status: p.status,
+        created_by: p.created_by,
+        created_at: p.created_at,
+        executed_at: p.executed_at,
+        transaction_data: p.transaction_data,
+        expires_at: p.expires_at,
+    });
+
+    Ok(HttpResponse::Ok().json(response))
 }
 
 #[get("/{id}")]
@@ -106,17 +151,12 @@ pub async fn get_proposal(
 ) -> ActixResult<HttpResponse> {
     let proposal_id = path.into_inner();
 
-    let proposal = ProposalService::get_proposal(&pool, proposal_id)
+    let proposal = ProposalService::view_proposal(&pool, proposal_id, &user.actor())
         .await
-        .map_err(actix_web::error::ErrorNotFound)?;
-
-    crate::services::MultisigService::check_user_is_owner(
-        &pool,
-        proposal.multisig_id,
-        user.user_id,
-    )
-    .await
-    .map_err(actix_web::error::ErrorForbidden)?;
+        .map_err(|e| match e {
+            AppError::NotFound(_) => actix_web::error::ErrorNotFound(e),
+            _ => access_error(e, actix_web::error::ErrorForbidden),
+        })?;
 
     let response = ProposalResponse {
         id: proposal.id,
@@ -128,6 +168,7 @@ pub async fn get_proposal(
         created_at: proposal.created_at,
         executed_at: proposal.executed_at,
         transaction_data: proposal.transaction_data,
+        expires_at: proposal.expires_at,
     };
 
     Ok(HttpResponse::Ok().json(response))
@@ -141,9 +182,9 @@ pub async fn activate_proposal(
 ) -> ActixResult<HttpResponse> {
     let proposal_id = path.into_inner();
 
-    let proposal = ProposalService::activate_proposal(&pool, proposal_id, user.user_id)
+    let proposal = ProposalService::activate_proposal(&pool, proposal_id, &user.actor())
         .await
-        .map_err(actix_web::error::ErrorBadRequest)?;
+        .map_err(|e| access_error(e, actix_web::error::ErrorBadRequest))?;
 
     let response = ProposalResponse {
         id: proposal.id,
@@ -155,6 +196,7 @@ pub async fn activate_proposal(
         created_at: proposal.created_at,
         executed_at: proposal.executed_at,
         transaction_data: proposal.transaction_data,
+        expires_at: proposal.expires_at,
     };
 
     Ok(HttpResponse::Ok().json(response))
@@ -168,9 +210,9 @@ pub async fn approve_proposal(
 ) -> ActixResult<HttpResponse> {
     let proposal_id = path.into_inner();
 
-    let (approval, proposal) = ProposalService::approve_proposal(&pool, proposal_id, user.user_id)
+    let (approval, proposal) = ProposalService::approve_proposal(&pool, proposal_id, &user.actor())
         .await
-        .map_err(actix_web::error::ErrorBadRequest)?;
+        .map_err(|e| access_error(e, actix_web::error::ErrorBadRequest))?;
 
     let approval_response = ProposalApprovalResponse {
         id: approval.id,
@@ -189,6 +231,7 @@ pub async fn approve_proposal(
         created_at: proposal.created_at,
         executed_at: proposal.executed_at,
         transaction_data: proposal.transaction_data,
+        expires_at: proposal.expires_at,
     };
 
     let response = serde_json::json!({
@@ -199,6 +242,34 @@ pub async fn approve_proposal(
     Ok(HttpResponse::Ok().json(response))
 }
 
+#[post("/{id}/revoke")]
+pub async fn revoke_approval(
+    pool: web::Data<DbPool>,
+    user: AuthUser,
+    path: web::Path<i64>,
+) -> ActixResult<HttpResponse> {
+    let proposal_id = path.into_inner();
+
+    let proposal = ProposalService::revoke_approval(&pool, proposal_id, &user.actor())
+        .await
+        .map_err(|e| access_error(e, actix_web::error::ErrorBadRequest))?;
+
+    let response = ProposalResponse {
+        id: proposal.id,
+        multisig_id: proposal.multisig_id,
+        title: proposal.title,
+        description: proposal.description,
+        status: proposal.status,
+        created_by: proposal.created_by,
+        created_at: proposal.created_at,
+        executed_at: proposal.executed_at,
+        transaction_data: proposal.transaction_data,
+        expires_at: proposal.expires_at,
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
 #[post("/{id}/execute")]
 pub async fn execute_proposal(
     pool: web::Data<DbPool>,
@@ -207,9 +278,9 @@ pub async fn execute_proposal(
 ) -> ActixResult<HttpResponse> {
     let proposal_id = path.into_inner();
 
-    let proposal = ProposalService::execute_proposal(&pool, proposal_id, user.user_id)
+    let proposal = ProposalService::execute_proposal(&pool, proposal_id, &user.actor())
         .await
-        .map_err(actix_web::error::ErrorBadRequest)?;
+        .map_err(|e| access_error(e, actix_web::error::ErrorBadRequest))?;
 
     let response = ProposalResponse {
         id: proposal.id,
@@ -221,6 +292,7 @@ pub async fn execute_proposal(
         created_at: proposal.created_at,
         executed_at: proposal.executed_at,
         transaction_data: proposal.transaction_data,
+        expires_at: proposal.expires_at,
     };
 
     Ok(HttpResponse::Ok().json(response))
@@ -234,9 +306,9 @@ pub async fn reject_proposal(
 ) -> ActixResult<HttpResponse> {
     let proposal_id = path.into_inner();
 
-    let proposal = ProposalService::reject_proposal(&pool, proposal_id, user.user_id)
+    let proposal = ProposalService::reject_proposal(&pool, proposal_id, &user.actor())
         .await
-        .map_err(actix_web::error::ErrorBadRequest)?;
+        .map_err(|e| access_error(e, actix_web::error::ErrorBadRequest))?;
 
     let response = ProposalResponse {
         id: proposal.id,
@@ -248,6 +320,7 @@ pub async fn reject_proposal(
         created_at: proposal.created_at,
         executed_at: proposal.executed_at,
         transaction_data: proposal.transaction_data,
+        expires_at: proposal.expires_at,
     };
 
     Ok(HttpResponse::Ok().json(response))
@@ -261,9 +334,9 @@ pub async fn get_proposal_approvals(
 ) -> ActixResult<HttpResponse> {
     let proposal_id = path.into_inner();
 
-    let approvals = ProposalService::get_proposal_approvals(&pool, proposal_id, user.user_id)
+    let approvals = ProposalService::get_proposal_approvals(&pool, proposal_id, &user.actor())
         .await
-        .map_err(actix_web::error::ErrorForbidden)?;
+        .map_err(|e| access_error(e, actix_web::error::ErrorForbidden))?;
 
     let responses: Vec<ProposalApprovalResponse> = approvals
         .into_iter()
@@ -277,3 +350,74 @@ pub async fn get_proposal_approvals(
 
     Ok(HttpResponse::Ok().json(responses))
 }
+
+#[get("/{id}/history")]
+pub async fn get_proposal_history(
+    pool: web::Data<DbPool>,
+    user: AuthUser,
+    path: web::Path<i64>,
+) -> ActixResult<HttpResponse> {
+    let proposal_id = path.into_inner();
+
+    let events = ProposalService::get_proposal_history(&pool, proposal_id, &user.actor())
+        .await
+        .map_err(|e| access_error(e, actix_web::error::ErrorForbidden))?;
+
+    let responses: Vec<ProposalEventResponse> = events
+        .into_iter()
+        .map(|e| ProposalEventResponse {
+            id: e.id,
+            proposal_id: e.proposal_id,
+            event_type: e.event_type,
+            actor_id: e.actor_id,
+            old_status: e.old_status,
+            new_status: e.new_status,
+            metadata: e.metadata,
+            created_at: e.created_at,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(responses))
+}
+
+// Missing step-up or API key scope keeps the structured 403 body so clients can react to it, and
+// validation errors stay 400; other errors use the handler's usual status.
+fn access_error(err: AppError, fallback: fn(AppError) -> actix_web::Error) -> actix_web::Error {
+    match err {
+        AppError::Authorization(_) | AppError::Validation(_) => err.into(),
+        _ => fallback(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use chrono::{Duration, Utc};
+
+    use super::*;
+    use crate::db::test_pool;
+    use crate::models::Actor;
+
+    #[tokio::test]
+    async fn an_inverted_date_filter_is_a_bad_request() {
+        let pool = test_pool().await;
+        let actor = Actor {
+            user_id: 0,
+            session_id: None,
+            api_key: None,
+        };
+        let filter = ProposalFilter {
+            created_after: Some(Utc::now()),
+            created_before: Some(Utc::now() - Duration::days(1)),
+            ..Default::default()
+        };
+        let page = PageRequest::new(None, None, None).unwrap();
+
+        let err = ProposalService::list_multisig_proposals(&pool, 0, &actor, &filter, &page)
+            .await
+            .map_err(|e| access_error(e, actix_web::error::ErrorForbidden))
+            .unwrap_err();
+
+        assert_eq!(err.error_response().status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,123 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, ResponseError, get, post, web};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::auth_middleware::SessionUser;
+use crate::jwt::JwtKeySet;
+use crate::models::SecondFactor;
+use crate::password::PasswordPolicy;
+use crate::routes::auth::{ReauthenticateRequest, login_update};
+use crate::services::{Reauthentication, TwoFactorService};
+
+#[derive(Deserialize)]
+pub struct TotpCodeRequest {
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct CompleteLoginRequest {
+    pub challenge_token: String,
+    #[serde(flatten)]
+    pub factor: SecondFactor,
+}
+
+#[get("/2fa")]
+pub async fn get_two_factor_status(pool: web::Data<PgPool>, user: SessionUser) -> impl Responder {
+    match TwoFactorService::status(&pool, user.user_id).await {
+        Ok(status) => HttpResponse::Ok().json(status),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[post("/2fa/totp/enroll")]
+pub async fn enroll_totp(
+    pool: web::Data<PgPool>,
+    policy: web::Data<PasswordPolicy>,
+    user: SessionUser,
+    body: Option<web::Json<ReauthenticateRequest>>,
+) -> impl Responder {
+    let body = body.map(web::Json::into_inner).unwrap_or_default();
+    let reauth = Reauthentication {
+        session_id: user.session_id,
+        current_password: body.current_password.as_deref(),
+        policy: &policy,
+    };
+
+    match TwoFactorService::enroll_totp(&pool, user.user_id, &reauth).await {
+        Ok(enrollment) => HttpResponse::Ok().json(enrollment),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[post("/2fa/totp/confirm")]
+pub async fn confirm_totp(
+    pool: web::Data<PgPool>,
+    user: SessionUser,
+    body: web::Json<TotpCodeRequest>,
+) -> impl Responder {
+    match TwoFactorService::confirm_totp(&pool, user.user_id, &body.code).await {
+        Ok(recovery_codes) => HttpResponse::Ok().json(json!({
+            "totp_enabled": true,
+            "recovery_codes": recovery_codes,
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[post("/2fa/totp/disable")]
+pub async fn disable_totp(
+    pool: web::Data<PgPool>,
+    user: SessionUser,
+    body: web::Json<SecondFactor>,
+) -> impl Responder {
+    match TwoFactorService::disable_totp(&pool, user.user_id, &body).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[post("/2fa/recovery-codes")]
+pub async fn regenerate_recovery_codes(
+    pool: web::Data<PgPool>,
+    user: SessionUser,
+    body: web::Json<TotpCodeRequest>,
+) -> impl Responder {
+    match TwoFactorService::regenerate_recovery_codes(&pool, user.user_id, &body.code).await {
+        Ok(recovery_codes) => HttpResponse::Ok().json(json!({ "recovery_codes": recovery_codes })),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[post("/login/2fa")]
+pub async fn complete_login(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    keys: web::Data<JwtKeySet>,
+    body: web::Json<CompleteLoginRequest>,
+) -> impl Responder {
+    match TwoFactorService::complete_login(
+        &pool,
+        &keys,
+        &body.challenge_token,
+        &body.factor,
+        login_update(&req),
+    )
+    .await
+    {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[post("/step-up")]
+pub async fn step_up(
+    pool: web::Data<PgPool>,
+    user: SessionUser,
+    body: web::Json<SecondFactor>,
+) -> impl Responder {
+    match TwoFactorService::step_up(&pool, user.user_id, user.session_id, &body).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => e.error_response(),
+    }
+}
@@ -0,0 +1,161 @@
+use actix_web::{HttpResponse, Result as ActixResult, delete, get, post, web};
+use serde::{Deserialize, Serialize};
+
+use crate::auth_middleware::SessionUser;
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::models::{CreateWebhook, WebhookEventType};
+use crate::services::WebhookService;
+
+#[derive(Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    #[serde(default)]
+    pub event_types: Vec<WebhookEventType>,
+}
+
+#[derive(Deserialize)]
+pub struct ListDeliveriesQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct WebhookResponse {
+    pub id: i64,
+    pub multisig_id: i64,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub is_active: bool,
+    pub created_by: i64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize)]
+pub struct CreateWebhookResponse {
+    pub webhook: WebhookResponse,
+    pub secret: String,
+}
+
+#[post("")]
+pub async fn create_webhook(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    user: SessionUser,
+    path: web::Path<i64>,
+    req: web::Json<CreateWebhookRequest>,
+) -> ActixResult<HttpResponse> {
+    let multisig_id = path.into_inner();
+    let req = req.into_inner();
+    let create_data = CreateWebhook::new(req.url, req.event_types);
+
+    let webhook = WebhookService::create_webhook(
+        &pool,
+        create_data,
+        multisig_id,
+        user.user_id,
+        &config.webhooks,
+    )
+    .await
+    .map_err(actix_web::error::ErrorBadRequest)?;
+
+    let response = CreateWebhookResponse {
+        secret: webhook.secret,
+        webhook: WebhookResponse {
+            id: webhook.id,
+            multisig_id: webhook.multisig_id,
+            url: webhook.url,
+            event_types: webhook.event_types,
+            is_active: webhook.is_active,
+            created_by: webhook.created_by,
+            created_at: webhook.created_at,
+        },
+    };
+
+    Ok(HttpResponse::Created().json(response))
+}
+
+#[get("")]
+pub async fn list_webhooks(
+    pool: web::Data<DbPool>,
+    user: SessionUser,
+    path: web::Path<i64>,
+) -> ActixResult<HttpResponse> {
+    let multisig_id = path.into_inner();
+
+    let webhooks = WebhookService::list_webhooks(&pool, multisig_id, user.user_id)
+        .await
+        .map_err(actix_web::error::ErrorForbidden)?;
+
+    let responses: Vec<WebhookResponse> = webhooks
+        .into_iter()
+        .map(|w| WebhookResponse {
+            id: w.id,
+            multisig_id: w.multisig_id,
+            url: w.url,
+            event_types: w.event_types,
+            is_active: w.is_active,
+            created_by: w.created_by,
+            created_at: w.created_at,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(responses))
+}
+
+#[delete("/{webhook_id}")]
+pub async fn delete_webhook(
+    pool: web::Data<DbPool>,
+    user: SessionUser,
+    path: web::Path<(i64, i64)>,
+) -> ActixResult<HttpResponse> {
+    let (multisig_id, webhook_id) = path.into_inner();
+
+    WebhookService::delete_webhook(&pool, multisig_id, webhook_id, user.user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/{webhook_id}/deliveries")]
+pub async fn list_webhook_deliveries(
+    pool: web::Data<DbPool>,
+    user: SessionUser,
+    path: web::Path<(i64, i64)>,
+    query: web::Query<ListDeliveriesQuery>,
+) -> ActixResult<HttpResponse> {
+    let (multisig_id, webhook_id) = path.into_inner();
+
+    let deliveries = WebhookService::list_deliveries(
+        &pool,
+        multisig_id,
+        webhook_id,
+        user.user_id,
+        query.limit.unwrap_or(50),
+    )
+    .await
+    .map_err(actix_web::error::ErrorBadRequest)?;
+
+    Ok(HttpResponse::Ok().json(deliveries))
+}
+
+#[get("/{webhook_id}/deliveries/{delivery_id}/attempts")]
+pub async fn list_webhook_delivery_attempts(
+    pool: web::Data<DbPool>,
+    user: SessionUser,
+    path: web::Path<(i64, i64, i64)>,
+) -> ActixResult<HttpResponse> {
+    let (multisig_id, webhook_id, delivery_id) = path.into_inner();
+
+    let attempts = WebhookService::list_delivery_attempts(
+        &pool,
+        multisig_id,
+        webhook_id,
+        delivery_id,
+        user.user_id,
+    )
+    .await
+    .map_err(actix_web::error::ErrorBadRequest)?;
+
+    Ok(HttpResponse::Ok().json(attempts))
+}
@@ -0,0 +1,10 @@
+use actix_web::{HttpResponse, Result as ActixResult, get, web};
+
+use crate::jwt::JwtKeySet;
+
+#[get("/jwks.json")]
+pub async fn get_jwks(keys: web::Data<JwtKeySet>) -> ActixResult<HttpResponse> {
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "public, max-age=300"))
+        .json(keys.jwks()))
+}
@@ -0,0 +1,327 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+use sqlx::PgConnection;
+use tracing::{Instrument, Span, error};
+
+use crate::db::{
+    DbPool, append_audit_entry, create_user, create_user_token, find_user_by_email,
+    find_user_by_id, find_user_token_for_update, get_user_credentials_for_update,
+    invalidate_user_tokens, mark_email_verified, mark_user_token_used, revoke_user_sessions,
+    update_user_password,
+};
+use crate::errors::{AppError, AppResult};
+use crate::mailer::{
+    EmailMessage, SharedMailer,
+    templates::{render_email_verification, render_password_reset},
+};
+use crate::models::{AuditAction, CreateAuditEntry, CreateUser, User, UserTokenPurpose};
+use crate::password::PasswordPolicy;
+use crate::services::{AuditService, TokenService};
+
+pub struct AccountService;
+
+impl AccountService {
+    pub async fn register(pool: &DbPool, user_data: CreateUser) -> AppResult<User> {
+        let mut tx = pool.begin().await?;
+
+        let user = create_user(&mut tx, user_data).await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::UserRegistered, Some(user.id))
+            .with_entity("user", user.id)
+            .with_details(json!({ "email": user.email }));
+        append_audit_entry(&mut tx, audit_entry).await?;
+
+        tx.commit().await?;
+
+        Ok(user)
+    }
+
+    pub async fn send_email_verification(
+        pool: &DbPool,
+        mailer: &SharedMailer,
+        base_url: &str,
+        user_id: i64,
+    ) -> AppResult<()> {
+        let user = find_user_by_id(pool, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        if user.is_email_verified() {
+            return Err(AppError::Conflict("Email is already verified".to_string()));
+        }
+
+        let token = Self::issue_token(pool, user.id, UserTokenPurpose::EmailVerification).await?;
+        Self::deliver(
+            mailer,
+            render_email_verification(base_url, &user.email, &token),
+        );
+
+        Ok(())
+    }
+
+    pub async fn verify_email(pool: &DbPool, token: &str) -> AppResult<()> {
+        let mut tx = pool.begin().await?;
+
+        let user_id =
+            Self::consume_token(&mut tx, token, UserTokenPurpose::EmailVerification).await?;
+        mark_email_verified(&mut tx, user_id).await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::EmailVerified, Some(user_id))
+            .with_entity("user", user_id);
+        append_audit_entry(&mut tx, audit_entry).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Succeeds whether or not the email belongs to an account, so the response does not
+    /// reveal which addresses are registered.
+    pub async fn request_password_reset(
+        pool: &DbPool,
+        mailer: &SharedMailer,
+        base_url: &str,
+        email: &str,
+    ) -> AppResult<()> {
+        let Some(user) = find_user_by_email(pool, email.trim()).await? else {
+            return Ok(());
+        };
+
+        let token = Self::issue_token(pool, user.id, UserTokenPurpose::PasswordReset).await?;
+        Self::deliver(mailer, render_password_reset(base_url, &user.email, &token));
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::PasswordResetRequested, Some(user.id))
+            .with_entity("user", user.id);
+        AuditService::log(pool, audit_entry).await?;
+
+        Ok(())
+    }
+
+    /// Sets a new password and signs the user out everywhere. Receiving the reset email also
+    /// proves ownership of the address, so it counts as verified afterwards.
+    pub async fn reset_password(
+        pool: &DbPool,
+        policy: &PasswordPolicy,
+        token: &str,
+        new_password: &str,
+    ) -> AppResult<()> {
+        let mut tx = pool.begin().await?;
+
+        let user_id = Self::consume_token(&mut tx, token, UserTokenPurpose::PasswordReset).await?;
+        let (email, _) = get_user_credentials_for_update(&mut tx, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        // Rejecting the password drops the transaction, so the reset token stays usable.
+        policy
+            .validate(new_password, &email)
+            .map_err(AppError::Validation)?;
+        let password_hash = policy.hash(new_password)?;
+
+        update_user_password(&mut tx, user_id, &password_hash).await?;
+        mark_email_verified(&mut tx, user_id).await?;
+        revoke_user_sessions(&mut tx, user_id, None).await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::PasswordReset, Some(user_id))
+            .with_entity("user", user_id);
+        append_audit_entry(&mut tx, audit_entry).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Replaces the password after checking the current one. Every other session is signed out;
+    /// the one making the change stays signed in.
+    pub async fn change_password(
+        pool: &DbPool,
+        policy: &PasswordPolicy,
+        user_id: i64,
+        session_id: i64,
+        current_password: &str,
+        new_password: &str,
+    ) -> AppResult<()> {
+        let mut tx = pool.begin().await?;
+
+        let (email, password_hash) = get_user_credentials_for_update(&mut tx, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        if !policy.verify(current_password, &password_hash)? {
+            return Err(AppError::Authentication(
+                "Current password is incorrect".to_string(),
+            ));
+        }
+
+        if current_password == new_password {
+            return Err(AppError::Validation(
+                "New password must differ from the current password".to_string(),
+            ));
+        }
+
+        policy
+            .validate(new_password, &email)
+            .map_err(AppError::Validation)?;
+        let new_hash = policy.hash(new_password)?;
+
+        update_user_password(&mut tx, user_id, &new_hash).await?;
+        revoke_user_sessions(&mut tx, user_id, Some(session_id)).await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::PasswordChanged, Some(user_id))
+            .with_entity("user", user_id);
+        append_audit_entry(&mut tx, audit_entry).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Called after a successful login with the plaintext still at hand, so hashes made under
+    /// older Argon2 settings are upgraded without the user noticing.
+    pub async fn rehash_password_if_needed(
+        pool: &DbPool,
+        policy: &PasswordPolicy,
+        user_id: i64,
+        password: &str,
+    ) -> AppResult<()> {
+        let mut tx = pool.begin().await?;
+
+        let Some((_, password_hash)) = get_user_credentials_for_update(&mut tx, user_id).await?
+        else {
+            return Ok(());
+        };
+
+        // The stored hash may have been replaced since the caller verified it.
+        if !policy.needs_rehash(&password_hash) || !policy.verify(password, &password_hash)? {
+            return Ok(());
+        }
+
+        update_user_password(&mut tx, user_id, &policy.hash(password)?).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn issue_token(
+        pool: &DbPool,
+        user_id: i64,
+        purpose: UserTokenPurpose,
+    ) -> AppResult<String> {
+        let token = TokenService::random_token(32);
+        let expires_at = Utc::now() + Duration::seconds(purpose.ttl_seconds());
+
+        let mut tx = pool.begin().await?;
+        invalidate_user_tokens(&mut tx, user_id, purpose).await?;
+        create_user_token(
+            &mut tx,
+            user_id,
+            purpose,
+            &TokenService::hash(&token),
+            expires_at,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(token)
+    }
+
+    async fn consume_token(
+        conn: &mut PgConnection,
+        token: &str,
+        purpose: UserTokenPurpose,
+    ) -> AppResult<i64> {
+        let stored = find_user_token_for_update(conn, &TokenService::hash(token), purpose)
+            .await?
+            .filter(|t| t.is_usable())
+            .ok_or_else(|| AppError::Validation("Invalid or expired token".to_string()))?;
+
+        mark_user_token_used(conn, stored.id).await?;
+
+        Ok(stored.user_id)
+    }
+
+    // Sent in the background so slow SMTP servers do not hold up (or time) the request.
+    fn deliver(mailer: &SharedMailer, message: EmailMessage) {
+        let mailer = mailer.clone();
+        tokio::spawn(
+            async move {
+                if let Err(e) = mailer.send(&message).await {
+                    error!("Failed to send \"{}\" email: {}", message.subject, e);
+                }
+            }
+            .instrument(Span::current()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration as StdDuration;
+
+    use super::*;
+    use crate::config::PasswordConfig;
+    use crate::db::test_pool;
+    use crate::mailer::MemoryMailer;
+
+    #[tokio::test]
+    async fn forgot_password_mails_a_working_reset_link() {
+        let pool = test_pool().await;
+        let policy = PasswordPolicy::from_config(&PasswordConfig::default()).unwrap();
+        let memory = Arc::new(MemoryMailer::new());
+        let mailer: SharedMailer = memory.clone();
+
+        let email = format!(
+            "reset-{}@example.com",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        );
+        let password_hash = policy.hash("original-password-1").unwrap();
+        AccountService::register(
+            &pool,
+            CreateUser::new(email.clone(), password_hash).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        AccountService::request_password_reset(&pool, &mailer, "https://app.example.com/", &email)
+            .await
+            .unwrap();
+
+        // Delivery happens on a spawned task.
+        let mut sent = memory.sent();
+        for _ in 0..50 {
+            if !sent.is_empty() {
+                break;
+            }
+            tokio::time::sleep(StdDuration::from_millis(20)).await;
+            sent = memory.sent();
+        }
+
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, email);
+        let link_prefix = "https://app.example.com/reset-password?token=";
+        let token = sent[0]
+            .body
+            .split_whitespace()
+            .find_map(|word| word.strip_prefix(link_prefix))
+            .expect("reset link in the email body");
+
+        AccountService::reset_password(&pool, &policy, token, "replacement-password-2")
+            .await
+            .unwrap();
+        let (_, stored_hash) = crate::db::get_user_password_hash(&pool, &email)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(
+            policy
+                .verify("replacement-password-2", &stored_hash)
+                .unwrap()
+        );
+        assert!(
+            AccountService::reset_password(&pool, &policy, token, "third-password-3")
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use serde_json::json;
+
+use crate::db::{
+    DbPool, count_user_api_keys, create_api_key, find_api_key_by_prefix, find_user_api_key,
+    list_user_api_keys, revoke_api_key, touch_api_key, update_api_key,
+};
+use crate::errors::{AppError, AppResult};
+use crate::models::{
+    API_KEY_PREFIX, ApiKey, AuditAction, CreateApiKey, CreateAuditEntry, MAX_API_KEYS_PER_USER,
+    UpdateApiKey,
+};
+use crate::services::{AuditService, MultisigService, TokenService};
+
+pub struct ApiKeyService;
+
+impl ApiKeyService {
+    /// Returns the stored key together with the full secret, which is never shown again.
+    pub async fn create_api_key(
+        pool: &DbPool,
+        user_id: i64,
+        key_data: CreateApiKey,
+    ) -> AppResult<(ApiKey, String)> {
+        key_data.validate().map_err(AppError::Validation)?;
+
+        if let Some(multisig_id) = key_data.multisig_id {
+            MultisigService::check_user_is_owner(pool, multisig_id, user_id).await?;
+        }
+
+        if count_user_api_keys(pool, user_id).await? >= MAX_API_KEYS_PER_USER {
+            return Err(AppError::Validation(format!(
+                "Cannot have more than {} active API keys",
+                MAX_API_KEYS_PER_USER
+            )));
+        }
+
+        let mut prefix_bytes = [0u8; 8];
+        OsRng.fill_bytes(&mut prefix_bytes);
+        let prefix = hex::encode(prefix_bytes);
+        let secret = format!(
+            "{}_{}_{}",
+            API_KEY_PREFIX,
+            prefix,
+            TokenService::random_token(32)
+        );
+
+        let api_key = create_api_key(
+            pool,
+            user_id,
+            &key_data,
+            &prefix,
+            &TokenService::hash(&secret),
+        )
+        .await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::ApiKeyCreated, Some(user_id))
+            .with_entity("api_key", api_key.id)
+            .with_details(json!({
+                "name": api_key.name,
+                "prefix": api_key.prefix,
+                "scopes": api_key.scopes,
+                "multisig_id": api_key.multisig_id,
+                "expires_at": api_key.expires_at,
+            }));
+        AuditService::log(pool, audit_entry).await?;
+
+        Ok((api_key, secret))
+    }
+
+    pub async fn list_api_keys(pool: &DbPool, user_id: i64) -> AppResult<Vec<ApiKey>> {
+        list_user_api_keys(pool, user_id).await
+    }
+
+    pub async fn get_api_key(pool: &DbPool, user_id: i64, api_key_id: i64) -> AppResult<ApiKey> {
+        find_user_api_key(pool, user_id, api_key_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("API key not found".to_string()))
+    }
+
+    pub async fn update_api_key(
+        pool: &DbPool,
+        user_id: i64,
+        api_key_id: i64,
+        changes: UpdateApiKey,
+    ) -> AppResult<ApiKey> {
+        let existing = Self::get_api_key(pool, user_id, api_key_id).await?;
+        let key_data = changes.apply(&existing).map_err(AppError::Validation)?;
+
+        let api_key = update_api_key(pool, api_key_id, &key_data).await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::ApiKeyUpdated, Some(user_id))
+            .with_entity("api_key", api_key.id)
+            .with_details(json!({
+                "name": api_key.name,
+                "scopes": api_key.scopes,
+                "expires_at": api_key.expires_at,
+            }));
+        AuditService::log(pool, audit_entry).await?;
+
+        Ok(api_key)
+    }
+
+    pub async fn revoke_api_key(pool: &DbPool, user_id: i64, api_key_id: i64) -> AppResult<()> {
+        if !revoke_api_key(pool, user_id, api_key_id).await? {
+            return Err(AppError::NotFound("API key not found".to_string()));
+        }
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::ApiKeyRevoked, Some(user_id))
+            .with_entity("api_key", api_key_id);
+        AuditService::log(pool, audit_entry).await?;
+
+        Ok(())
+    }
+
+    /// Resolves a presented `msk_<prefix>_<secret>` key. Every failure reads the same so
+    /// callers cannot tell an unknown prefix from a wrong secret or an expired key.
+    pub async fn authenticate(pool: &DbPool, presented: &str) -> AppResult<ApiKey> {
+        let invalid = || AppError::Authentication("Invalid or expired API key".to_string());
+
+        let prefix = presented
+            .strip_prefix(API_KEY_PREFIX)
+            .and_then(|rest| rest.strip_prefix('_'))
+            .and_then(|rest| rest.split_once('_'))
+            .map(|(prefix, _)| prefix)
+            .ok_or_else(invalid)?;
+
+        let api_key = find_api_key_by_prefix(pool, prefix)
+            .await?
+            .ok_or_else(invalid)?;
+
+        // Both sides are SHA-256 digests, so comparing them leaks nothing about the secret.
+        if api_key.key_hash != TokenService::hash(presented) || !api_key.is_usable() {
+            return Err(invalid());
+        }
+
+        touch_api_key(pool, api_key.id).await?;
+
+        Ok(api_key)
+    }
+}
@@ -0,0 +1,111 @@
+use crate::db::{DbPool, append_audit_entry, find_latest_audit_entry, list_audit_entries_after};
+use crate::errors::{AppError, AppResult};
+use crate::models::{
+    AUDIT_GENESIS_HASH, AuditAction, AuditCheckpoint, AuditEntry, AuditSigningKey,
+    AuditVerification, CreateAuditEntry,
+};
+use chrono::Utc;
+use serde_json::json;
+
+const VERIFY_BATCH_SIZE: i64 = 1000;
+
+pub struct AuditService;
+
+impl AuditService {
+    pub async fn log(pool: &DbPool, entry_data: CreateAuditEntry) -> AppResult<AuditEntry> {
+        let mut tx = pool.begin().await?;
+        let entry = append_audit_entry(&mut tx, entry_data).await?;
+        tx.commit().await?;
+
+        Ok(entry)
+    }
+
+    pub async fn list_entries(
+        pool: &DbPool,
+        after_id: i64,
+        limit: i64,
+    ) -> AppResult<Vec<AuditEntry>> {
+        if limit <= 0 || limit > VERIFY_BATCH_SIZE {
+            return Err(AppError::Validation(format!(
+                "Limit must be between 1 and {}",
+                VERIFY_BATCH_SIZE
+            )));
+        }
+
+        list_audit_entries_after(pool, after_id, limit).await
+    }
+
+    pub async fn verify_chain(pool: &DbPool) -> AppResult<AuditVerification> {
+        let mut expected_prev = AUDIT_GENESIS_HASH.to_string();
+        let mut last_entry_id = None;
+        let mut entries_checked = 0;
+        let mut after_id = 0;
+
+        loop {
+            let batch = list_audit_entries_after(pool, after_id, VERIFY_BATCH_SIZE).await?;
+            if batch.is_empty() {
+                break;
+            }
+
+            for entry in &batch {
+                let reason = if entry.prev_hash != expected_prev {
+                    Some("prev_hash does not match the preceding entry".to_string())
+                } else if entry.recompute_hash() != entry.entry_hash {
+                    Some("entry_hash does not match the entry contents".to_string())
+                } else {
+                    None
+                };
+
+                if reason.is_some() {
+                    return Ok(AuditVerification {
+                        valid: false,
+                        entries_checked,
+                        last_entry_id,
+                        last_entry_hash: expected_prev,
+                        broken_at_id: Some(entry.id),
+                        reason,
+                    });
+                }
+
+                expected_prev = entry.entry_hash.clone();
+                last_entry_id = Some(entry.id);
+                entries_checked += 1;
+            }
+
+            after_id = batch.last().map(|e| e.id).unwrap_or(after_id);
+        }
+
+        Ok(AuditVerification {
+            valid: true,
+            entries_checked,
+            last_entry_id,
+            last_entry_hash: expected_prev,
+            broken_at_id: None,
+            reason: None,
+        })
+    }
+
+    pub async fn create_checkpoint(
+        pool: &DbPool,
+        key: &AuditSigningKey,
+        requested_by: Option<i64>,
+    ) -> AppResult<AuditCheckpoint> {
+        let (last_entry_id, last_entry_hash) = match find_latest_audit_entry(pool).await? {
+            Some((id, hash)) => (Some(id), hash),
+            None => (None, AUDIT_GENESIS_HASH.to_string()),
+        };
+
+        let checkpoint = AuditCheckpoint::sign(last_entry_id, last_entry_hash, Utc::now(), key)
+            .map_err(AppError::Internal)?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::CheckpointExported, requested_by)
+            .with_details(json!({
+                "last_entry_id": checkpoint.last_entry_id,
+                "last_entry_hash": checkpoint.last_entry_hash,
+                "signature": checkpoint.signature,
+            }));
+        Self::log(pool, audit_entry).await?;
+
+        Ok(checkpoint)
+    }
+}
@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use tracing::{error, info};
+
+use crate::db::{
+    DbPool, delete_expired_login_challenges, delete_expired_oidc_login_states,
+    delete_expired_refresh_tokens, delete_expired_revoked_tokens, delete_expired_user_tokens,
+    delete_expired_webauthn_challenges, delete_finished_jobs_before, delete_login_attempts_before,
+};
+use crate::errors::AppResult;
+use crate::models::LOGIN_ATTEMPT_WINDOW_SECONDS;
+use crate::services::WorkerMonitor;
+use crate::supervisor::Shutdown;
+
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+const CLEANUP_WORKER_STALE_AFTER: Duration = Duration::from_secs(3 * 3600);
+// Succeeded and cancelled jobs stay visible to admins for a week.
+const FINISHED_JOB_RETENTION_DAYS: i64 = 7;
+pub const CLEANUP_WORKER: &str = "cleanup";
+
+pub struct CleanupService;
+
+impl CleanupService {
+    pub async fn run_worker(pool: DbPool, monitor: WorkerMonitor, shutdown: Shutdown) {
+        monitor.register(CLEANUP_WORKER, Some(CLEANUP_WORKER_STALE_AFTER));
+
+        loop {
+            match Self::purge_expired(&pool).await {
+                Ok(deleted) => {
+                    if deleted > 0 {
+                        info!("Cleanup removed {} expired rows", deleted);
+                    }
+                    monitor.beat(CLEANUP_WORKER);
+                }
+                Err(e) => {
+                    error!("Cleanup run failed: {}", e);
+                    monitor.fail(CLEANUP_WORKER, e);
+                }
+            }
+            if !shutdown.sleep(CLEANUP_INTERVAL).await {
+                break;
+            }
+        }
+    }
+
+    /// Deletes rows nothing reads any more: expired tokens, challenges and login states, login
+    /// attempts that fell out of the throttle window, and old finished jobs.
+    pub async fn purge_expired(pool: &DbPool) -> AppResult<u64> {
+        let attempts_before = Utc::now() - chrono::Duration::seconds(LOGIN_ATTEMPT_WINDOW_SECONDS);
+        let jobs_before = Utc::now() - chrono::Duration::days(FINISHED_JOB_RETENTION_DAYS);
+
+        Ok(delete_expired_revoked_tokens(pool).await?
+            + delete_expired_refresh_tokens(pool).await?
+            + delete_login_attempts_before(pool, attempts_before).await?
+            + delete_expired_login_challenges(pool).await?
+            + delete_expired_webauthn_challenges(pool).await?
+            + delete_expired_oidc_login_states(pool).await?
+            + delete_expired_user_tokens(pool).await?
+            + delete_finished_jobs_before(pool, jobs_before).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration as ChronoDuration;
+
+    use super::*;
+    use crate::db::{create_webauthn_challenge, is_token_revoked, revoke_access_token, test_pool};
+    use crate::models::{CreateUser, WebauthnCeremony};
+    use crate::services::AccountService;
+
+    #[tokio::test]
+    async fn purges_expired_rows_and_keeps_live_ones() {
+        let pool = test_pool().await;
+        let tag = Utc::now().timestamp_nanos_opt().unwrap().to_string();
+        let user = AccountService::register(
+            &pool,
+            CreateUser::new(format!("cleanup-{}@example.com", tag), "unused".to_string()).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let expired = Utc::now() - ChronoDuration::hours(1);
+        let live = Utc::now() + ChronoDuration::hours(1);
+        let (old_jti, new_jti) = (format!("old-{}", tag), format!("new-{}", tag));
+        let mut conn = pool.acquire().await.unwrap();
+        revoke_access_token(&mut conn, &old_jti, user.id, expired)
+            .await
+            .unwrap();
+        revoke_access_token(&mut conn, &new_jti, user.id, live)
+            .await
+            .unwrap();
+        for (challenge, expires_at) in [(&old_jti, expired), (&new_jti, live)] {
+            create_webauthn_challenge(
+                &pool,
+                Some(user.id),
+                WebauthnCeremony::Registration,
+                challenge,
+                expires_at,
+            )
+            .await
+            .unwrap();
+        }
+        sqlx::query(
+            "INSERT INTO login_attempts (email, user_id, succeeded, attempted_at) VALUES ($1, $2, FALSE, $3)",
+        )
+        .bind(&user.email)
+        .bind(user.id)
+        .bind(Utc::now() - ChronoDuration::days(1))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert!(CleanupService::purge_expired(&pool).await.unwrap() >= 3);
+
+        assert!(!is_token_revoked(&pool, &old_jti).await.unwrap());
+        assert!(is_token_revoked(&pool, &new_jti).await.unwrap());
+        let challenges: Vec<String> =
+            sqlx::query_scalar("SELECT challenge FROM webauthn_challenges WHERE user_id = $1")
+                .bind(user.id)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(challenges, vec![new_jti]);
+        let attempts: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM login_attempts WHERE user_id = $1")
+                .bind(user.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(attempts, 0);
+    }
+}
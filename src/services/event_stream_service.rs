@@ -0,0 +1,215 @@
+use std::convert::Infallible;
+use std::time::Duration;
+
+use actix_web::web::Bytes;
+use chrono::{DateTime, Utc};
+use futures_util::{Stream, StreamExt, stream};
+use sqlx::postgres::PgListener;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{error, warn};
+
+use crate::db::{DbPool, find_session_by_id, is_token_revoked};
+use crate::errors::AppResult;
+use crate::models::{PROPOSAL_EVENTS_CHANNEL, ProposalEventNotification};
+use crate::services::WorkerMonitor;
+use crate::supervisor::Shutdown;
+
+const BROADCAST_CAPACITY: usize = 1024;
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+pub const LISTENER_WORKER: &str = "proposal_event_listener";
+
+/// The token a stream was opened with. It is checked again on every keep-alive, so revoking the
+/// session or letting the token expire also ends open streams.
+pub struct StreamSession {
+    pub session_id: i64,
+    pub jti: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl StreamSession {
+    async fn is_live(&self, pool: &DbPool) -> AppResult<bool> {
+        if Utc::now() >= self.expires_at || is_token_revoked(pool, &self.jti).await? {
+            return Ok(false);
+        }
+
+        Ok(find_session_by_id(pool, self.session_id)
+            .await?
+            .is_some_and(|session| session.revoked_at.is_none()))
+    }
+}
+
+#[derive(Clone)]
+pub struct EventBroadcaster {
+    sender: broadcast::Sender<ProposalEventNotification>,
+}
+
+impl Default for EventBroadcaster {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBroadcaster {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(BROADCAST_CAPACITY);
+        Self { sender }
+    }
+
+    // Idle channels are normal, so the listener only reports health when it connects or fails.
+    pub async fn run_listener(self, pool: DbPool, monitor: WorkerMonitor, shutdown: Shutdown) {
+        monitor.register(LISTENER_WORKER, None);
+
+        loop {
+            match self.listen(&pool, &monitor, &shutdown).await {
+                Ok(()) => break,
+                Err(e) => {
+                    error!("Proposal event listener failed: {}", e);
+                    monitor.fail(LISTENER_WORKER, e);
+                }
+            }
+            if !shutdown.sleep(RECONNECT_DELAY).await {
+                break;
+            }
+        }
+    }
+
+    // Returns Ok only when shutdown is requested.
+    async fn listen(
+        &self,
+        pool: &DbPool,
+        monitor: &WorkerMonitor,
+        shutdown: &Shutdown,
+    ) -> Result<(), sqlx::Error> {
+        let mut listener = PgListener::connect_with(pool).await?;
+        listener.listen(PROPOSAL_EVENTS_CHANNEL).await?;
+        monitor.beat(LISTENER_WORKER);
+
+        loop {
+            let notification = tokio::select! {
+                notification = listener.recv() => notification?,
+                _ = shutdown.requested() => return Ok(()),
+            };
+
+            match serde_json::from_str::<ProposalEventNotification>(notification.payload()) {
+                // Sending only fails when nobody is subscribed, which is fine.
+                Ok(event) => {
+                    let _ = self.sender.send(event);
+                }
+                Err(e) => warn!("Ignoring malformed proposal event notification: {}", e),
+            }
+        }
+    }
+
+    pub fn multisig_stream(
+        &self,
+        pool: DbPool,
+        multisig_id: i64,
+        session: StreamSession,
+    ) -> impl Stream<Item = Result<Bytes, Infallible>> + 'static {
+        let receiver = self.sender.subscribe();
+
+        let events = stream::unfold(
+            (receiver, pool, session),
+            move |(mut receiver, pool, session)| async move {
+                loop {
+                    let frame =
+                        match tokio::time::timeout(KEEP_ALIVE_INTERVAL, receiver.recv()).await {
+                            Err(_) => match session.is_live(&pool).await {
+                                Ok(true) => Bytes::from_static(b": keep-alive\n\n"),
+                                Ok(false) => return None,
+                                Err(e) => {
+                                    warn!("Closing event stream, session check failed: {}", e);
+                                    return None;
+                                }
+                            },
+                            Ok(Ok(notification)) if notification.multisig_id == multisig_id => {
+                                Self::sse_frame(&notification)
+                            }
+                            Ok(Ok(_)) => continue,
+                            Ok(Err(RecvError::Lagged(skipped))) => Bytes::from(format!(
+                                "event: lagged\ndata: {{\"skipped\":{}}}\n\n",
+                                skipped
+                            )),
+                            Ok(Err(RecvError::Closed)) => return None,
+                        };
+                    return Some((frame, (receiver, pool, session)));
+                }
+            },
+        );
+
+        stream::once(async { Bytes::from_static(b"retry: 5000\n\n") })
+            .chain(events)
+            .map(Ok)
+    }
+
+    fn sse_frame(notification: &ProposalEventNotification) -> Bytes {
+        let data = serde_json::to_string(notification).unwrap_or_else(|_| "{}".to_string());
+        let event_type = serde_json::to_value(notification.event.event_type)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+
+        Bytes::from(format!(
+            "id: {}\nevent: proposal.{}\ndata: {}\n\n",
+            notification.event.id, event_type, data
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration as ChronoDuration;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::db::{create_session, revoke_session, test_pool};
+    use crate::models::{CreateUser, UpdateUserLogin};
+    use crate::services::AccountService;
+
+    #[tokio::test]
+    async fn revoking_the_session_ends_the_stream_check() {
+        let pool = test_pool().await;
+        let email = format!(
+            "stream-{}@example.com",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        );
+        let user =
+            AccountService::register(&pool, CreateUser::new(email, "unused".to_string()).unwrap())
+                .await
+                .unwrap();
+        let login = UpdateUserLogin {
+            last_login_at: Utc::now(),
+            user_agent: None,
+            ip_address: None,
+        };
+        let mut conn = pool.acquire().await.unwrap();
+        let session_id = create_session(&mut conn, user.id, &login).await.unwrap().id;
+        let session = StreamSession {
+            session_id,
+            jti: format!("stream-{}", session_id),
+            expires_at: Utc::now() + ChronoDuration::minutes(15),
+        };
+
+        assert!(session.is_live(&pool).await.unwrap());
+        revoke_session(&mut conn, session_id).await.unwrap();
+        assert!(!session.is_live(&pool).await.unwrap());
+    }
+
+    // An expired token is caught before any query, so the lazy pool never connects.
+    #[tokio::test(start_paused = true)]
+    async fn an_expired_token_closes_the_stream_at_the_next_keep_alive() {
+        let pool = PgPool::connect_lazy("postgres://unused@localhost/unused").unwrap();
+        let session = StreamSession {
+            session_id: 0,
+            jti: "expired".to_string(),
+            expires_at: Utc::now() - ChronoDuration::seconds(1),
+        };
+
+        let broadcaster = EventBroadcaster::new();
+        let mut stream = Box::pin(broadcaster.multisig_stream(pool, 1, session));
+
+        assert!(stream.next().await.is_some());
+        assert!(stream.next().await.is_none());
+    }
+}
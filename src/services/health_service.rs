@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::db::{DbPool, ping};
+use crate::errors::{AppError, AppResult};
+use crate::migrations::{MigrationState, migration_status};
+use crate::models::{CheckStatus, HealthCheck};
+use crate::solana::SolanaRpc;
+
+struct WorkerState {
+    last_beat: Instant,
+    stale_after: Option<Duration>,
+    error: Option<String>,
+    restarts: u32,
+}
+
+// Background workers report in here so readiness can tell a stuck or crashed loop from an idle one.
+// A worker registered with `stale_after` must beat at least that often; one without it (such as a
+// listener blocked on a socket) only has to avoid reporting an error.
+#[derive(Clone, Default)]
+pub struct WorkerMonitor {
+    workers: Arc<Mutex<HashMap<&'static str, WorkerState>>>,
+}
+
+impl WorkerMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Workers register each time they start, so a restart keeps the count from the previous run.
+    pub fn register(&self, name: &'static str, stale_after: Option<Duration>) {
+        let mut workers = self.lock();
+        let restarts = workers.get(name).map_or(0, |state| state.restarts);
+        workers.insert(
+            name,
+            WorkerState {
+                last_beat: Instant::now(),
+                stale_after,
+                error: None,
+                restarts,
+            },
+        );
+    }
+
+    pub fn beat(&self, name: &'static str) {
+        if let Some(state) = self.lock().get_mut(name) {
+            state.last_beat = Instant::now();
+            state.error = None;
+        }
+    }
+
+    pub fn fail(&self, name: &'static str, error: impl ToString) {
+        if let Some(state) = self.lock().get_mut(name) {
+            state.error = Some(error.to_string());
+        }
+    }
+
+    /// Marks a crashed worker as failed until its replacement registers.
+    pub fn restarting(&self, name: &'static str, reason: impl ToString) {
+        if let Some(state) = self.lock().get_mut(name) {
+            state.error = Some(reason.to_string());
+            state.restarts += 1;
+        }
+    }
+
+    /// Names of unhealthy workers with the reason, sorted by name.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = self
+            .lock()
+            .iter()
+            .filter_map(|(name, state)| {
+                if let Some(error) = &state.error {
+                    return Some(format!("{}: {}", name, error));
+                }
+                let silent = state.last_beat.elapsed();
+                state
+                    .stale_after
+                    .is_some_and(|limit| silent > limit)
+                    .then(|| format!("{}: no heartbeat for {}s", name, silent.as_secs()))
+            })
+            .collect();
+        problems.sort();
+        problems
+    }
+
+    pub fn count(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn restarts(&self) -> u32 {
+        self.lock().values().map(|state| state.restarts).sum()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<&'static str, WorkerState>> {
+        // A panic while holding the lock leaves plain data behind, so keep using it.
+        self.workers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub struct HealthService;
+
+impl HealthService {
+    /// Runs every readiness check concurrently.
+    pub async fn readiness(
+        pool: &DbPool,
+        rpc: Option<&SolanaRpc>,
+        workers: &WorkerMonitor,
+    ) -> Vec<HealthCheck> {
+        let (database, migrations, solana) = tokio::join!(
+            timed("database", async {
+                ping(pool).await?;
+                Ok(None)
+            }),
+            timed("migrations", Self::check_migrations(pool)),
+            async {
+                match rpc {
+                    Some(rpc) => Some(
+                        timed("solana_rpc", async {
+                            Ok(Some(format!("{} is healthy", rpc.health().await?)))
+                        })
+                        .await,
+                    ),
+                    None => None,
+                }
+            },
+        );
+
+        let problems = workers.problems();
+        let workers = HealthCheck {
+            name: "workers",
+            status: if problems.is_empty() {
+                CheckStatus::Ok
+            } else {
+                CheckStatus::Failed
+            },
+            latency_ms: 0.0,
+            detail: Some(match (problems.is_empty(), workers.restarts()) {
+                (true, 0) => format!("{} running", workers.count()),
+                (true, restarts) => format!("{} running, {} restarts", workers.count(), restarts),
+                (false, _) => problems.join("; "),
+            }),
+        };
+
+        [Some(database), Some(migrations), solana, Some(workers)]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    async fn check_migrations(pool: &DbPool) -> AppResult<Option<String>> {
+        let statuses = migration_status(pool).await?;
+        let not_applied: Vec<String> = statuses
+            .iter()
+            .filter(|s| s.state != MigrationState::Applied)
+            .map(|s| format!("{} {}", s.version, s.state.as_str()))
+            .collect();
+
+        if not_applied.is_empty() {
+            Ok(Some(format!("{} applied", statuses.len())))
+        } else {
+            Err(AppError::Internal(not_applied.join(", ")))
+        }
+    }
+}
+
+async fn timed(
+    name: &'static str,
+    check: impl Future<Output = AppResult<Option<String>>>,
+) -> HealthCheck {
+    let started = Instant::now();
+    let result = check.await;
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    match result {
+        Ok(detail) => HealthCheck {
+            name,
+            status: CheckStatus::Ok,
+            latency_ms,
+            detail,
+        },
+        Err(e) => HealthCheck {
+            name,
+            status: CheckStatus::Failed,
+            latency_ms,
+            detail: Some(match e {
+                AppError::Internal(message) => message,
+                other => other.to_string(),
+            }),
+        },
+    }
+}
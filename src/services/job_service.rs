@@ -0,0 +1,320 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::json;
+use sqlx::PgConnection;
+use tracing::{error, warn};
+
+use crate::db::{
+    DbPool, cancel_job, claim_due_jobs, complete_job, enqueue_job, fail_job, find_job_by_id,
+    list_jobs, release_job, retry_job,
+};
+use crate::errors::{AppError, AppResult};
+use crate::models::{
+    AuditAction, CreateAuditEntry, ExpireProposalJob, Job, JobFailure, JobFilter, JobPayload,
+    NewJob, Page, PageRequest, job_retry_delay,
+};
+use crate::services::{AuditService, ProposalService, WorkerMonitor};
+use crate::supervisor::Shutdown;
+
+const JOB_BATCH_SIZE: i64 = 10;
+const JOB_LEASE_SECONDS: i64 = 300;
+const JOB_POLL_INTERVAL: Duration = Duration::from_secs(2);
+// Generous, since a batch may hold several slow jobs.
+const JOB_WORKER_STALE_AFTER: Duration = Duration::from_secs(900);
+pub const JOB_WORKER: &str = "job_runner";
+
+pub struct JobService;
+
+impl JobService {
+    pub async fn enqueue(conn: &mut PgConnection, job: NewJob) -> AppResult<Option<Job>> {
+        enqueue_job(conn, &job).await
+    }
+
+    pub async fn run_worker(pool: DbPool, monitor: WorkerMonitor, shutdown: Shutdown) {
+        monitor.register(JOB_WORKER, Some(JOB_WORKER_STALE_AFTER));
+
+        while !shutdown.is_requested() {
+            let result = Self::run_due(&pool, &shutdown).await;
+            match &result {
+                Ok(_) => monitor.beat(JOB_WORKER),
+                Err(e) => monitor.fail(JOB_WORKER, e),
+            }
+
+            let idle = match result {
+                Ok(0) => true,
+                Ok(_) => false,
+                Err(e) => {
+                    error!("Job batch failed: {}", e);
+                    true
+                }
+            };
+            if idle && !shutdown.sleep(JOB_POLL_INTERVAL).await {
+                break;
+            }
+        }
+    }
+
+    async fn run_due(pool: &DbPool, shutdown: &Shutdown) -> AppResult<usize> {
+        let jobs = claim_due_jobs(pool, JOB_BATCH_SIZE, JOB_LEASE_SECONDS).await?;
+
+        for job in &jobs {
+            // Jobs not started before shutdown go back to the queue instead of waiting out the lease.
+            if shutdown.is_requested() {
+                release_job(pool, job.id, job.attempts).await?;
+                continue;
+            }
+
+            match Self::run(pool, job).await {
+                Ok(()) => complete_job(pool, job.id, job.attempts).await?,
+                Err(failure) => {
+                    let retry_at = match failure {
+                        JobFailure::Transient(_) if job.attempts < job.max_attempts => {
+                            Some(Utc::now() + job_retry_delay(job.attempts - 1))
+                        }
+                        _ => None,
+                    };
+                    if retry_at.is_none() {
+                        warn!(
+                            "Job {} ({}) moved to dead after {} attempts: {}",
+                            job.id,
+                            job.kind,
+                            job.attempts,
+                            failure.message()
+                        );
+                    }
+                    fail_job(pool, job.id, job.attempts, failure.message(), retry_at).await?;
+                }
+            }
+        }
+
+        Ok(jobs.len())
+    }
+
+    // Each job kind is matched to its payload type here. Payloads that no longer decode and kinds
+    // this build does not know are dead-lettered straight away, since retrying cannot fix them.
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(job_id = job.id, kind = %job.kind, attempt = job.attempts)
+    )]
+    async fn run(pool: &DbPool, job: &Job) -> Result<(), JobFailure> {
+        match job.kind.as_str() {
+            ExpireProposalJob::KIND => {
+                let payload: ExpireProposalJob = job.decode().map_err(JobFailure::Permanent)?;
+                ProposalService::expire_if_due(pool, payload.proposal_id)
+                    .await
+                    .map_err(|e| JobFailure::Transient(e.to_string()))
+            }
+            kind => Err(JobFailure::Permanent(format!(
+                "Unknown job kind '{}'",
+                kind
+            ))),
+        }
+    }
+
+    pub async fn list_jobs(
+        pool: &DbPool,
+        filter: &JobFilter,
+        page: &PageRequest,
+    ) -> AppResult<Page<Job>> {
+        list_jobs(pool, filter, page).await
+    }
+
+    pub async fn get_job(pool: &DbPool, job_id: i64) -> AppResult<Job> {
+        find_job_by_id(pool, job_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Job not found".to_string()))
+    }
+
+    pub async fn retry_job(pool: &DbPool, admin_id: i64, job_id: i64) -> AppResult<Job> {
+        let job = Self::get_job(pool, job_id).await?;
+
+        if !job.status.can_retry() {
+            return Err(AppError::Validation(format!(
+                "Job with status {} cannot be retried",
+                job.status.as_str()
+            )));
+        }
+
+        let retried = retry_job(pool, job_id)
+            .await?
+            .ok_or_else(|| AppError::Conflict("Job status changed; reload it".to_string()))?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::JobRetried, Some(admin_id))
+            .with_entity("job", job.id)
+            .with_details(json!({ "kind": job.kind, "previous_status": job.status }));
+        AuditService::log(pool, audit_entry).await?;
+
+        Ok(retried)
+    }
+
+    // A running job cannot be interrupted, but once cancelled its result is discarded.
+    pub async fn cancel_job(pool: &DbPool, admin_id: i64, job_id: i64) -> AppResult<Job> {
+        let job = Self::get_job(pool, job_id).await?;
+
+        if !job.status.can_cancel() {
+            return Err(AppError::Validation(format!(
+                "Job with status {} cannot be cancelled",
+                job.status.as_str()
+            )));
+        }
+
+        let cancelled = cancel_job(pool, job_id)
+            .await?
+            .ok_or_else(|| AppError::Conflict("Job status changed; reload it".to_string()))?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::JobCancelled, Some(admin_id))
+            .with_entity("job", job.id)
+            .with_details(json!({ "kind": job.kind, "previous_status": job.status }));
+        AuditService::log(pool, audit_entry).await?;
+
+        Ok(cancelled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration as ChronoDuration, TimeZone};
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::db::test_pool;
+    use crate::models::{CreateUser, JobStatus};
+    use crate::services::AccountService;
+
+    #[derive(Serialize, Deserialize)]
+    struct NoopJob {
+        label: String,
+    }
+
+    impl JobPayload for NoopJob {
+        const KIND: &'static str = "test_noop";
+    }
+
+    fn unique_label(prefix: &str) -> String {
+        format!("{}-{}", prefix, Utc::now().timestamp_nanos_opt().unwrap())
+    }
+
+    // Not due for a day, so no claim in a concurrently running test picks it up.
+    fn later_job(label: &str) -> NewJob {
+        NewJob::new(&NoopJob {
+            label: label.to_string(),
+        })
+        .unwrap()
+        .run_at(Utc::now() + ChronoDuration::days(1))
+    }
+
+    async fn enqueue(pool: &DbPool, job: NewJob) -> Option<Job> {
+        let mut conn = pool.acquire().await.unwrap();
+        JobService::enqueue(&mut conn, job).await.unwrap()
+    }
+
+    async fn admin_id(pool: &DbPool) -> i64 {
+        let email = format!("{}@example.com", unique_label("jobs-admin"));
+        AccountService::register(pool, CreateUser::new(email, "unused".to_string()).unwrap())
+            .await
+            .unwrap()
+            .id
+    }
+
+    #[tokio::test]
+    async fn unique_keys_allow_one_live_job() {
+        let pool = test_pool().await;
+        let admin_id = admin_id(&pool).await;
+        let key = unique_label("unique");
+
+        let first = enqueue(&pool, later_job("first").unique_key(&key))
+            .await
+            .unwrap();
+        assert!(
+            enqueue(&pool, later_job("second").unique_key(&key))
+                .await
+                .is_none()
+        );
+
+        // Finished jobs release the key; retrying one while another holds it is a conflict.
+        JobService::cancel_job(&pool, admin_id, first.id)
+            .await
+            .unwrap();
+        let replacement = enqueue(&pool, later_job("third").unique_key(&key))
+            .await
+            .unwrap();
+        let error = JobService::retry_job(&pool, admin_id, first.id)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::Conflict(_)), "{}", error);
+
+        JobService::cancel_job(&pool, admin_id, replacement.id)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn retry_and_cancel_follow_the_job_status() {
+        let pool = test_pool().await;
+        let admin_id = admin_id(&pool).await;
+        let job = enqueue(&pool, later_job("cancel")).await.unwrap();
+
+        let error = JobService::retry_job(&pool, admin_id, job.id)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::Validation(_)), "{}", error);
+
+        let cancelled = JobService::cancel_job(&pool, admin_id, job.id)
+            .await
+            .unwrap();
+        assert_eq!(cancelled.status, JobStatus::Cancelled);
+        assert!(cancelled.finished_at.is_some());
+        let error = JobService::cancel_job(&pool, admin_id, job.id)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::Validation(_)), "{}", error);
+
+        let retried = JobService::retry_job(&pool, admin_id, job.id)
+            .await
+            .unwrap();
+        assert_eq!(retried.status, JobStatus::Pending);
+        assert_eq!(retried.attempts, 0);
+        assert!(retried.finished_at.is_none());
+
+        // Retrying makes it due now; cancel it again so no worker runs it.
+        JobService::cancel_job(&pool, admin_id, job.id)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn an_expired_lease_hands_the_job_to_the_next_claim() {
+        let pool = test_pool().await;
+        // Claims take the oldest due job first, so this one is picked ahead of anything else.
+        let run_at = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let job = enqueue(&pool, later_job("lease").run_at(run_at))
+            .await
+            .unwrap();
+
+        let first = claim_due_jobs(&pool, 1, 0).await.unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!((first[0].id, first[0].attempts), (job.id, 1));
+
+        // The zero-second lease has already run out, so the next claim takes the job over.
+        let second = claim_due_jobs(&pool, 1, JOB_LEASE_SECONDS).await.unwrap();
+        assert_eq!(second.len(), 1);
+        assert_eq!((second[0].id, second[0].attempts), (job.id, 2));
+
+        // The first worker finishing late must not touch the second worker's run.
+        complete_job(&pool, job.id, 1).await.unwrap();
+        fail_job(&pool, job.id, 1, "late failure", None)
+            .await
+            .unwrap();
+        release_job(&pool, job.id, 1).await.unwrap();
+        let current = JobService::get_job(&pool, job.id).await.unwrap();
+        assert_eq!(current.status, JobStatus::Running);
+        assert_eq!(current.attempts, 2);
+        assert!(current.last_error.is_none());
+
+        complete_job(&pool, job.id, 2).await.unwrap();
+        let current = JobService::get_job(&pool, job.id).await.unwrap();
+        assert_eq!(current.status, JobStatus::Succeeded);
+    }
+}
@@ -0,0 +1,144 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+
+use crate::db::{
+    DbPool, append_audit_entry, clear_login_failures, find_user_by_id, get_login_attempt_stats,
+    lock_login_attempts, mark_login_attempt_succeeded, record_login_attempt,
+};
+use crate::errors::{AppError, AppResult};
+use crate::metrics::METRICS;
+use crate::models::{
+    AuditAction, CreateAuditEntry, CreateLoginAttempt, LOGIN_ATTEMPT_WINDOW_SECONDS,
+};
+
+pub struct LoginThrottleService;
+
+impl LoginThrottleService {
+    /// Counts an attempt as failed before the credential is verified, checking the throttle and
+    /// inserting the row under a per-account lock so concurrent guesses cannot all slip under the
+    /// limit. Returns the attempt id for `attempt_succeeded`.
+    pub async fn begin_attempt(
+        pool: &DbPool,
+        email: &str,
+        user_id: Option<i64>,
+        ip_address: Option<String>,
+    ) -> AppResult<i64> {
+        let email = email.to_lowercase();
+        let mut tx = pool.begin().await?;
+
+        lock_login_attempts(&mut tx, &email).await?;
+
+        let now = Utc::now();
+        let since = now - Duration::seconds(LOGIN_ATTEMPT_WINDOW_SECONDS);
+        let stats = get_login_attempt_stats(&mut tx, &email, ip_address.as_deref(), since).await?;
+        if let Some(throttle) = stats.throttle(now) {
+            METRICS
+                .login_throttled
+                .with_label_values(&[throttle.reason.as_str()])
+                .inc();
+            return Err(AppError::TooManyRequests(throttle));
+        }
+
+        let attempt_id = record_login_attempt(
+            &mut tx,
+            CreateLoginAttempt {
+                email,
+                user_id,
+                ip_address,
+                succeeded: false,
+            },
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(attempt_id)
+    }
+
+    /// Earlier failures on the account still count; only `record_success` clears those.
+    pub async fn attempt_succeeded(pool: &DbPool, attempt_id: i64) -> AppResult<()> {
+        mark_login_attempt_succeeded(pool, attempt_id).await
+    }
+
+    pub fn count_failure(reason: &str) {
+        METRICS.login_failures.with_label_values(&[reason]).inc();
+    }
+
+    pub async fn record_success(
+        pool: &DbPool,
+        email: &str,
+        user_id: i64,
+        ip_address: Option<String>,
+    ) -> AppResult<()> {
+        let email = email.to_lowercase();
+        let mut tx = pool.begin().await?;
+
+        clear_login_failures(&mut tx, &email).await?;
+        record_login_attempt(
+            &mut tx,
+            CreateLoginAttempt {
+                email,
+                user_id: Some(user_id),
+                ip_address,
+                succeeded: true,
+            },
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn unlock_user(pool: &DbPool, admin_id: i64, user_id: i64) -> AppResult<u64> {
+        let user = find_user_by_id(pool, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        let mut tx = pool.begin().await?;
+
+        let cleared = clear_login_failures(&mut tx, &user.email).await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::LoginUnlocked, Some(admin_id))
+            .with_entity("user", user.id)
+            .with_details(json!({ "cleared_failures": cleared }));
+        append_audit_entry(&mut tx, audit_entry).await?;
+
+        tx.commit().await?;
+
+        Ok(cleared)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::join_all;
+
+    use super::*;
+    use crate::db::test_pool;
+    use crate::models::{ACCOUNT_DELAY_AFTER_FAILURES, LoginThrottleReason};
+
+    #[tokio::test]
+    async fn concurrent_attempts_cannot_overrun_the_account_limit() {
+        let pool = test_pool().await;
+        let email = format!(
+            "burst-{}@example.com",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        );
+
+        let results = join_all(
+            (0..20).map(|_| LoginThrottleService::begin_attempt(&pool, &email, None, None)),
+        )
+        .await;
+
+        let accepted = results.iter().filter(|result| result.is_ok()).count();
+        assert_eq!(accepted as i64, ACCOUNT_DELAY_AFTER_FAILURES);
+        assert!(results.iter().all(|result| match result {
+            Ok(_) => true,
+            Err(AppError::TooManyRequests(throttle)) => {
+                throttle.reason == LoginThrottleReason::AccountDelay
+            }
+            Err(_) => false,
+        }));
+    }
+}
@@ -0,0 +1,61 @@
+use crate::db::{
+    DbPool, count_jobs_by_status, count_proposals_by_status, count_unsent_notifications,
+    get_webhook_backlog,
+};
+use crate::errors::AppResult;
+use crate::metrics::METRICS;
+use crate::models::{JobStatus, ProposalStatus};
+
+pub struct MetricsService;
+
+impl MetricsService {
+    /// Updates the gauges that are read from the pool and the database, ahead of a scrape.
+    pub async fn refresh(pool: &DbPool) -> AppResult<()> {
+        let idle = pool.num_idle() as i64;
+        let size = pool.size() as i64;
+        METRICS
+            .db_pool_connections
+            .with_label_values(&["idle"])
+            .set(idle);
+        METRICS
+            .db_pool_connections
+            .with_label_values(&["in_use"])
+            .set(size - idle);
+        METRICS
+            .db_pool_max_connections
+            .set(pool.options().get_max_connections() as i64);
+
+        let counts = count_proposals_by_status(pool).await?;
+        for status in ProposalStatus::ALL {
+            let count = counts
+                .iter()
+                .find(|(s, _)| *s == status)
+                .map_or(0, |(_, count)| *count);
+            METRICS
+                .proposals
+                .with_label_values(&[status.as_str()])
+                .set(count);
+        }
+
+        let counts = count_jobs_by_status(pool).await?;
+        for status in JobStatus::ALL {
+            let count = counts
+                .iter()
+                .find(|(s, _)| *s == status)
+                .map_or(0, |(_, count)| *count);
+            METRICS
+                .jobs
+                .with_label_values(&[status.as_str()])
+                .set(count);
+        }
+
+        let (pending, oldest_seconds) = get_webhook_backlog(pool).await?;
+        METRICS.webhook_backlog.set(pending);
+        METRICS.webhook_oldest_pending_seconds.set(oldest_seconds);
+        METRICS
+            .notification_backlog
+            .set(count_unsent_notifications(pool).await?);
+
+        Ok(())
+    }
+}
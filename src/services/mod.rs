@@ -1,5 +1,37 @@
+pub mod account_service;
+pub mod api_key_service;
+pub mod audit_service;
+pub mod cleanup_service;
+pub mod event_stream_service;
+pub mod health_service;
+pub mod job_service;
+pub mod login_throttle_service;
+pub mod metrics_service;
 pub mod multisig_service;
+pub mod notification_service;
+pub mod oidc_service;
+pub mod passkey_service;
 pub mod proposal_service;
+pub mod session_service;
+pub mod token_service;
+pub mod two_factor_service;
+pub mod webhook_service;
 
+pub use account_service::*;
+pub use api_key_service::*;
+pub use audit_service::*;
+pub use cleanup_service::*;
+pub use event_stream_service::*;
+pub use health_service::*;
+pub use job_service::*;
+pub use login_throttle_service::*;
+pub use metrics_service::*;
 pub use multisig_service::*;
+pub use notification_service::*;
+pub use oidc_service::*;
+pub use passkey_service::*;
 pub use proposal_service::*;
+pub use session_service::*;
+pub use token_service::*;
+pub use two_factor_service::*;
+pub use webhook_service::*;
@@ -1,6 +1,10 @@
-use crate::db::{DbPool, create_multisig, find_multisig_by_id, list_user_multisigs};
+use crate::db::{
+    DbPool, create_multisig, find_multisig_by_id, list_user_multisigs, update_multisig_step_up,
+};
 use crate::errors::{AppError, AppResult};
-use crate::models::{CreateMultisig, Multisig};
+use crate::models::{AuditAction, CreateAuditEntry, CreateMultisig, Multisig, Page, PageRequest};
+use crate::services::{AuditService, TwoFactorService};
+use serde_json::json;
 
 pub struct MultisigService;
 
@@ -22,6 +26,15 @@ impl MultisigService {
             ));
         }
 
+        let audit_entry = CreateAuditEntry::new(AuditAction::MultisigCreated, Some(created_by))
+            .with_entity("multisig", multisig.id)
+            .with_details(json!({
+                "name": multisig.name,
+                "owners": multisig.owners,
+                "threshold": multisig.threshold,
+            }));
+        AuditService::log(pool, audit_entry).await?;
+
         Ok(multisig)
     }
 
@@ -31,8 +44,12 @@ impl MultisigService {
             .ok_or_else(|| AppError::NotFound("Multisig not found".to_string()))
     }
 
-    pub async fn list_user_multisigs(pool: &DbPool, user_id: i64) -> AppResult<Vec<Multisig>> {
-        list_user_multisigs(pool, user_id).await
+    pub async fn list_user_multisigs(
+        pool: &DbPool,
+        user_id: i64,
+        page: &PageRequest,
+    ) -> AppResult<Page<Multisig>> {
+        list_user_multisigs(pool, user_id, page).await
     }
 
     pub async fn check_user_is_owner(
@@ -50,4 +67,93 @@ impl MultisigService {
 
         Ok(multisig)
     }
+
+    /// Turning the requirement off weakens every owner's protection, so it needs a
+    /// fresh step-up from the caller itself.
+    pub async fn update_step_up_policy(
+        pool: &DbPool,
+        multisig_id: i64,
+        user_id: i64,
+        session_id: i64,
+        require_step_up: bool,
+    ) -> AppResult<Multisig> {
+        let multisig = Self::check_user_is_owner(pool, multisig_id, user_id).await?;
+
+        if multisig.require_step_up == require_step_up {
+            return Ok(multisig);
+        }
+
+        if !require_step_up {
+            TwoFactorService::require_recent_step_up(pool, user_id, session_id).await?;
+        }
+
+        update_multisig_step_up(pool, multisig_id, require_step_up).await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::MultisigStepUpChanged, Some(user_id))
+            .with_entity("multisig", multisig_id)
+            .with_details(json!({ "require_step_up": require_step_up }));
+        AuditService::log(pool, audit_entry).await?;
+
+        Self::get_multisig(pool, multisig_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::db::test_pool;
+    use crate::models::{CreateUser, SortOrder};
+    use crate::services::AccountService;
+
+    #[tokio::test]
+    async fn cursor_pages_cover_each_owned_multisig_once() {
+        let pool = test_pool().await;
+        let email = format!(
+            "pages-{}@example.com",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        );
+        let user =
+            AccountService::register(&pool, CreateUser::new(email, "unused".to_string()).unwrap())
+                .await
+                .unwrap();
+
+        let mut created = Vec::new();
+        for i in 0..5 {
+            let data = CreateMultisig::new(format!("vault {}", i), None, vec![user.id], 1, false);
+            created.push(
+                MultisigService::create_multisig(&pool, data, user.id)
+                    .await
+                    .unwrap()
+                    .id,
+            );
+        }
+
+        for sort in [SortOrder::CreatedAtDesc, SortOrder::CreatedAtAsc] {
+            let mut seen = Vec::new();
+            let mut cursor: Option<String> = None;
+            loop {
+                let page = PageRequest::new(Some(2), cursor.as_deref(), Some(sort)).unwrap();
+                let result = MultisigService::list_user_multisigs(&pool, user.id, &page)
+                    .await
+                    .unwrap();
+                assert_eq!(result.total_count, 5);
+                assert!(result.items.len() <= 2);
+                seen.extend(result.items.iter().map(|m| m.id));
+                match result.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => break,
+                }
+            }
+
+            let mut expected = created.clone();
+            if sort.is_descending() {
+                expected.reverse();
+            }
+            assert_eq!(seen, expected);
+        }
+
+        assert!(PageRequest::new(Some(2), Some("not-a-cursor"), None).is_err());
+    }
 }
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use tracing::error;
+
+use crate::db::{
+    DbPool, claim_pending_notifications, enqueue_notifications, find_notification_preferences,
+    list_users_with_due_notifications, mark_notifications_sent, upsert_notification_preferences,
+};
+use crate::errors::AppResult;
+use crate::mailer::{SharedMailer, templates::render_notifications};
+use crate::models::{
+    NotificationKind, NotificationPreferences, ProposalEvent, ProposalEventType, ProposalStatus,
+    UpdateNotificationPreferences,
+};
+use crate::services::WorkerMonitor;
+use crate::supervisor::Shutdown;
+use sqlx::PgConnection;
+
+const DIGEST_POLL_INTERVAL: Duration = Duration::from_secs(30);
+const DIGEST_BATCH_SIZE: i64 = 100;
+const DIGEST_WORKER_STALE_AFTER: Duration = Duration::from_secs(300);
+pub const DIGEST_WORKER: &str = "notification_digest";
+
+pub struct NotificationService;
+
+impl NotificationService {
+    pub async fn get_preferences(
+        pool: &DbPool,
+        user_id: i64,
+    ) -> AppResult<NotificationPreferences> {
+        Ok(find_notification_preferences(pool, user_id)
+            .await?
+            .unwrap_or_else(|| NotificationPreferences::default_for(user_id)))
+    }
+
+    pub async fn update_preferences(
+        pool: &DbPool,
+        user_id: i64,
+        update: UpdateNotificationPreferences,
+    ) -> AppResult<NotificationPreferences> {
+        let current = Self::get_preferences(pool, user_id).await?;
+
+        upsert_notification_preferences(pool, &update.apply(current)).await
+    }
+
+    // Called inside the transition transaction, so emails are only queued for committed changes.
+    pub async fn enqueue_for_event(
+        conn: &mut PgConnection,
+        multisig_id: i64,
+        event: &ProposalEvent,
+    ) -> AppResult<()> {
+        let kind = match event.event_type {
+            ProposalEventType::Activated => NotificationKind::VoteNeeded,
+            ProposalEventType::Approved if event.new_status == Some(ProposalStatus::Approved) => {
+                NotificationKind::ThresholdReached
+            }
+            _ => return Ok(()),
+        };
+
+        let exclude = match kind {
+            NotificationKind::VoteNeeded => event.actor_id,
+            NotificationKind::ThresholdReached => None,
+        };
+
+        enqueue_notifications(conn, multisig_id, event.proposal_id, kind, exclude).await?;
+
+        Ok(())
+    }
+
+    pub async fn run_digest_worker(
+        pool: DbPool,
+        mailer: SharedMailer,
+        base_url: String,
+        monitor: WorkerMonitor,
+        shutdown: Shutdown,
+    ) {
+        monitor.register(DIGEST_WORKER, Some(DIGEST_WORKER_STALE_AFTER));
+
+        loop {
+            match Self::send_due(&pool, &mailer, &base_url).await {
+                Ok(()) => monitor.beat(DIGEST_WORKER),
+                Err(e) => {
+                    error!("Notification digest run failed: {}", e);
+                    monitor.fail(DIGEST_WORKER, e);
+                }
+            }
+            if !shutdown.sleep(DIGEST_POLL_INTERVAL).await {
+                break;
+            }
+        }
+    }
+
+    async fn send_due(pool: &DbPool, mailer: &SharedMailer, base_url: &str) -> AppResult<()> {
+        for user_id in list_users_with_due_notifications(pool, DIGEST_BATCH_SIZE).await? {
+            let mut tx = pool.begin().await?;
+
+            let pending = claim_pending_notifications(&mut tx, user_id).await?;
+            let ids: Vec<i64> = pending.iter().map(|n| n.id).collect();
+            let relevant: Vec<_> = pending
+                .into_iter()
+                .filter(|n| n.is_still_relevant())
+                .collect();
+
+            if let Some(message) = render_notifications(base_url, &relevant) {
+                // On failure the transaction rolls back and the batch is retried next run.
+                if let Err(e) = mailer.send(&message).await {
+                    error!("Failed to email user {}: {}", user_id, e);
+                    continue;
+                }
+            }
+
+            mark_notifications_sent(&mut tx, &ids).await?;
+            tx.commit().await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,414 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+
+use crate::db::{
+    DbPool, append_audit_entry, consume_oidc_login_state, create_oidc_login_state, create_user,
+    create_user_identity, find_user_by_email, find_user_identity, mark_email_verified,
+    record_identity_login,
+};
+use crate::errors::{AppError, AppResult};
+use crate::models::{AuditAction, CreateAuditEntry, CreateUser, OIDC_LOGIN_STATE_TTL_SECONDS};
+use crate::oidc::{IdTokenClaims, OidcProvider};
+use crate::password::PasswordPolicy;
+use crate::services::TokenService;
+
+pub struct OidcService;
+
+impl OidcService {
+    /// Stores a fresh state, nonce and PKCE verifier and returns the provider URL to redirect to.
+    pub async fn begin_login(pool: &DbPool, provider: &OidcProvider) -> AppResult<String> {
+        let state = TokenService::random_token(32);
+        let nonce = TokenService::random_token(32);
+        let code_verifier = TokenService::random_token(32);
+
+        let url = provider
+            .authorization_url(&state, &nonce, &code_verifier)
+            .await?;
+
+        let expires_at = Utc::now() + Duration::seconds(OIDC_LOGIN_STATE_TTL_SECONDS);
+        create_oidc_login_state(pool, &state, &nonce, &code_verifier, expires_at).await?;
+
+        Ok(url)
+    }
+
+    /// Handles the provider's callback and returns the local user id; the caller issues tokens.
+    pub async fn complete_login(
+        pool: &DbPool,
+        provider: &OidcProvider,
+        policy: &PasswordPolicy,
+        code: &str,
+        state: &str,
+    ) -> AppResult<i64> {
+        let login_state = consume_oidc_login_state(pool, state)
+            .await?
+            .ok_or_else(|| {
+                AppError::Authentication("Login request is invalid or has expired".to_string())
+            })?;
+
+        let id_token = provider
+            .exchange_code(code, &login_state.code_verifier)
+            .await?;
+        let claims = provider
+            .validate_id_token(&id_token, &login_state.nonce)
+            .await?;
+
+        Self::resolve_user(pool, provider.issuer(), policy, &claims).await
+    }
+
+    // A known subject logs straight in. Otherwise the identity is linked to the account with the
+    // same email, but only if the provider vouches for that address; failing that a new account
+    // is created.
+    async fn resolve_user(
+        pool: &DbPool,
+        issuer: &str,
+        policy: &PasswordPolicy,
+        claims: &IdTokenClaims,
+    ) -> AppResult<i64> {
+        let email = claims.email.as_deref().map(str::trim);
+
+        if let Some(identity) = find_user_identity(pool, issuer, &claims.sub).await? {
+            record_identity_login(pool, identity.id, email).await?;
+            return Ok(identity.user_id);
+        }
+
+        let email = email.ok_or_else(|| {
+            AppError::Validation(
+                "The identity provider did not share an email address; add the email scope"
+                    .to_string(),
+            )
+        })?;
+        let email_verified = claims.email_verified == Some(true);
+
+        let existing_user = find_user_by_email(pool, email).await?;
+        if existing_user.is_some() && !email_verified {
+            return Err(AppError::Conflict(
+                "An account with this email already exists and the identity provider has not verified the address"
+                    .to_string(),
+            ));
+        }
+
+        let mut tx = pool.begin().await?;
+
+        let user_id = match existing_user {
+            Some(user) => user.id,
+            None => {
+                // SSO-only accounts get a random password nobody knows; a reset sets a real one.
+                let password_hash = policy.hash(&TokenService::random_token(32))?;
+                let user_data = CreateUser::new(email.to_string(), password_hash)
+                    .map_err(AppError::Validation)?;
+                let user = create_user(&mut tx, user_data).await?;
+
+                let audit_entry = CreateAuditEntry::new(AuditAction::UserRegistered, Some(user.id))
+                    .with_entity("user", user.id)
+                    .with_details(json!({ "email": user.email, "method": "oidc" }));
+                append_audit_entry(&mut tx, audit_entry).await?;
+
+                user.id
+            }
+        };
+
+        create_user_identity(&mut tx, user_id, issuer, &claims.sub, Some(email)).await?;
+        if email_verified {
+            mark_email_verified(&mut tx, user_id).await?;
+        }
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::OidcIdentityLinked, Some(user_id))
+            .with_entity("user", user_id)
+            .with_details(json!({ "issuer": issuer, "subject": claims.sub }));
+        append_audit_entry(&mut tx, audit_entry).await?;
+
+        tx.commit().await?;
+
+        Ok(user_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    use actix_web::{App, HttpResponse, HttpServer, web};
+    use argon2::password_hash::rand_core::{OsRng, RngCore};
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+    use ed25519_dalek::SigningKey;
+    use ed25519_dalek::pkcs8::EncodePrivateKey;
+    use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+    use jsonwebtoken::{Algorithm, EncodingKey, Header, encode};
+    use serde_json::Value;
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::config::{OidcConfig, PasswordConfig};
+    use crate::db::{find_user_by_id, test_pool};
+    use crate::services::AccountService;
+
+    const CLIENT_ID: &str = "test-client";
+    const KEY_ID: &str = "mock-key";
+
+    struct PendingCode {
+        code_challenge: String,
+        redirect_uri: String,
+        claims: Value,
+    }
+
+    // A local identity provider: discovery, JWKS and a token endpoint that enforces PKCE.
+    struct MockProvider {
+        issuer: String,
+        signing_key: SigningKey,
+        codes: Mutex<HashMap<String, PendingCode>>,
+    }
+
+    impl MockProvider {
+        fn start() -> Arc<Self> {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let mut secret = [0u8; 32];
+            OsRng.fill_bytes(&mut secret);
+            let mock = Arc::new(Self {
+                issuer: format!("http://{}", listener.local_addr().unwrap()),
+                signing_key: SigningKey::from_bytes(&secret),
+                codes: Mutex::default(),
+            });
+
+            let state = web::Data::from(mock.clone());
+            let server = HttpServer::new(move || {
+                App::new()
+                    .app_data(state.clone())
+                    .route(
+                        "/.well-known/openid-configuration",
+                        web::get().to(Self::discovery),
+                    )
+                    .route("/jwks", web::get().to(Self::jwks))
+                    .route("/token", web::post().to(Self::token))
+            })
+            .workers(1)
+            .listen(listener)
+            .unwrap()
+            .run();
+            actix_web::rt::spawn(server);
+
+            mock
+        }
+
+        async fn discovery(mock: web::Data<Self>) -> HttpResponse {
+            HttpResponse::Ok().json(json!({
+                "issuer": mock.issuer,
+                "authorization_endpoint": format!("{}/authorize", mock.issuer),
+                "token_endpoint": format!("{}/token", mock.issuer),
+                "jwks_uri": format!("{}/jwks", mock.issuer),
+                "code_challenge_methods_supported": ["S256"],
+            }))
+        }
+
+        async fn jwks(mock: web::Data<Self>) -> HttpResponse {
+            let public_key = mock.signing_key.verifying_key().to_bytes();
+            HttpResponse::Ok().json(json!({
+                "keys": [{
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "x": URL_SAFE_NO_PAD.encode(public_key),
+                    "kid": KEY_ID,
+                    "alg": "EdDSA",
+                    "use": "sig",
+                }]
+            }))
+        }
+
+        async fn token(
+            mock: web::Data<Self>,
+            form: web::Form<HashMap<String, String>>,
+        ) -> HttpResponse {
+            let field = |name: &str| form.get(name).map(String::as_str).unwrap_or_default();
+            let Some(pending) = mock.codes.lock().unwrap().remove(field("code")) else {
+                return HttpResponse::BadRequest().json(json!({ "error": "invalid_grant" }));
+            };
+
+            let verifier_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(field("code_verifier")));
+            if field("grant_type") != "authorization_code"
+                || field("client_id") != CLIENT_ID
+                || field("redirect_uri") != pending.redirect_uri
+                || verifier_challenge != pending.code_challenge
+            {
+                return HttpResponse::BadRequest().json(json!({ "error": "invalid_grant" }));
+            }
+
+            HttpResponse::Ok().json(json!({
+                "access_token": "unused",
+                "token_type": "Bearer",
+                "id_token": mock.sign(&pending.claims),
+            }))
+        }
+
+        fn sign(&self, claims: &Value) -> String {
+            let pem = self.signing_key.to_pkcs8_pem(LineEnding::LF).unwrap();
+            let key = EncodingKey::from_ed_pem(pem.as_bytes()).unwrap();
+            let mut header = Header::new(Algorithm::EdDSA);
+            header.kid = Some(KEY_ID.to_string());
+            encode(&header, claims, &key).unwrap()
+        }
+
+        // Plays the user approving the login at the provider: checks the authorization request
+        // and hands back a code plus the state to return with it.
+        fn authorize(&self, authorization_url: &str, identity: Value) -> (String, String) {
+            let url = reqwest::Url::parse(authorization_url).unwrap();
+            let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+            assert_eq!(params["response_type"], "code");
+            assert_eq!(params["client_id"], CLIENT_ID);
+            assert_eq!(params["code_challenge_method"], "S256");
+            assert!(params["scope"].split(' ').any(|scope| scope == "openid"));
+
+            let now = Utc::now().timestamp();
+            let mut claims = json!({
+                "iss": self.issuer,
+                "aud": CLIENT_ID,
+                "iat": now,
+                "exp": now + 300,
+                "nonce": params["nonce"],
+            });
+            claims
+                .as_object_mut()
+                .unwrap()
+                .extend(identity.as_object().unwrap().clone());
+
+            let code = TokenService::random_token(16);
+            self.codes.lock().unwrap().insert(
+                code.clone(),
+                PendingCode {
+                    code_challenge: params["code_challenge"].clone(),
+                    redirect_uri: params["redirect_uri"].clone(),
+                    claims,
+                },
+            );
+
+            (code, params["state"].clone())
+        }
+    }
+
+    fn provider_for(mock: &MockProvider) -> OidcProvider {
+        let config = OidcConfig {
+            issuer: Some(mock.issuer.clone()),
+            client_id: Some(CLIENT_ID.to_string()),
+            scopes: "openid email".to_string(),
+            ..OidcConfig::default()
+        };
+        OidcProvider::from_config(&config, "http://app.test")
+            .unwrap()
+            .unwrap()
+    }
+
+    fn unique_email(prefix: &str) -> String {
+        format!(
+            "{}-{}@example.com",
+            prefix,
+            Utc::now().timestamp_nanos_opt().unwrap()
+        )
+    }
+
+    fn subject() -> String {
+        TokenService::random_token(12)
+    }
+
+    #[actix_web::test]
+    async fn signs_in_new_and_returning_users() {
+        let pool = test_pool().await;
+        let policy = PasswordPolicy::from_config(&PasswordConfig::default()).unwrap();
+        let mock = MockProvider::start();
+        let provider = provider_for(&mock);
+        let (sub, email) = (subject(), unique_email("sso"));
+        let identity = json!({ "sub": sub, "email": email, "email_verified": true });
+
+        let url = OidcService::begin_login(&pool, &provider).await.unwrap();
+        let (code, state) = mock.authorize(&url, identity.clone());
+        let user_id = OidcService::complete_login(&pool, &provider, &policy, &code, &state)
+            .await
+            .unwrap();
+
+        let user = find_user_by_id(&pool, user_id).await.unwrap().unwrap();
+        assert_eq!(user.email, email);
+        assert!(user.is_email_verified());
+
+        // The state is single use.
+        let error = OidcService::complete_login(&pool, &provider, &policy, &code, &state)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::Authentication(_)));
+
+        let url = OidcService::begin_login(&pool, &provider).await.unwrap();
+        let (code, state) = mock.authorize(&url, identity);
+        let returning = OidcService::complete_login(&pool, &provider, &policy, &code, &state)
+            .await
+            .unwrap();
+        assert_eq!(returning, user_id);
+    }
+
+    #[actix_web::test]
+    async fn links_existing_accounts_only_by_verified_email() {
+        let pool = test_pool().await;
+        let policy = PasswordPolicy::from_config(&PasswordConfig::default()).unwrap();
+        let mock = MockProvider::start();
+        let provider = provider_for(&mock);
+
+        let email = unique_email("link");
+        let existing = AccountService::register(
+            &pool,
+            CreateUser::new(email.clone(), "unused".to_string()).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let url = OidcService::begin_login(&pool, &provider).await.unwrap();
+        let unverified = json!({ "sub": subject(), "email": email, "email_verified": false });
+        let (code, state) = mock.authorize(&url, unverified);
+        let error = OidcService::complete_login(&pool, &provider, &policy, &code, &state)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::Conflict(_)));
+
+        let url = OidcService::begin_login(&pool, &provider).await.unwrap();
+        let verified = json!({ "sub": subject(), "email": email, "email_verified": true });
+        let (code, state) = mock.authorize(&url, verified);
+        let user_id = OidcService::complete_login(&pool, &provider, &policy, &code, &state)
+            .await
+            .unwrap();
+        assert_eq!(user_id, existing.id);
+    }
+
+    #[actix_web::test]
+    async fn rejects_a_wrong_pkce_verifier_or_nonce() {
+        let pool = test_pool().await;
+        let policy = PasswordPolicy::from_config(&PasswordConfig::default()).unwrap();
+        let mock = MockProvider::start();
+        let provider = provider_for(&mock);
+        let identity = json!({ "sub": subject(), "email": unique_email("pkce") });
+
+        // A code bound to another verifier's challenge is refused by the token endpoint.
+        let url = OidcService::begin_login(&pool, &provider).await.unwrap();
+        let (code, state) = mock.authorize(&url, identity.clone());
+        mock.codes
+            .lock()
+            .unwrap()
+            .get_mut(&code)
+            .unwrap()
+            .code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(b"someone else's verifier"));
+        let error = OidcService::complete_login(&pool, &provider, &policy, &code, &state)
+            .await
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("rejected the authorization code"),
+            "{}",
+            error
+        );
+
+        // An ID token minted for a different login request carries the wrong nonce.
+        let url = OidcService::begin_login(&pool, &provider).await.unwrap();
+        let (code, state) = mock.authorize(&url, identity);
+        mock.codes.lock().unwrap().get_mut(&code).unwrap().claims["nonce"] = json!("replayed");
+        let error = OidcService::complete_login(&pool, &provider, &policy, &code, &state)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("nonce mismatch"), "{}", error);
+    }
+}
@@ -0,0 +1,726 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{Duration, Utc};
+use serde_json::{Value, json};
+
+use crate::db::{
+    DbPool, consume_webauthn_challenge, create_passkey_credential, create_webauthn_challenge,
+    delete_passkey, find_passkey_for_update, find_user_by_email, find_user_by_id,
+    find_webauthn_challenge_for_update, list_user_passkeys, passkey_credential_exists,
+    record_passkey_use, record_session_step_up,
+};
+use crate::errors::{AppError, AppResult};
+use crate::jwt::JwtKeySet;
+use crate::models::{
+    AssertionCredential, AuditAction, CreateAuditEntry, CreatePasskeyCredential,
+    MAX_PASSKEY_NAME_LENGTH, PasskeyCredential, RegistrationCredential, TokenPair, UpdateUserLogin,
+    WEBAUTHN_CHALLENGE_TTL_SECONDS, WebauthnCeremony,
+};
+use crate::services::{
+    AuditService, LoginThrottleService, Reauthentication, TokenService, TwoFactorService,
+};
+use crate::webauthn::{COSE_ALG_EDDSA, COSE_ALG_ES256, RelyingParty, decode_base64url};
+
+pub struct PasskeyService;
+
+impl PasskeyService {
+    pub async fn list_passkeys(pool: &DbPool, user_id: i64) -> AppResult<Vec<PasskeyCredential>> {
+        list_user_passkeys(pool, user_id).await
+    }
+
+    pub async fn has_passkeys(pool: &DbPool, user_id: i64) -> AppResult<bool> {
+        Ok(!list_user_passkeys(pool, user_id).await?.is_empty())
+    }
+
+    /// `PublicKeyCredentialCreationOptions` for `navigator.credentials.create()`.
+    pub async fn registration_options(
+        pool: &DbPool,
+        rp: &RelyingParty,
+        user_id: i64,
+        reauth: &Reauthentication<'_>,
+    ) -> AppResult<Value> {
+        TwoFactorService::require_reauthentication(pool, user_id, reauth).await?;
+
+        let user = find_user_by_id(pool, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        let existing = list_user_passkeys(pool, user_id).await?;
+
+        let challenge =
+            Self::issue_challenge(pool, Some(user_id), WebauthnCeremony::Registration).await?;
+
+        Ok(json!({
+            "publicKey": {
+                "rp": { "id": rp.id, "name": rp.name },
+                "user": {
+                    "id": Self::user_handle(user_id),
+                    "name": user.email,
+                    "displayName": user.email,
+                },
+                "challenge": challenge,
+                "pubKeyCredParams": [
+                    { "type": "public-key", "alg": COSE_ALG_EDDSA },
+                    { "type": "public-key", "alg": COSE_ALG_ES256 },
+                ],
+                "timeout": WEBAUTHN_CHALLENGE_TTL_SECONDS * 1000,
+                "excludeCredentials": Self::credential_descriptors(&existing),
+                "authenticatorSelection": {
+                    "residentKey": "preferred",
+                    "userVerification": "required",
+                },
+                "attestation": "none",
+            }
+        }))
+    }
+
+    pub async fn register(
+        pool: &DbPool,
+        rp: &RelyingParty,
+        user_id: i64,
+        reauth: &Reauthentication<'_>,
+        name: Option<String>,
+        credential: &RegistrationCredential,
+    ) -> AppResult<PasskeyCredential> {
+        TwoFactorService::require_reauthentication(pool, user_id, reauth).await?;
+
+        let name = name
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| "Passkey".to_string());
+        if name.len() > MAX_PASSKEY_NAME_LENGTH {
+            return Err(AppError::Validation(format!(
+                "Passkey name cannot exceed {} characters",
+                MAX_PASSKEY_NAME_LENGTH
+            )));
+        }
+
+        let client_data_json = decode_base64url(&credential.response.client_data_json)?;
+        let client_data = rp.verify_client_data(&client_data_json, "webauthn.create")?;
+        Self::consume_challenge(
+            pool,
+            &client_data.challenge,
+            WebauthnCeremony::Registration,
+            Some(user_id),
+        )
+        .await?;
+
+        let attested =
+            rp.verify_attestation(&decode_base64url(&credential.response.attestation_object)?)?;
+        let credential_id = URL_SAFE_NO_PAD.encode(&attested.credential_id);
+        if credential.credential_type != "public-key"
+            || credential.id != credential_id
+            || credential
+                .raw_id
+                .as_ref()
+                .is_some_and(|raw| *raw != credential_id)
+        {
+            return Err(AppError::Validation(
+                "Credential id does not match the attested credential".to_string(),
+            ));
+        }
+
+        if passkey_credential_exists(pool, &credential_id).await? {
+            return Err(AppError::Conflict(
+                "This passkey is already registered".to_string(),
+            ));
+        }
+
+        let passkey = create_passkey_credential(
+            pool,
+            CreatePasskeyCredential {
+                user_id,
+                credential_id,
+                public_key: attested.public_key,
+                algorithm: attested.algorithm,
+                sign_count: attested.sign_count as i64,
+                name,
+            },
+        )
+        .await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::PasskeyRegistered, Some(user_id))
+            .with_entity("passkey", passkey.id)
+            .with_details(json!({ "name": passkey.name }));
+        AuditService::log(pool, audit_entry).await?;
+
+        Ok(passkey)
+    }
+
+    pub async fn delete_passkey(
+        pool: &DbPool,
+        user_id: i64,
+        reauth: &Reauthentication<'_>,
+        passkey_id: i64,
+    ) -> AppResult<()> {
+        TwoFactorService::require_reauthentication(pool, user_id, reauth).await?;
+
+        if !delete_passkey(pool, user_id, passkey_id).await? {
+            return Err(AppError::NotFound("Passkey not found".to_string()));
+        }
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::PasskeyRemoved, Some(user_id))
+            .with_entity("passkey", passkey_id);
+        AuditService::log(pool, audit_entry).await?;
+
+        Ok(())
+    }
+
+    /// `PublicKeyCredentialRequestOptions` for a passkey login. Without an email the browser
+    /// offers discoverable credentials; unknown emails get an empty allow list.
+    pub async fn authentication_options(
+        pool: &DbPool,
+        rp: &RelyingParty,
+        email: Option<&str>,
+    ) -> AppResult<Value> {
+        let allowed = match email {
+            Some(email) => match find_user_by_email(pool, email).await? {
+                Some(user) => list_user_passkeys(pool, user.id).await?,
+                None => Vec::new(),
+            },
+            None => Vec::new(),
+        };
+
+        let challenge = Self::issue_challenge(pool, None, WebauthnCeremony::Authentication).await?;
+
+        Ok(Self::request_options(rp, challenge, &allowed))
+    }
+
+    /// Passkeys with user verification count as both factors, so this skips the TOTP step.
+    pub async fn login(
+        pool: &DbPool,
+        rp: &RelyingParty,
+        keys: &JwtKeySet,
+        credential: &AssertionCredential,
+        login_data: UpdateUserLogin,
+    ) -> AppResult<TokenPair> {
+        let user_id =
+            Self::verify_assertion(pool, rp, credential, WebauthnCeremony::Authentication, None)
+                .await?;
+
+        let user = find_user_by_id(pool, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        LoginThrottleService::record_success(
+            pool,
+            &user.email,
+            user_id,
+            login_data.ip_address.clone(),
+        )
+        .await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::LoginSucceeded, Some(user_id))
+            .with_entity("user", user_id)
+            .with_details(json!({ "method": "passkey" }));
+
+        TokenService::start_session(pool, keys, user_id, login_data, audit_entry).await
+    }
+
+    pub async fn step_up_options(
+        pool: &DbPool,
+        rp: &RelyingParty,
+        user_id: i64,
+    ) -> AppResult<Value> {
+        let passkeys = list_user_passkeys(pool, user_id).await?;
+        if passkeys.is_empty() {
+            return Err(AppError::Validation("No passkeys registered".to_string()));
+        }
+
+        let challenge =
+            Self::issue_challenge(pool, Some(user_id), WebauthnCeremony::StepUp).await?;
+
+        Ok(Self::request_options(rp, challenge, &passkeys))
+    }
+
+    pub async fn step_up(
+        pool: &DbPool,
+        rp: &RelyingParty,
+        user_id: i64,
+        session_id: i64,
+        credential: &AssertionCredential,
+    ) -> AppResult<()> {
+        Self::verify_assertion(
+            pool,
+            rp,
+            credential,
+            WebauthnCeremony::StepUp,
+            Some(user_id),
+        )
+        .await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::StepUpVerified, Some(user_id))
+            .with_entity("session", session_id)
+            .with_details(json!({ "method": "passkey" }));
+        AuditService::log(pool, audit_entry).await?;
+
+        record_session_step_up(pool, session_id).await
+    }
+
+    // Returns the id of the user owning the credential.
+    async fn verify_assertion(
+        pool: &DbPool,
+        rp: &RelyingParty,
+        credential: &AssertionCredential,
+        ceremony: WebauthnCeremony,
+        user_id: Option<i64>,
+    ) -> AppResult<i64> {
+        let client_data_json = decode_base64url(&credential.response.client_data_json)?;
+        let client_data = rp.verify_client_data(&client_data_json, "webauthn.get")?;
+        Self::consume_challenge(pool, &client_data.challenge, ceremony, user_id).await?;
+
+        let mut tx = pool.begin().await?;
+
+        let passkey = find_passkey_for_update(&mut tx, &credential.id)
+            .await?
+            .filter(|p| user_id.is_none_or(|id| id == p.user_id))
+            .ok_or_else(|| AppError::Authentication("Unknown passkey".to_string()))?;
+
+        if let Some(handle) = &credential.response.user_handle
+            && !handle.is_empty()
+            && *handle != Self::user_handle(passkey.user_id)
+        {
+            return Err(AppError::Authentication(
+                "Passkey does not belong to this user".to_string(),
+            ));
+        }
+
+        let sign_count = rp.verify_assertion(
+            &passkey.public_key,
+            &decode_base64url(&credential.response.authenticator_data)?,
+            &client_data_json,
+            &decode_base64url(&credential.response.signature)?,
+        )? as i64;
+
+        // Authenticators that keep a counter must increase it; a stale value suggests a clone.
+        if (sign_count != 0 || passkey.sign_count != 0) && sign_count <= passkey.sign_count {
+            return Err(AppError::Authentication(
+                "Passkey sign counter did not increase".to_string(),
+            ));
+        }
+
+        record_passkey_use(&mut tx, passkey.id, sign_count).await?;
+
+        tx.commit().await?;
+
+        Ok(passkey.user_id)
+    }
+
+    async fn issue_challenge(
+        pool: &DbPool,
+        user_id: Option<i64>,
+        ceremony: WebauthnCeremony,
+    ) -> AppResult<String> {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        let challenge = URL_SAFE_NO_PAD.encode(bytes);
+
+        let expires_at = Utc::now() + Duration::seconds(WEBAUTHN_CHALLENGE_TTL_SECONDS);
+        create_webauthn_challenge(pool, user_id, ceremony, &challenge, expires_at).await?;
+
+        Ok(challenge)
+    }
+
+    // Challenges are spent before the response is verified, so each one is tried at most once.
+    async fn consume_challenge(
+        pool: &DbPool,
+        challenge: &str,
+        ceremony: WebauthnCeremony,
+        user_id: Option<i64>,
+    ) -> AppResult<()> {
+        let mut tx = pool.begin().await?;
+
+        let stored = find_webauthn_challenge_for_update(&mut tx, challenge)
+            .await?
+            .filter(|c| c.is_usable(ceremony) && (user_id.is_none() || c.user_id == user_id))
+            .ok_or_else(|| {
+                AppError::Authentication("Invalid or expired passkey challenge".to_string())
+            })?;
+
+        consume_webauthn_challenge(&mut tx, stored.id).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    fn request_options(
+        rp: &RelyingParty,
+        challenge: String,
+        allowed: &[PasskeyCredential],
+    ) -> Value {
+        json!({
+            "publicKey": {
+                "challenge": challenge,
+                "rpId": rp.id,
+                "timeout": WEBAUTHN_CHALLENGE_TTL_SECONDS * 1000,
+                "allowCredentials": Self::credential_descriptors(allowed),
+                "userVerification": "required",
+            }
+        })
+    }
+
+    fn credential_descriptors(passkeys: &[PasskeyCredential]) -> Vec<Value> {
+        passkeys
+            .iter()
+            .map(|p| json!({ "type": "public-key", "id": p.credential_id }))
+            .collect()
+    }
+
+    // WebAuthn user handles must not contain personal data, so the numeric user id is used.
+    fn user_handle(user_id: i64) -> String {
+        URL_SAFE_NO_PAD.encode(user_id.to_be_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ciborium::Value as Cbor;
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::{Signature, SigningKey};
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::config::{AuthConfig, PasswordConfig, WebauthnConfig};
+    use crate::db::{create_session, test_pool};
+    use crate::models::{AssertionResponseJson, AttestationResponseJson, CreateUser, User};
+    use crate::password::PasswordPolicy;
+    use crate::services::AccountService;
+
+    const PASSWORD: &str = "passkey-test-password-1";
+
+    // Stands in for a platform authenticator: one ES256 key, user present and verified.
+    struct SoftwareAuthenticator {
+        key: SigningKey,
+        credential_id: Vec<u8>,
+        sign_count: u32,
+        origin: String,
+    }
+
+    impl SoftwareAuthenticator {
+        fn new(origin: &str) -> Self {
+            let mut credential_id = vec![0u8; 16];
+            OsRng.fill_bytes(&mut credential_id);
+
+            Self {
+                key: SigningKey::random(&mut OsRng),
+                credential_id,
+                sign_count: 0,
+                origin: origin.to_string(),
+            }
+        }
+
+        fn client_data(&self, ceremony_type: &str, challenge: &str) -> Vec<u8> {
+            json!({ "type": ceremony_type, "challenge": challenge, "origin": self.origin })
+                .to_string()
+                .into_bytes()
+        }
+
+        fn authenticator_data(&self, rp_id: &str, attested: Option<&[u8]>) -> Vec<u8> {
+            let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+            data.push(if attested.is_some() { 0x45 } else { 0x05 });
+            data.extend_from_slice(&self.sign_count.to_be_bytes());
+            data.extend_from_slice(attested.unwrap_or_default());
+            data
+        }
+
+        fn create(&self, rp_id: &str, challenge: &str) -> RegistrationCredential {
+            let point = self.key.verifying_key().to_encoded_point(false);
+            let cose_key = Cbor::Map(vec![
+                (Cbor::Integer(1.into()), Cbor::Integer(2.into())),
+                (
+                    Cbor::Integer(3.into()),
+                    Cbor::Integer(COSE_ALG_ES256.into()),
+                ),
+                (Cbor::Integer((-1).into()), Cbor::Integer(1.into())),
+                (
+                    Cbor::Integer((-2).into()),
+                    Cbor::Bytes(point.x().unwrap().to_vec()),
+                ),
+                (
+                    Cbor::Integer((-3).into()),
+                    Cbor::Bytes(point.y().unwrap().to_vec()),
+                ),
+            ]);
+
+            let mut attested = vec![0u8; 16];
+            attested.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+            attested.extend_from_slice(&self.credential_id);
+            ciborium::into_writer(&cose_key, &mut attested).unwrap();
+
+            let attestation = Cbor::Map(vec![
+                (Cbor::Text("fmt".into()), Cbor::Text("none".into())),
+                (Cbor::Text("attStmt".into()), Cbor::Map(Vec::new())),
+                (
+                    Cbor::Text("authData".into()),
+                    Cbor::Bytes(self.authenticator_data(rp_id, Some(&attested))),
+                ),
+            ]);
+            let mut attestation_object = Vec::new();
+            ciborium::into_writer(&attestation, &mut attestation_object).unwrap();
+
+            let id = URL_SAFE_NO_PAD.encode(&self.credential_id);
+            RegistrationCredential {
+                id: id.clone(),
+                raw_id: Some(id),
+                credential_type: "public-key".to_string(),
+                response: AttestationResponseJson {
+                    client_data_json: URL_SAFE_NO_PAD
+                        .encode(self.client_data("webauthn.create", challenge)),
+                    attestation_object: URL_SAFE_NO_PAD.encode(attestation_object),
+                },
+            }
+        }
+
+        fn get(&mut self, rp_id: &str, challenge: &str) -> AssertionCredential {
+            self.sign_count += 1;
+            let authenticator_data = self.authenticator_data(rp_id, None);
+            let client_data = self.client_data("webauthn.get", challenge);
+
+            let mut signed = authenticator_data.clone();
+            signed.extend_from_slice(&Sha256::digest(&client_data));
+            let signature: Signature = self.key.sign(&signed);
+
+            let id = URL_SAFE_NO_PAD.encode(&self.credential_id);
+            AssertionCredential {
+                id: id.clone(),
+                raw_id: Some(id),
+                credential_type: "public-key".to_string(),
+                response: AssertionResponseJson {
+                    client_data_json: URL_SAFE_NO_PAD.encode(client_data),
+                    authenticator_data: URL_SAFE_NO_PAD.encode(authenticator_data),
+                    signature: URL_SAFE_NO_PAD.encode(signature.to_der().as_bytes()),
+                    user_handle: None,
+                },
+            }
+        }
+    }
+
+    fn challenge_of(options: &Value) -> String {
+        options["publicKey"]["challenge"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    fn login_data() -> UpdateUserLogin {
+        UpdateUserLogin {
+            last_login_at: Utc::now(),
+            user_agent: None,
+            ip_address: None,
+        }
+    }
+
+    // A registered user with a password and one signed-in session.
+    async fn new_user(pool: &DbPool, policy: &PasswordPolicy, prefix: &str) -> (User, i64) {
+        let email = format!(
+            "{}-{}@example.com",
+            prefix,
+            Utc::now().timestamp_nanos_opt().unwrap()
+        );
+        let user = AccountService::register(
+            pool,
+            CreateUser::new(email, policy.hash(PASSWORD).unwrap()).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        let session = create_session(&mut conn, user.id, &login_data())
+            .await
+            .unwrap();
+
+        (user, session.id)
+    }
+
+    fn with_password<'a>(
+        policy: &'a PasswordPolicy,
+        session_id: i64,
+        password: &'a str,
+    ) -> Reauthentication<'a> {
+        Reauthentication {
+            session_id,
+            current_password: Some(password),
+            policy,
+        }
+    }
+
+    #[tokio::test]
+    async fn registers_and_logs_in_with_a_software_authenticator() {
+        let pool = test_pool().await;
+        let rp = RelyingParty::from_config(&WebauthnConfig::default());
+        let keys = JwtKeySet::from_config(&AuthConfig {
+            jwt_secret: Some("passkey-test-secret".to_string()),
+            ..AuthConfig::default()
+        })
+        .unwrap();
+
+        let policy = PasswordPolicy::from_config(&PasswordConfig::default()).unwrap();
+        let (user, session_id) = new_user(&pool, &policy, "passkey").await;
+        let reauth = with_password(&policy, session_id, PASSWORD);
+        let mut authenticator = SoftwareAuthenticator::new(&rp.origin);
+
+        let options = PasskeyService::registration_options(&pool, &rp, user.id, &reauth)
+            .await
+            .unwrap();
+        let credential = authenticator.create(&rp.id, &challenge_of(&options));
+        let passkey = PasskeyService::register(&pool, &rp, user.id, &reauth, None, &credential)
+            .await
+            .unwrap();
+        assert_eq!(passkey.credential_id, credential.id);
+
+        // The account now has a passkey, so the password alone no longer authorizes changes.
+        let error = PasskeyService::register(&pool, &rp, user.id, &reauth, None, &credential)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Step-up"), "{}", error);
+
+        let options = PasskeyService::authentication_options(&pool, &rp, Some(&user.email))
+            .await
+            .unwrap();
+        assert_eq!(
+            options["publicKey"]["allowCredentials"][0]["id"],
+            credential.id
+        );
+        let assertion = authenticator.get(&rp.id, &challenge_of(&options));
+        let tokens = PasskeyService::login(&pool, &rp, &keys, &assertion, login_data())
+            .await
+            .unwrap();
+        assert_eq!(tokens.user_id, user.id);
+
+        assert!(
+            PasskeyService::login(&pool, &rp, &keys, &assertion, login_data())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_foreign_origins_and_cloned_authenticators() {
+        let pool = test_pool().await;
+        let rp = RelyingParty::from_config(&WebauthnConfig::default());
+        let keys = JwtKeySet::from_config(&AuthConfig {
+            jwt_secret: Some("passkey-test-secret".to_string()),
+            ..AuthConfig::default()
+        })
+        .unwrap();
+
+        let policy = PasswordPolicy::from_config(&PasswordConfig::default()).unwrap();
+        let (user, session_id) = new_user(&pool, &policy, "clone").await;
+        let reauth = with_password(&policy, session_id, PASSWORD);
+        let mut authenticator = SoftwareAuthenticator::new(&rp.origin);
+
+        let options = PasskeyService::registration_options(&pool, &rp, user.id, &reauth)
+            .await
+            .unwrap();
+        let credential = authenticator.create(&rp.id, &challenge_of(&options));
+        PasskeyService::register(&pool, &rp, user.id, &reauth, None, &credential)
+            .await
+            .unwrap();
+
+        let mut phishing = SoftwareAuthenticator {
+            origin: "https://evil.example".to_string(),
+            key: authenticator.key.clone(),
+            credential_id: authenticator.credential_id.clone(),
+            sign_count: 10,
+        };
+        let options = PasskeyService::authentication_options(&pool, &rp, None)
+            .await
+            .unwrap();
+        let assertion = phishing.get(&rp.id, &challenge_of(&options));
+        let error = PasskeyService::login(&pool, &rp, &keys, &assertion, login_data())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("origin mismatch"), "{}", error);
+
+        let mut clone = SoftwareAuthenticator {
+            origin: rp.origin.clone(),
+            key: authenticator.key.clone(),
+            credential_id: authenticator.credential_id.clone(),
+            sign_count: 0,
+        };
+        let options = PasskeyService::authentication_options(&pool, &rp, None)
+            .await
+            .unwrap();
+        let assertion = authenticator.get(&rp.id, &challenge_of(&options));
+        PasskeyService::login(&pool, &rp, &keys, &assertion, login_data())
+            .await
+            .unwrap();
+
+        // The copy still reports counter 1, which the server has already seen.
+        let options = PasskeyService::authentication_options(&pool, &rp, None)
+            .await
+            .unwrap();
+        let assertion = clone.get(&rp.id, &challenge_of(&options));
+        let error = PasskeyService::login(&pool, &rp, &keys, &assertion, login_data())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("sign counter"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn changing_passkeys_requires_reauthentication() {
+        let pool = test_pool().await;
+        let rp = RelyingParty::from_config(&WebauthnConfig::default());
+        let policy = PasswordPolicy::from_config(&PasswordConfig::default()).unwrap();
+        let (user, session_id) = new_user(&pool, &policy, "passkey-reauth").await;
+        let mut authenticator = SoftwareAuthenticator::new(&rp.origin);
+
+        // Without a second factor the session alone is not enough; the password is.
+        let bare = Reauthentication {
+            session_id,
+            current_password: None,
+            policy: &policy,
+        };
+        for reauth in [bare, with_password(&policy, session_id, "not-the-password")] {
+            let error = PasskeyService::registration_options(&pool, &rp, user.id, &reauth)
+                .await
+                .unwrap_err();
+            assert!(matches!(error, AppError::Authorization(_)), "{}", error);
+        }
+
+        let reauth = with_password(&policy, session_id, PASSWORD);
+        let options = PasskeyService::registration_options(&pool, &rp, user.id, &reauth)
+            .await
+            .unwrap();
+        let credential = authenticator.create(&rp.id, &challenge_of(&options));
+        let passkey = PasskeyService::register(&pool, &rp, user.id, &reauth, None, &credential)
+            .await
+            .unwrap();
+
+        // With a passkey enrolled, adding another or removing one needs a fresh step-up.
+        let error = PasskeyService::registration_options(&pool, &rp, user.id, &reauth)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::Authorization(_)), "{}", error);
+        let error = PasskeyService::delete_passkey(&pool, user.id, &reauth, passkey.id)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::Authorization(_)), "{}", error);
+
+        let options = PasskeyService::step_up_options(&pool, &rp, user.id)
+            .await
+            .unwrap();
+        let assertion = authenticator.get(&rp.id, &challenge_of(&options));
+        PasskeyService::step_up(&pool, &rp, user.id, session_id, &assertion)
+            .await
+            .unwrap();
+
+        let stepped_up = Reauthentication {
+            session_id,
+            current_password: None,
+            policy: &policy,
+        };
+        // A challenge is single use, so replaying the registration still fails.
+        assert!(
+            PasskeyService::register(&pool, &rp, user.id, &stepped_up, None, &credential)
+                .await
+                .is_err()
+        );
+        PasskeyService::registration_options(&pool, &rp, user.id, &stepped_up)
+            .await
+            .unwrap();
+        PasskeyService::delete_passkey(&pool, user.id, &stepped_up, passkey.id)
+            .await
+            .unwrap();
+        assert!(!PasskeyService::has_passkeys(&pool, user.id).await.unwrap());
+    }
+}
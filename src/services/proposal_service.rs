@@ -1,56 +1,136 @@
 use crate::db::{
-    DbPool, approve_proposal, count_proposal_approvals, create_proposal, find_proposal_by_id,
-    get_proposal_approvals, list_multisig_proposals, update_proposal_status,
+    DbPool, append_audit_entry, approve_proposal, count_proposal_approvals, create_proposal,
+    find_proposal_by_id, find_proposal_for_update, get_proposal_approvals, list_multisig_proposals,
+    list_proposal_events, list_user_inbox, notify_proposal_event, record_proposal_event,
+    revoke_proposal_approval, update_proposal_status,
 };
 use crate::errors::{AppError, AppResult};
+use crate::metrics::METRICS;
 use crate::models::{
-    CreateProposal, Proposal, ProposalApproval, ProposalStatus, UpdateProposalStatus,
+    Actor, ApiKeyScope, CreateAuditEntry, CreateProposal, CreateProposalEvent, ExpireProposalJob,
+    InboxItem, Multisig, NewJob, Page, PageRequest, Proposal, ProposalApproval, ProposalEvent,
+    ProposalEventNotification, ProposalEventType, ProposalFilter, ProposalStatus,
+    UpdateProposalStatus,
+};
+use crate::services::{
+    JobService, MultisigService, NotificationService, TwoFactorService, WebhookService,
 };
-use crate::services::MultisigService;
 use chrono::Utc;
+use serde_json::json;
+use sqlx::PgConnection;
 
 pub struct ProposalService;
 
 impl ProposalService {
+    #[tracing::instrument(level = "debug", skip(pool, proposal_data, actor))]
     pub async fn create_proposal(
         pool: &DbPool,
         proposal_data: CreateProposal,
         multisig_id: i64,
-        created_by: i64,
+        actor: &Actor,
     ) -> AppResult<Proposal> {
         if let Err(msg) = proposal_data.validate() {
             return Err(AppError::Validation(msg));
         }
 
-        MultisigService::check_user_is_owner(pool, multisig_id, created_by).await?;
+        Self::authorize(pool, multisig_id, actor, ApiKeyScope::ProposalsCreate).await?;
+        let created_by = actor.user_id;
+
+        let mut tx = pool.begin().await?;
+
+        let proposal = create_proposal(&mut tx, proposal_data, multisig_id, created_by).await?;
+
+        let event = CreateProposalEvent::new(
+            proposal.id,
+            ProposalEventType::Created,
+            Some(created_by),
+            None,
+            Some(proposal.status),
+        )
+        .with_metadata(json!({ "title": proposal.title, "expires_at": proposal.expires_at }));
+        Self::record_event(&mut tx, multisig_id, event).await?;
+
+        if let Some(expires_at) = proposal.expires_at {
+            let job = NewJob::new(&ExpireProposalJob {
+                proposal_id: proposal.id,
+            })?
+            .run_at(expires_at)
+            .unique_key(proposal.id.to_string());
+            JobService::enqueue(&mut tx, job).await?;
+        }
+
+        tx.commit().await?;
 
-        create_proposal(pool, proposal_data, multisig_id, created_by).await
+        Ok(proposal)
     }
 
+    #[tracing::instrument(level = "debug", skip(pool))]
     pub async fn get_proposal(pool: &DbPool, proposal_id: i64) -> AppResult<Proposal> {
         find_proposal_by_id(pool, proposal_id)
             .await?
             .ok_or_else(|| AppError::NotFound("Proposal not found".to_string()))
     }
 
+    async fn get_proposal_for_update(
+        conn: &mut PgConnection,
+        proposal_id: i64,
+    ) -> AppResult<Proposal> {
+        find_proposal_for_update(conn, proposal_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Proposal not found".to_string()))
+    }
+
+    #[tracing::instrument(level = "debug", skip(pool, actor))]
+    pub async fn view_proposal(
+        pool: &DbPool,
+        proposal_id: i64,
+        actor: &Actor,
+    ) -> AppResult<Proposal> {
+        let proposal = Self::get_proposal(pool, proposal_id).await?;
+        Self::authorize(
+            pool,
+            proposal.multisig_id,
+            actor,
+            ApiKeyScope::ProposalsRead,
+        )
+        .await?;
+
+        Ok(proposal)
+    }
+
+    #[tracing::instrument(level = "debug", skip(pool, actor, filter, page))]
     pub async fn list_multisig_proposals(
         pool: &DbPool,
         multisig_id: i64,
-        user_id: i64,
-    ) -> AppResult<Vec<Proposal>> {
-        MultisigService::check_user_is_owner(pool, multisig_id, user_id).await?;
+        actor: &Actor,
+        filter: &ProposalFilter,
+        page: &PageRequest,
+    ) -> AppResult<Page<Proposal>> {
+        if let Err(msg) = filter.validate() {
+            return Err(AppError::Validation(msg));
+        }
+
+        Self::authorize(pool, multisig_id, actor, ApiKeyScope::ProposalsRead).await?;
 
-        list_multisig_proposals(pool, multisig_id).await
+        list_multisig_proposals(pool, multisig_id, filter, page).await
     }
 
+    #[tracing::instrument(level = "debug", skip(pool, actor))]
     pub async fn activate_proposal(
         pool: &DbPool,
         proposal_id: i64,
-        user_id: i64,
+        actor: &Actor,
     ) -> AppResult<Proposal> {
         let proposal = Self::get_proposal(pool, proposal_id).await?;
 
-        MultisigService::check_user_is_owner(pool, proposal.multisig_id, user_id).await?;
+        Self::authorize(
+            pool,
+            proposal.multisig_id,
+            actor,
+            ApiKeyScope::ProposalsCreate,
+        )
+        .await?;
+        let user_id = actor.user_id;
 
         if proposal.status != ProposalStatus::Draft {
             return Err(AppError::Validation(format!(
@@ -59,24 +139,52 @@ impl ProposalService {
             )));
         }
 
-        let status_update = UpdateProposalStatus {
-            status: ProposalStatus::Active,
-            executed_at: None,
-        };
-
-        update_proposal_status(pool, proposal_id, status_update).await?;
+        Self::transition(
+            pool,
+            &proposal,
+            Some(user_id),
+            ProposalEventType::Activated,
+            UpdateProposalStatus {
+                status: ProposalStatus::Active,
+                executed_at: None,
+            },
+        )
+        .await?;
 
         Self::get_proposal(pool, proposal_id).await
     }
 
+    #[tracing::instrument(level = "debug", skip(pool, actor))]
     pub async fn approve_proposal(
         pool: &DbPool,
         proposal_id: i64,
-        user_id: i64,
+        actor: &Actor,
     ) -> AppResult<(ProposalApproval, Proposal)> {
         let proposal = Self::get_proposal(pool, proposal_id).await?;
 
-        MultisigService::check_user_is_owner(pool, proposal.multisig_id, user_id).await?;
+        let multisig = Self::authorize(
+            pool,
+            proposal.multisig_id,
+            actor,
+            ApiKeyScope::ProposalsApprove,
+        )
+        .await?;
+        let user_id = actor.user_id;
+
+        if multisig.require_step_up {
+            Self::require_step_up(pool, actor).await?;
+        }
+
+        let mut tx = pool.begin().await?;
+
+        // Checked again under the row lock; the read above only served authorization.
+        let proposal = Self::get_proposal_for_update(&mut tx, proposal_id).await?;
+
+        if proposal.status == ProposalStatus::Active && proposal.is_expired() {
+            tx.rollback().await?;
+            Self::expire_if_due(pool, proposal_id).await?;
+            return Err(AppError::Validation("Proposal has expired".to_string()));
+        }
 
         if !proposal.can_be_approved() {
             return Err(AppError::Validation(format!(
@@ -85,17 +193,37 @@ impl ProposalService {
             )));
         }
 
-        let approval = approve_proposal(pool, proposal_id, user_id).await?;
-
-        let multisig = MultisigService::get_multisig(pool, proposal.multisig_id).await?;
-        let approval_count = count_proposal_approvals(pool, proposal_id).await?;
+        let approval = approve_proposal(&mut tx, proposal_id, user_id).await?;
+        let approval_count = count_proposal_approvals(&mut tx, proposal_id).await?;
 
-        let updated_proposal = if approval_count >= multisig.threshold as i64 {
+        let new_status = if approval_count >= multisig.threshold as i64 {
             let status_update = UpdateProposalStatus {
                 status: ProposalStatus::Approved,
                 executed_at: None,
             };
-            update_proposal_status(pool, proposal_id, status_update).await?;
+            update_proposal_status(&mut tx, proposal_id, status_update).await?;
+            ProposalStatus::Approved
+        } else {
+            proposal.status
+        };
+
+        let event = CreateProposalEvent::new(
+            proposal_id,
+            ProposalEventType::Approved,
+            Some(user_id),
+            Some(proposal.status),
+            Some(new_status),
+        )
+        .with_metadata(json!({
+            "approval_count": approval_count,
+            "threshold": multisig.threshold,
+        }));
+        Self::record_event(&mut tx, proposal.multisig_id, event).await?;
+
+        tx.commit().await?;
+        METRICS.proposal_approvals.inc();
+
+        let updated_proposal = if new_status != proposal.status {
             Self::get_proposal(pool, proposal_id).await?
         } else {
             proposal
@@ -104,14 +232,76 @@ impl ProposalService {
         Ok((approval, updated_proposal))
     }
 
+    #[tracing::instrument(level = "debug", skip(pool, actor))]
+    pub async fn revoke_approval(
+        pool: &DbPool,
+        proposal_id: i64,
+        actor: &Actor,
+    ) -> AppResult<Proposal> {
+        let proposal = Self::get_proposal(pool, proposal_id).await?;
+
+        let multisig = Self::authorize(
+            pool,
+            proposal.multisig_id,
+            actor,
+            ApiKeyScope::ProposalsApprove,
+        )
+        .await?;
+        let user_id = actor.user_id;
+
+        let mut tx = pool.begin().await?;
+
+        // Without the lock an approval racing this revoke could count it towards the threshold.
+        let proposal = Self::get_proposal_for_update(&mut tx, proposal_id).await?;
+
+        if proposal.status != ProposalStatus::Active {
+            return Err(AppError::Validation(format!(
+                "Cannot revoke approval of proposal with status {:?}",
+                proposal.status
+            )));
+        }
+
+        revoke_proposal_approval(&mut tx, proposal_id, user_id).await?;
+        let approval_count = count_proposal_approvals(&mut tx, proposal_id).await?;
+
+        let event = CreateProposalEvent::new(
+            proposal_id,
+            ProposalEventType::Revoked,
+            Some(user_id),
+            Some(proposal.status),
+            Some(proposal.status),
+        )
+        .with_metadata(json!({
+            "approval_count": approval_count,
+            "threshold": multisig.threshold,
+        }));
+        Self::record_event(&mut tx, proposal.multisig_id, event).await?;
+
+        tx.commit().await?;
+
+        Self::get_proposal(pool, proposal_id).await
+    }
+
+    #[tracing::instrument(level = "debug", skip(pool, actor))]
     pub async fn execute_proposal(
         pool: &DbPool,
         proposal_id: i64,
-        user_id: i64,
+        actor: &Actor,
     ) -> AppResult<Proposal> {
         let proposal = Self::get_proposal(pool, proposal_id).await?;
 
-        MultisigService::check_user_is_owner(pool, proposal.multisig_id, user_id).await?;
+        let multisig = Self::authorize(
+            pool,
+            proposal.multisig_id,
+            actor,
+            ApiKeyScope::ProposalsExecute,
+        )
+        .await?;
+        let user_id = actor.user_id;
+
+        if multisig.require_step_up {
+            Self::require_step_up(pool, actor).await?;
+        }
 
         if proposal.status != ProposalStatus::Approved {
             return Err(AppError::Validation(format!(
@@ -120,24 +310,37 @@ impl ProposalService {
             )));
         }
 
-        let status_update = UpdateProposalStatus {
-            status: ProposalStatus::Executed,
-            executed_at: Some(Utc::now()),
-        };
-
-        update_proposal_status(pool, proposal_id, status_update).await?;
+        Self::transition(
+            pool,
+            &proposal,
+            Some(user_id),
+            ProposalEventType::Executed,
+            UpdateProposalStatus {
+                status: ProposalStatus::Executed,
+                executed_at: Some(Utc::now()),
+            },
+        )
+        .await?;
 
         Self::get_proposal(pool, proposal_id).await
     }
 
+    #[tracing::instrument(level = "debug", skip(pool, actor))]
     pub async fn reject_proposal(
         pool: &DbPool,
         proposal_id: i64,
-        user_id: i64,
+        actor: &Actor,
     ) -> AppResult<Proposal> {
         let proposal = Self::get_proposal(pool, proposal_id).await?;
 
-        MultisigService::check_user_is_owner(pool, proposal.multisig_id, user_id).await?;
+        Self::authorize(
+            pool,
+            proposal.multisig_id,
+            actor,
+            ApiKeyScope::ProposalsApprove,
+        )
+        .await?;
+        let user_id = actor.user_id;
 
         if !matches!(
             proposal.status,
@@ -149,24 +352,320 @@ impl ProposalService {
             )));
         }
 
-        let status_update = UpdateProposalStatus {
-            status: ProposalStatus::Rejected,
-            executed_at: None,
+        Self::transition(
+            pool,
+            &proposal,
+            Some(user_id),
+            ProposalEventType::Rejected,
+            UpdateProposalStatus {
+                status: ProposalStatus::Rejected,
+                executed_at: None,
+            },
+        )
+        .await?;
+
+        Self::get_proposal(pool, proposal_id).await
+    }
+
+    #[tracing::instrument(level = "debug", skip(pool))]
+    pub async fn expire_proposal(pool: &DbPool, proposal_id: i64) -> AppResult<()> {
+        let proposal = Self::get_proposal(pool, proposal_id).await?;
+
+        Self::transition(
+            pool,
+            &proposal,
+            None,
+            ProposalEventType::Expired,
+            UpdateProposalStatus {
+                status: ProposalStatus::Expired,
+                executed_at: None,
+            },
+        )
+        .await
+    }
+
+    /// Expires the proposal if it is still active past its deadline; anything else is left alone.
+    #[tracing::instrument(level = "debug", skip(pool))]
+    pub async fn expire_if_due(pool: &DbPool, proposal_id: i64) -> AppResult<()> {
+        let Some(proposal) = find_proposal_by_id(pool, proposal_id).await? else {
+            return Ok(());
         };
 
-        update_proposal_status(pool, proposal_id, status_update).await?;
+        if proposal.status == ProposalStatus::Active && proposal.is_expired() {
+            Self::expire_proposal(pool, proposal_id).await?;
+        }
 
-        Self::get_proposal(pool, proposal_id).await
+        Ok(())
     }
 
+    #[tracing::instrument(level = "debug", skip(pool))]
+    pub async fn get_user_inbox(pool: &DbPool, user_id: i64) -> AppResult<Vec<InboxItem>> {
+        list_user_inbox(pool, user_id).await
+    }
+
+    #[tracing::instrument(level = "debug", skip(pool, actor))]
     pub async fn get_proposal_approvals(
         pool: &DbPool,
         proposal_id: i64,
-        user_id: i64,
+        actor: &Actor,
     ) -> AppResult<Vec<ProposalApproval>> {
         let proposal = Self::get_proposal(pool, proposal_id).await?;
-        MultisigService::check_user_is_owner(pool, proposal.multisig_id, user_id).await?;
+        Self::authorize(
+            pool,
+            proposal.multisig_id,
+            actor,
+            ApiKeyScope::ProposalsRead,
+        )
+        .await?;
 
         get_proposal_approvals(pool, proposal_id).await
     }
+
+    #[tracing::instrument(level = "debug", skip(pool, actor))]
+    pub async fn get_proposal_history(
+        pool: &DbPool,
+        proposal_id: i64,
+        actor: &Actor,
+    ) -> AppResult<Vec<ProposalEvent>> {
+        let proposal = Self::get_proposal(pool, proposal_id).await?;
+        Self::authorize(
+            pool,
+            proposal.multisig_id,
+            actor,
+            ApiKeyScope::ProposalsRead,
+        )
+        .await?;
+
+        list_proposal_events(pool, proposal_id).await
+    }
+
+    /// Owners only, and for API keys only within the key's scopes and multisig.
+    async fn authorize(
+        pool: &DbPool,
+        multisig_id: i64,
+        actor: &Actor,
+        scope: ApiKeyScope,
+    ) -> AppResult<Multisig> {
+        actor
+            .check_scope(scope, multisig_id)
+            .map_err(AppError::Authorization)?;
+
+        MultisigService::check_user_is_owner(pool, multisig_id, actor.user_id).await
+    }
+
+    async fn require_step_up(pool: &DbPool, actor: &Actor) -> AppResult<()> {
+        let Some(session_id) = actor.session_id else {
+            return Err(AppError::Authorization(
+                "This multisig requires step-up authentication, which API keys cannot provide"
+                    .to_string(),
+            ));
+        };
+
+        TwoFactorService::require_recent_step_up(pool, actor.user_id, session_id).await
+    }
+
+    async fn transition(
+        pool: &DbPool,
+        proposal: &Proposal,
+        actor_id: Option<i64>,
+        event_type: ProposalEventType,
+        status_update: UpdateProposalStatus,
+    ) -> AppResult<()> {
+        let new_status = status_update.status;
+
+        let mut tx = pool.begin().await?;
+
+        let old_status = update_proposal_status(&mut tx, proposal.id, status_update).await?;
+
+        let event = CreateProposalEvent::new(
+            proposal.id,
+            event_type,
+            actor_id,
+            Some(old_status),
+            Some(new_status),
+        );
+        Self::record_event(&mut tx, proposal.multisig_id, event).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn record_event(
+        conn: &mut PgConnection,
+        multisig_id: i64,
+        event: CreateProposalEvent,
+    ) -> AppResult<ProposalEvent> {
+        let event = record_proposal_event(conn, event).await?;
+        append_audit_entry(conn, CreateAuditEntry::from(&event)).await?;
+        WebhookService::enqueue_for_event(conn, multisig_id, &event).await?;
+        NotificationService::enqueue_for_event(conn, multisig_id, &event).await?;
+
+        let notification = ProposalEventNotification {
+            multisig_id,
+            event: event.clone(),
+        };
+        notify_proposal_event(conn, &notification).await?;
+
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::db::test_pool;
+    use crate::models::{CreateMultisig, CreateUser, InboxAction};
+    use crate::services::AccountService;
+
+    async fn owners(pool: &DbPool, count: usize) -> Vec<Actor> {
+        let mut actors = Vec::new();
+        for i in 0..count {
+            let email = format!(
+                "owner{}-{}@example.com",
+                i,
+                Utc::now().timestamp_nanos_opt().unwrap()
+            );
+            let user = AccountService::register(
+                pool,
+                CreateUser::new(email, "unused".to_string()).unwrap(),
+            )
+            .await
+            .unwrap();
+            actors.push(Actor {
+                user_id: user.id,
+                session_id: None,
+                api_key: None,
+            });
+        }
+        actors
+    }
+
+    async fn multisig(pool: &DbPool, owners: &[Actor], threshold: i32) -> i64 {
+        let ids = owners.iter().map(|owner| owner.user_id).collect();
+        let data = CreateMultisig::new("shared vault".to_string(), None, ids, threshold, false);
+        MultisigService::create_multisig(pool, data, owners[0].user_id)
+            .await
+            .unwrap()
+            .id
+    }
+
+    async fn active_proposal(pool: &DbPool, multisig_id: i64, creator: &Actor) -> i64 {
+        let data = CreateProposal::new("pay the auditors".to_string(), None, None, None);
+        let proposal = ProposalService::create_proposal(pool, data, multisig_id, creator)
+            .await
+            .unwrap();
+        ProposalService::activate_proposal(pool, proposal.id, creator)
+            .await
+            .unwrap();
+        proposal.id
+    }
+
+    #[tokio::test]
+    async fn awaiting_approval_lists_only_active_proposals_the_owner_has_not_approved() {
+        let pool = test_pool().await;
+        let owners = owners(&pool, 2).await;
+        let (alice, bob) = (&owners[0], &owners[1]);
+        let multisig_id = multisig(&pool, &owners, 2).await;
+
+        let untouched = active_proposal(&pool, multisig_id, alice).await;
+        let approved_by_alice = active_proposal(&pool, multisig_id, alice).await;
+        ProposalService::approve_proposal(&pool, approved_by_alice, alice)
+            .await
+            .unwrap();
+        let draft = CreateProposal::new("still drafting".to_string(), None, None, None);
+        ProposalService::create_proposal(&pool, draft, multisig_id, alice)
+            .await
+            .unwrap();
+
+        let awaiting = |actor: &Actor| {
+            let filter = ProposalFilter {
+                awaiting_approval_by: Some(actor.user_id),
+                ..Default::default()
+            };
+            let actor = actor.clone();
+            let pool = pool.clone();
+            async move {
+                let page = PageRequest::new(None, None, None).unwrap();
+                ProposalService::list_multisig_proposals(&pool, multisig_id, &actor, &filter, &page)
+                    .await
+                    .unwrap()
+                    .items
+                    .into_iter()
+                    .map(|p| p.id)
+                    .collect::<HashSet<_>>()
+            }
+        };
+
+        assert_eq!(awaiting(alice).await, HashSet::from([untouched]));
+        assert_eq!(
+            awaiting(bob).await,
+            HashSet::from([untouched, approved_by_alice])
+        );
+
+        let inbox: Vec<_> = ProposalService::get_user_inbox(&pool, alice.user_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|item| item.multisig_id == multisig_id)
+            .map(|item| (item.proposal_id, item.action))
+            .collect();
+        assert_eq!(inbox, vec![(untouched, InboxAction::Approve)]);
+    }
+
+    #[tokio::test]
+    async fn racing_approvals_and_revokes_keep_status_and_count_in_step() {
+        let pool = test_pool().await;
+        let owners = owners(&pool, 3).await;
+        let (alice, bob, carol) = (&owners[0], &owners[1], &owners[2]);
+        let multisig_id = multisig(&pool, &owners, 2).await;
+
+        // Two approvals arriving together must still reach the threshold.
+        let proposal_id = active_proposal(&pool, multisig_id, alice).await;
+        let (first, second) = tokio::join!(
+            ProposalService::approve_proposal(&pool, proposal_id, alice),
+            ProposalService::approve_proposal(&pool, proposal_id, bob),
+        );
+        first.unwrap();
+        second.unwrap();
+        let proposal = ProposalService::get_proposal(&pool, proposal_id)
+            .await
+            .unwrap();
+        assert_eq!(proposal.status, ProposalStatus::Approved);
+
+        // A revoke racing the approval that would meet the threshold either lands first, leaving
+        // one approval on an active proposal, or is refused because the proposal is approved.
+        for _ in 0..5 {
+            let proposal_id = active_proposal(&pool, multisig_id, alice).await;
+            ProposalService::approve_proposal(&pool, proposal_id, alice)
+                .await
+                .unwrap();
+
+            let (revoked, approved) = tokio::join!(
+                ProposalService::revoke_approval(&pool, proposal_id, alice),
+                ProposalService::approve_proposal(&pool, proposal_id, carol),
+            );
+            approved.unwrap();
+
+            let proposal = ProposalService::get_proposal(&pool, proposal_id)
+                .await
+                .unwrap();
+            let approvals = ProposalService::get_proposal_approvals(&pool, proposal_id, alice)
+                .await
+                .unwrap();
+            match revoked {
+                Ok(_) => {
+                    assert_eq!(proposal.status, ProposalStatus::Active);
+                    assert_eq!(approvals.len(), 1);
+                }
+                Err(AppError::Validation(_)) => {
+                    assert_eq!(proposal.status, ProposalStatus::Approved);
+                    assert_eq!(approvals.len(), 2);
+                }
+                Err(e) => panic!("unexpected revoke error: {}", e),
+            }
+        }
+    }
 }
@@ -0,0 +1,33 @@
+use crate::db::{
+    DbPool, append_audit_entry, find_session_by_id, list_user_sessions, revoke_session,
+};
+use crate::errors::{AppError, AppResult};
+use crate::models::{AuditAction, CreateAuditEntry, Session};
+
+pub struct SessionService;
+
+impl SessionService {
+    pub async fn list_sessions(pool: &DbPool, user_id: i64) -> AppResult<Vec<Session>> {
+        list_user_sessions(pool, user_id).await
+    }
+
+    pub async fn revoke_session(pool: &DbPool, user_id: i64, session_id: i64) -> AppResult<()> {
+        // Other users' sessions are reported as missing rather than forbidden.
+        let session = find_session_by_id(pool, session_id)
+            .await?
+            .filter(|s| s.user_id == user_id && s.revoked_at.is_none())
+            .ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
+
+        let mut tx = pool.begin().await?;
+
+        revoke_session(&mut tx, session.id).await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::SessionRevoked, Some(user_id))
+            .with_entity("session", session.id);
+        append_audit_entry(&mut tx, audit_entry).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
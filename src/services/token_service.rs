@@ -0,0 +1,252 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::PgConnection;
+
+use crate::db::{
+    DbPool, append_audit_entry, create_refresh_token, find_refresh_token_for_update,
+    find_token_family_by_access_jti, mark_refresh_token_used, revoke_access_token, revoke_session,
+    revoke_token_family, update_user_login,
+};
+use crate::errors::{AppError, AppResult};
+use crate::jwt::JwtKeySet;
+use crate::models::{
+    AuditAction, Claims, CreateAuditEntry, CreateRefreshToken, TokenPair, UpdateUserLogin,
+};
+
+pub struct TokenService;
+
+impl TokenService {
+    /// `audit_entry` records how the user signed in and is written in the same transaction, so a
+    /// session never exists without it.
+    pub async fn start_session(
+        pool: &DbPool,
+        keys: &JwtKeySet,
+        user_id: i64,
+        login_data: UpdateUserLogin,
+        audit_entry: CreateAuditEntry,
+    ) -> AppResult<TokenPair> {
+        let mut tx = pool.begin().await?;
+
+        let session = update_user_login(&mut tx, user_id, login_data).await?;
+        let family_id = Self::random_token(16);
+        let tokens = Self::issue(&mut tx, keys, user_id, session.id, family_id, None).await?;
+        append_audit_entry(&mut tx, audit_entry).await?;
+
+        tx.commit().await?;
+
+        Ok(tokens)
+    }
+
+    pub async fn refresh(
+        pool: &DbPool,
+        keys: &JwtKeySet,
+        refresh_token: &str,
+    ) -> AppResult<TokenPair> {
+        let mut tx = pool.begin().await?;
+
+        let token = find_refresh_token_for_update(&mut tx, &Self::hash(refresh_token))
+            .await?
+            .ok_or_else(|| AppError::Authentication("Invalid refresh token".to_string()))?;
+
+        if token.is_reused() {
+            revoke_token_family(&mut tx, &token.family_id, Self::latest_access_expiry(keys))
+                .await?;
+            if let Some(session_id) = token.session_id {
+                revoke_session(&mut tx, session_id).await?;
+            }
+
+            let audit_entry =
+                CreateAuditEntry::new(AuditAction::RefreshTokenReused, Some(token.user_id))
+                    .with_entity("user", token.user_id)
+                    .with_details(json!({ "family_id": token.family_id }));
+            append_audit_entry(&mut tx, audit_entry).await?;
+
+            // Commit the revocation even though the request itself is rejected.
+            tx.commit().await?;
+
+            return Err(AppError::Authentication(
+                "Refresh token has already been used or revoked".to_string(),
+            ));
+        }
+
+        if token.is_expired() {
+            return Err(AppError::Authentication(
+                "Refresh token has expired".to_string(),
+            ));
+        }
+
+        let session_id = token.session_id.ok_or_else(|| {
+            AppError::Authentication("Session has expired, please log in again".to_string())
+        })?;
+
+        mark_refresh_token_used(&mut tx, token.id).await?;
+        let tokens = Self::issue(
+            &mut tx,
+            keys,
+            token.user_id,
+            session_id,
+            token.family_id,
+            Some(token.id),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(tokens)
+    }
+
+    pub async fn logout(
+        pool: &DbPool,
+        keys: &JwtKeySet,
+        user_id: i64,
+        jti: &str,
+        session_id: i64,
+    ) -> AppResult<()> {
+        let mut tx = pool.begin().await?;
+
+        revoke_access_token(&mut tx, jti, user_id, Self::latest_access_expiry(keys)).await?;
+
+        if let Some(family_id) = find_token_family_by_access_jti(&mut tx, jti).await? {
+            revoke_token_family(&mut tx, &family_id, Self::latest_access_expiry(keys)).await?;
+        }
+        revoke_session(&mut tx, session_id).await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::Logout, Some(user_id))
+            .with_entity("session", session_id);
+        append_audit_entry(&mut tx, audit_entry).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn issue(
+        conn: &mut PgConnection,
+        keys: &JwtKeySet,
+        user_id: i64,
+        session_id: i64,
+        family_id: String,
+        parent_id: Option<i64>,
+    ) -> AppResult<TokenPair> {
+        let now = Utc::now();
+        let jti = Self::random_token(16);
+        let access_token = Self::encode_access_token(keys, user_id, session_id, &jti, now)?;
+        let refresh_token = Self::random_token(32);
+
+        create_refresh_token(
+            conn,
+            CreateRefreshToken {
+                user_id,
+                session_id,
+                family_id,
+                parent_id,
+                token_hash: Self::hash(&refresh_token),
+                access_jti: jti,
+                expires_at: now + keys.refresh_token_ttl,
+            },
+        )
+        .await?;
+
+        Ok(TokenPair {
+            user_id,
+            token: access_token,
+            refresh_token,
+            expires_in: keys.access_token_ttl.num_seconds(),
+        })
+    }
+
+    fn encode_access_token(
+        keys: &JwtKeySet,
+        user_id: i64,
+        session_id: i64,
+        jti: &str,
+        issued_at: DateTime<Utc>,
+    ) -> AppResult<String> {
+        keys.encode(&Claims {
+            sub: user_id,
+            iss: keys.issuer.clone(),
+            aud: keys.audience.clone(),
+            exp: (issued_at + keys.access_token_ttl).timestamp() as usize,
+            iat: issued_at.timestamp() as usize,
+            jti: jti.to_string(),
+            sid: session_id,
+        })
+    }
+
+    // Any access token that could still be valid expires before this point.
+    fn latest_access_expiry(keys: &JwtKeySet) -> DateTime<Utc> {
+        Utc::now() + keys.access_token_ttl
+    }
+
+    pub(crate) fn hash(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+
+    pub(crate) fn random_token(len: usize) -> String {
+        let mut bytes = vec![0u8; len];
+        OsRng.fill_bytes(&mut bytes);
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AuthConfig;
+    use crate::db::{find_session_by_id, is_token_revoked, test_pool};
+    use crate::models::CreateUser;
+    use crate::services::AccountService;
+
+    #[tokio::test]
+    async fn replaying_a_used_refresh_token_revokes_the_whole_family() {
+        let pool = test_pool().await;
+        let keys = JwtKeySet::from_config(&AuthConfig {
+            jwt_secret: Some("token-service-test-secret".to_string()),
+            ..AuthConfig::default()
+        })
+        .unwrap();
+        let email = format!(
+            "refresh-{}@example.com",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        );
+        let user =
+            AccountService::register(&pool, CreateUser::new(email, "unused".to_string()).unwrap())
+                .await
+                .unwrap();
+        let login = UpdateUserLogin {
+            last_login_at: Utc::now(),
+            user_agent: None,
+            ip_address: None,
+        };
+        let audit_entry = CreateAuditEntry::new(AuditAction::LoginSucceeded, Some(user.id));
+
+        let first = TokenService::start_session(&pool, &keys, user.id, login, audit_entry)
+            .await
+            .unwrap();
+        let second = TokenService::refresh(&pool, &keys, &first.refresh_token)
+            .await
+            .unwrap();
+
+        let replay = TokenService::refresh(&pool, &keys, &first.refresh_token)
+            .await
+            .unwrap_err();
+        assert!(matches!(replay, AppError::Authentication(_)), "{}", replay);
+
+        // The rotated token was never used, but it belongs to the compromised family.
+        let error = TokenService::refresh(&pool, &keys, &second.refresh_token)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::Authentication(_)), "{}", error);
+
+        let claims = keys.decode(&second.token).unwrap();
+        assert!(is_token_revoked(&pool, &claims.jti).await.unwrap());
+        let session = find_session_by_id(&pool, claims.sid)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(session.revoked_at.is_some());
+    }
+}
@@ -0,0 +1,749 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use sqlx::PgConnection;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::db::{
+    DbPool, append_audit_entry, confirm_user_totp, consume_login_challenge,
+    count_unused_recovery_codes, create_login_challenge, delete_user_totp,
+    find_login_challenge_for_update, find_session_by_id, find_user_by_id, find_user_totp,
+    find_user_totp_for_update, get_user_password_hash, record_login_challenge_attempt,
+    record_session_step_up, replace_recovery_codes, update_totp_last_used_step,
+    upsert_pending_totp, use_recovery_code,
+};
+use crate::errors::{AppError, AppResult};
+use crate::jwt::JwtKeySet;
+use crate::models::{
+    AuditAction, CreateAuditEntry, LOGIN_CHALLENGE_TTL_SECONDS, MfaChallenge, RECOVERY_CODE_COUNT,
+    SecondFactor, Session, TOTP_DIGITS, TOTP_ISSUER, TOTP_STEP_SECONDS, TokenPair, TotpEnrollment,
+    TwoFactorStatus, UpdateUserLogin, UserTotp,
+};
+use crate::password::PasswordPolicy;
+use crate::services::{LoginThrottleService, PasskeyService, TokenService};
+
+const RECOVERY_CODE_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// What a session presents before it may add or remove a sign-in factor.
+pub struct Reauthentication<'a> {
+    pub session_id: i64,
+    pub current_password: Option<&'a str>,
+    pub policy: &'a PasswordPolicy,
+}
+
+pub struct TwoFactorService;
+
+impl TwoFactorService {
+    pub async fn status(pool: &DbPool, user_id: i64) -> AppResult<TwoFactorStatus> {
+        let enabled = Self::is_enabled(pool, user_id).await?;
+        let recovery_codes_remaining = if enabled {
+            count_unused_recovery_codes(pool, user_id).await?
+        } else {
+            0
+        };
+
+        Ok(TwoFactorStatus {
+            totp_enabled: enabled,
+            recovery_codes_remaining,
+        })
+    }
+
+    pub async fn is_enabled(pool: &DbPool, user_id: i64) -> AppResult<bool> {
+        Ok(find_user_totp(pool, user_id)
+            .await?
+            .is_some_and(|totp| totp.is_confirmed()))
+    }
+
+    pub async fn enroll_totp(
+        pool: &DbPool,
+        user_id: i64,
+        reauth: &Reauthentication<'_>,
+    ) -> AppResult<TotpEnrollment> {
+        Self::require_reauthentication(pool, user_id, reauth).await?;
+
+        let user = find_user_by_id(pool, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        let mut secret_bytes = vec![0u8; 20];
+        OsRng.fill_bytes(&mut secret_bytes);
+        let secret = Secret::Raw(secret_bytes).to_encoded().to_string();
+
+        if !upsert_pending_totp(pool, user_id, &secret).await? {
+            return Err(AppError::Conflict(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        let otpauth_uri = Self::totp(&secret, &user.email)?.get_url();
+
+        Ok(TotpEnrollment {
+            secret,
+            otpauth_uri,
+        })
+    }
+
+    /// Confirms a pending enrollment with a code from the authenticator and returns the
+    /// recovery codes. They are only stored hashed, so this is the one time they are shown.
+    pub async fn confirm_totp(pool: &DbPool, user_id: i64, code: &str) -> AppResult<Vec<String>> {
+        let mut tx = pool.begin().await?;
+
+        let totp = find_user_totp_for_update(&mut tx, user_id)
+            .await?
+            .ok_or_else(|| {
+                AppError::Validation("Start TOTP enrollment before confirming it".to_string())
+            })?;
+
+        if totp.is_confirmed() {
+            return Err(AppError::Conflict(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        let step = Self::matching_step(&totp, code)?
+            .ok_or_else(|| AppError::Authentication("Invalid two-factor code".to_string()))?;
+        update_totp_last_used_step(&mut tx, user_id, step).await?;
+        confirm_user_totp(&mut tx, user_id).await?;
+
+        let codes = Self::store_new_recovery_codes(&mut tx, user_id).await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::TwoFactorEnabled, Some(user_id))
+            .with_entity("user", user_id)
+            .with_details(json!({ "method": "totp" }));
+        append_audit_entry(&mut tx, audit_entry).await?;
+
+        tx.commit().await?;
+
+        Ok(codes)
+    }
+
+    pub async fn disable_totp(pool: &DbPool, user_id: i64, factor: &SecondFactor) -> AppResult<()> {
+        let mut tx = pool.begin().await?;
+
+        Self::require_second_factor(pool, &mut tx, user_id, factor).await?;
+        delete_user_totp(&mut tx, user_id).await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::TwoFactorDisabled, Some(user_id))
+            .with_entity("user", user_id);
+        append_audit_entry(&mut tx, audit_entry).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn regenerate_recovery_codes(
+        pool: &DbPool,
+        user_id: i64,
+        code: &str,
+    ) -> AppResult<Vec<String>> {
+        let mut tx = pool.begin().await?;
+
+        let factor = SecondFactor {
+            code: Some(code.to_string()),
+            recovery_code: None,
+        };
+        Self::require_second_factor(pool, &mut tx, user_id, &factor).await?;
+
+        let codes = Self::store_new_recovery_codes(&mut tx, user_id).await?;
+
+        let audit_entry =
+            CreateAuditEntry::new(AuditAction::RecoveryCodesRegenerated, Some(user_id))
+                .with_entity("user", user_id);
+        append_audit_entry(&mut tx, audit_entry).await?;
+
+        tx.commit().await?;
+
+        Ok(codes)
+    }
+
+    pub async fn begin_login_challenge(pool: &DbPool, user_id: i64) -> AppResult<MfaChallenge> {
+        let challenge_token = TokenService::random_token(32);
+        let expires_at = Utc::now() + Duration::seconds(LOGIN_CHALLENGE_TTL_SECONDS);
+
+        create_login_challenge(
+            pool,
+            user_id,
+            &TokenService::hash(&challenge_token),
+            expires_at,
+        )
+        .await?;
+
+        Ok(MfaChallenge {
+            mfa_required: true,
+            challenge_token,
+            methods: vec!["totp".to_string(), "recovery_code".to_string()],
+            expires_in: LOGIN_CHALLENGE_TTL_SECONDS,
+        })
+    }
+
+    /// Second step of a login for users with two-factor enabled. Failed attempts count
+    /// against the challenge, which stops working after a handful of wrong codes, and against
+    /// the account's login throttle, so fresh challenges do not buy more guesses.
+    pub async fn complete_login(
+        pool: &DbPool,
+        keys: &JwtKeySet,
+        challenge_token: &str,
+        factor: &SecondFactor,
+        login_data: UpdateUserLogin,
+    ) -> AppResult<TokenPair> {
+        factor.validate().map_err(AppError::Validation)?;
+
+        let mut tx = pool.begin().await?;
+
+        let challenge =
+            find_login_challenge_for_update(&mut tx, &TokenService::hash(challenge_token))
+                .await?
+                .filter(|c| c.is_usable())
+                .ok_or_else(|| {
+                    AppError::Authentication("Invalid or expired login challenge".to_string())
+                })?;
+
+        let verified = Self::verify_second_factor(
+            pool,
+            &mut tx,
+            challenge.user_id,
+            factor,
+            login_data.ip_address.clone(),
+        )
+        .await?;
+        if !verified {
+            record_login_challenge_attempt(&mut tx, challenge.id).await?;
+            tx.commit().await?;
+            return Err(AppError::Authentication(
+                "Invalid two-factor code".to_string(),
+            ));
+        }
+
+        consume_login_challenge(&mut tx, challenge.id).await?;
+
+        let method = if factor.code.is_some() {
+            "totp"
+        } else {
+            "recovery_code"
+        };
+        tx.commit().await?;
+
+        let user = find_user_by_id(pool, challenge.user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        LoginThrottleService::record_success(
+            pool,
+            &user.email,
+            user.id,
+            login_data.ip_address.clone(),
+        )
+        .await?;
+
+        let audit_entry =
+            CreateAuditEntry::new(AuditAction::LoginSucceeded, Some(challenge.user_id))
+                .with_entity("user", challenge.user_id)
+                .with_details(json!({ "second_factor": method }));
+        TokenService::start_session(pool, keys, challenge.user_id, login_data, audit_entry).await
+    }
+
+    /// Re-verifies the second factor inside an existing session so that multisigs requiring
+    /// step-up accept approvals and executions from it for a short while.
+    pub async fn step_up(
+        pool: &DbPool,
+        user_id: i64,
+        session_id: i64,
+        factor: &SecondFactor,
+    ) -> AppResult<()> {
+        let mut tx = pool.begin().await?;
+
+        Self::require_second_factor(pool, &mut tx, user_id, factor).await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::StepUpVerified, Some(user_id))
+            .with_entity("session", session_id)
+            .with_details(json!({ "method": "totp" }));
+        append_audit_entry(&mut tx, audit_entry).await?;
+
+        tx.commit().await?;
+
+        record_session_step_up(pool, session_id).await
+    }
+
+    pub async fn require_recent_step_up(
+        pool: &DbPool,
+        user_id: i64,
+        session_id: i64,
+    ) -> AppResult<()> {
+        let session = Self::live_session(pool, user_id, session_id).await?;
+
+        if session.has_recent_step_up() {
+            return Ok(());
+        }
+
+        if !Self::has_second_factor(pool, user_id).await? {
+            return Err(AppError::Authorization(
+                "This multisig requires step-up authentication; enable two-factor authentication or register a passkey first"
+                    .to_string(),
+            ));
+        }
+
+        Err(AppError::Authorization(
+            "Step-up authentication required; verify a fresh two-factor code or passkey"
+                .to_string(),
+        ))
+    }
+
+    /// A stolen access token must not be able to enroll a factor of its own. Once the account has
+    /// a second factor, changing factors needs a recent step-up with one of them; before that,
+    /// the current password is re-entered, and wrong guesses count toward the login throttle.
+    pub async fn require_reauthentication(
+        pool: &DbPool,
+        user_id: i64,
+        reauth: &Reauthentication<'_>,
+    ) -> AppResult<()> {
+        let session = Self::live_session(pool, user_id, reauth.session_id).await?;
+
+        if Self::has_second_factor(pool, user_id).await? {
+            if session.has_recent_step_up() {
+                return Ok(());
+            }
+
+            return Err(AppError::Authorization(
+                "Step-up authentication required; verify a fresh two-factor code or passkey"
+                    .to_string(),
+            ));
+        }
+
+        let password = reauth.current_password.ok_or_else(|| {
+            AppError::Authorization("Re-enter your current password to continue".to_string())
+        })?;
+
+        let user = find_user_by_id(pool, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        let (_, password_hash) = get_user_password_hash(pool, &user.email)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        let attempt_id =
+            LoginThrottleService::begin_attempt(pool, &user.email, Some(user_id), None).await?;
+        if !reauth.policy.verify(password, &password_hash)? {
+            LoginThrottleService::count_failure("wrong_password");
+            return Err(AppError::Authorization(
+                "Current password is incorrect".to_string(),
+            ));
+        }
+        LoginThrottleService::attempt_succeeded(pool, attempt_id).await
+    }
+
+    async fn has_second_factor(pool: &DbPool, user_id: i64) -> AppResult<bool> {
+        Ok(Self::is_enabled(pool, user_id).await?
+            || PasskeyService::has_passkeys(pool, user_id).await?)
+    }
+
+    async fn live_session(pool: &DbPool, user_id: i64, session_id: i64) -> AppResult<Session> {
+        find_session_by_id(pool, session_id)
+            .await?
+            .filter(|s| s.user_id == user_id && s.revoked_at.is_none())
+            .ok_or_else(|| AppError::Authentication("Session has been revoked".to_string()))
+    }
+
+    async fn require_second_factor(
+        pool: &DbPool,
+        conn: &mut PgConnection,
+        user_id: i64,
+        factor: &SecondFactor,
+    ) -> AppResult<()> {
+        factor.validate().map_err(AppError::Validation)?;
+
+        if !Self::verify_second_factor(pool, conn, user_id, factor, None).await? {
+            return Err(AppError::Authentication(
+                "Invalid two-factor code".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Every guess is counted on the account's login throttle before the code is checked, so
+    // wrong codes run into the same delay and lockout as wrong passwords.
+    async fn verify_second_factor(
+        pool: &DbPool,
+        conn: &mut PgConnection,
+        user_id: i64,
+        factor: &SecondFactor,
+        ip_address: Option<String>,
+    ) -> AppResult<bool> {
+        let totp = match find_user_totp_for_update(conn, user_id).await? {
+            Some(totp) if totp.is_confirmed() => totp,
+            _ => {
+                return Err(AppError::Validation(
+                    "Two-factor authentication is not enabled".to_string(),
+                ));
+            }
+        };
+
+        let user = find_user_by_id(pool, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        let attempt_id =
+            LoginThrottleService::begin_attempt(pool, &user.email, Some(user_id), ip_address)
+                .await?;
+
+        let verified = Self::check_second_factor(conn, &totp, factor).await?;
+        if verified {
+            LoginThrottleService::attempt_succeeded(pool, attempt_id).await?;
+        } else {
+            LoginThrottleService::count_failure("wrong_second_factor");
+        }
+
+        Ok(verified)
+    }
+
+    async fn check_second_factor(
+        conn: &mut PgConnection,
+        totp: &UserTotp,
+        factor: &SecondFactor,
+    ) -> AppResult<bool> {
+        let user_id = totp.user_id;
+
+        if let Some(code) = &factor.code {
+            return match Self::matching_step(totp, code)? {
+                Some(step) => {
+                    update_totp_last_used_step(conn, totp.user_id, step).await?;
+                    Ok(true)
+                }
+                None => Ok(false),
+            };
+        }
+
+        let Some(recovery_code) = &factor.recovery_code else {
+            return Ok(false);
+        };
+
+        let code_hash = TokenService::hash(&Self::normalize_recovery_code(recovery_code));
+        if !use_recovery_code(conn, user_id, &code_hash).await? {
+            return Ok(false);
+        }
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::RecoveryCodeUsed, Some(user_id))
+            .with_entity("user", user_id);
+        append_audit_entry(conn, audit_entry).await?;
+
+        Ok(true)
+    }
+
+    // Accepts the previous, current and next time step, but never one at or before the
+    // last accepted step, so each code works only once.
+    fn matching_step(totp: &UserTotp, code: &str) -> AppResult<Option<i64>> {
+        let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+        if code.len() != TOTP_DIGITS {
+            return Ok(None);
+        }
+
+        let generator = Self::totp(&totp.secret, "")?;
+        let current_step = (Utc::now().timestamp() as u64 / TOTP_STEP_SECONDS) as i64;
+
+        for step in (current_step - 1)..=(current_step + 1) {
+            if totp.last_used_step.is_some_and(|last| step <= last) {
+                continue;
+            }
+
+            let expected = generator.generate(step as u64 * TOTP_STEP_SECONDS);
+            if constant_time_eq(expected.as_bytes(), code.as_bytes()) {
+                return Ok(Some(step));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn totp(secret: &str, account_name: &str) -> AppResult<TOTP> {
+        let secret = Secret::Encoded(secret.to_string())
+            .to_bytes()
+            .map_err(|e| AppError::Internal(format!("Invalid TOTP secret: {:?}", e)))?;
+
+        TOTP::new(
+            Algorithm::SHA1,
+            TOTP_DIGITS,
+            1,
+            TOTP_STEP_SECONDS,
+            secret,
+            Some(TOTP_ISSUER.to_string()),
+            account_name.to_string(),
+        )
+        .map_err(|e| AppError::Internal(format!("Invalid TOTP parameters: {}", e)))
+    }
+
+    async fn store_new_recovery_codes(
+        conn: &mut PgConnection,
+        user_id: i64,
+    ) -> AppResult<Vec<String>> {
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| Self::random_recovery_code())
+            .collect();
+        let hashes: Vec<String> = codes
+            .iter()
+            .map(|code| TokenService::hash(&Self::normalize_recovery_code(code)))
+            .collect();
+
+        replace_recovery_codes(conn, user_id, &hashes).await?;
+
+        Ok(codes)
+    }
+
+    fn random_recovery_code() -> String {
+        let mut bytes = [0u8; 10];
+        OsRng.fill_bytes(&mut bytes);
+
+        let chars: String = bytes
+            .iter()
+            .map(|b| RECOVERY_CODE_ALPHABET[(*b % 32) as usize] as char)
+            .collect();
+        format!("{}-{}", &chars[..5], &chars[5..])
+    }
+
+    fn normalize_recovery_code(code: &str) -> String {
+        code.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect()
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration as StdDuration;
+
+    use super::*;
+    use crate::config::{AuthConfig, PasswordConfig};
+    use crate::db::{create_session, record_login_attempt, test_pool};
+    use crate::models::{CreateLoginAttempt, CreateUser, LoginThrottleReason};
+    use crate::services::AccountService;
+
+    const PASSWORD: &str = "two-factor-test-password-1";
+
+    struct Enrolled {
+        user_id: i64,
+        email: String,
+        secret: String,
+    }
+
+    impl Enrolled {
+        // Codes are generated for an explicit step so a test can pick one that was not used yet.
+        fn code(&self, steps_ahead: i64) -> String {
+            let step = Utc::now().timestamp() / TOTP_STEP_SECONDS as i64 + steps_ahead;
+            TwoFactorService::totp(&self.secret, "")
+                .unwrap()
+                .generate(step as u64 * TOTP_STEP_SECONDS)
+        }
+
+        fn wrong_code(&self) -> String {
+            let code: u32 = self.code(0).parse().unwrap();
+            format!("{:06}", (code + 500_000) % 1_000_000)
+        }
+    }
+
+    async fn enrolled_user(pool: &DbPool, prefix: &str) -> Enrolled {
+        let policy = PasswordPolicy::from_config(&PasswordConfig::default()).unwrap();
+        let email = format!(
+            "{}-{}@example.com",
+            prefix,
+            Utc::now().timestamp_nanos_opt().unwrap()
+        );
+        let user = AccountService::register(
+            pool,
+            CreateUser::new(email.clone(), policy.hash(PASSWORD).unwrap()).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let session_id = new_session(pool, user.id).await;
+        let reauth = Reauthentication {
+            session_id,
+            current_password: Some(PASSWORD),
+            policy: &policy,
+        };
+        let secret = TwoFactorService::enroll_totp(pool, user.id, &reauth)
+            .await
+            .unwrap()
+            .secret;
+        let enrolled = Enrolled {
+            user_id: user.id,
+            email,
+            secret,
+        };
+        TwoFactorService::confirm_totp(pool, user.id, &enrolled.code(0))
+            .await
+            .unwrap();
+
+        enrolled
+    }
+
+    async fn new_session(pool: &DbPool, user_id: i64) -> i64 {
+        let mut conn = pool.acquire().await.unwrap();
+        create_session(&mut conn, user_id, &login_data())
+            .await
+            .unwrap()
+            .id
+    }
+
+    fn login_data() -> UpdateUserLogin {
+        UpdateUserLogin {
+            last_login_at: Utc::now(),
+            user_agent: None,
+            ip_address: None,
+        }
+    }
+
+    fn code_factor(code: String) -> SecondFactor {
+        SecondFactor {
+            code: Some(code),
+            recovery_code: None,
+        }
+    }
+
+    fn throttle_reason(error: AppError) -> LoginThrottleReason {
+        match error {
+            AppError::TooManyRequests(throttle) => throttle.reason,
+            other => panic!("expected a throttled attempt, got {}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn enrolling_totp_without_another_factor_needs_the_password() {
+        let pool = test_pool().await;
+        let policy = PasswordPolicy::from_config(&PasswordConfig::default()).unwrap();
+        let user = enrolled_user(&pool, "totp-reauth").await;
+        let session_id = new_session(&pool, user.user_id).await;
+
+        // Disabling leaves the account without a second factor, so enrolling asks for the password.
+        TwoFactorService::disable_totp(&pool, user.user_id, &code_factor(user.code(1)))
+            .await
+            .unwrap();
+        let bare = Reauthentication {
+            session_id,
+            current_password: None,
+            policy: &policy,
+        };
+        let error = TwoFactorService::enroll_totp(&pool, user.user_id, &bare)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::Authorization(_)), "{}", error);
+
+        let reauth = Reauthentication {
+            current_password: Some(PASSWORD),
+            ..bare
+        };
+        TwoFactorService::enroll_totp(&pool, user.user_id, &reauth)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn step_up_lapses_after_its_ttl() {
+        let pool = test_pool().await;
+        let user = enrolled_user(&pool, "step-up-ttl").await;
+        let session_id = new_session(&pool, user.user_id).await;
+
+        let error = TwoFactorService::require_recent_step_up(&pool, user.user_id, session_id)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::Authorization(_)), "{}", error);
+
+        TwoFactorService::step_up(&pool, user.user_id, session_id, &code_factor(user.code(1)))
+            .await
+            .unwrap();
+        TwoFactorService::require_recent_step_up(&pool, user.user_id, session_id)
+            .await
+            .unwrap();
+
+        sqlx::query(
+            "UPDATE sessions SET step_up_at = NOW() - make_interval(secs => $2) WHERE id = $1",
+        )
+        .bind(session_id)
+        .bind((crate::models::STEP_UP_TTL_SECONDS + 1) as f64)
+        .execute(&pool)
+        .await
+        .unwrap();
+        let error = TwoFactorService::require_recent_step_up(&pool, user.user_id, session_id)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::Authorization(_)), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn wrong_codes_are_throttled_across_step_up_and_login_challenges() {
+        let pool = test_pool().await;
+        let keys = JwtKeySet::from_config(&AuthConfig {
+            jwt_secret: Some("two-factor-test-secret".to_string()),
+            ..AuthConfig::default()
+        })
+        .unwrap();
+        let user = enrolled_user(&pool, "step-up-guess").await;
+        let session_id = new_session(&pool, user.user_id).await;
+
+        for _ in 0..3 {
+            let error = TwoFactorService::step_up(
+                &pool,
+                user.user_id,
+                session_id,
+                &code_factor(user.wrong_code()),
+            )
+            .await
+            .unwrap_err();
+            assert!(matches!(error, AppError::Authentication(_)), "{}", error);
+        }
+
+        // The right code is refused too while the account is delayed.
+        let error =
+            TwoFactorService::step_up(&pool, user.user_id, session_id, &code_factor(user.code(1)))
+                .await
+                .unwrap_err();
+        assert_eq!(throttle_reason(error), LoginThrottleReason::AccountDelay);
+
+        tokio::time::sleep(StdDuration::from_millis(1100)).await;
+        let challenge = TwoFactorService::begin_login_challenge(&pool, user.user_id)
+            .await
+            .unwrap();
+        let error = TwoFactorService::complete_login(
+            &pool,
+            &keys,
+            &challenge.challenge_token,
+            &code_factor(user.wrong_code()),
+            login_data(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, AppError::Authentication(_)), "{}", error);
+
+        let mut conn = pool.acquire().await.unwrap();
+        for _ in 0..6 {
+            record_login_attempt(
+                &mut conn,
+                CreateLoginAttempt {
+                    email: user.email.clone(),
+                    user_id: Some(user.user_id),
+                    ip_address: None,
+                    succeeded: false,
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        // A fresh challenge does not reset the count.
+        let challenge = TwoFactorService::begin_login_challenge(&pool, user.user_id)
+            .await
+            .unwrap();
+        let error = TwoFactorService::complete_login(
+            &pool,
+            &keys,
+            &challenge.challenge_token,
+            &code_factor(user.code(1)),
+            login_data(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(throttle_reason(error), LoginThrottleReason::AccountLocked);
+    }
+}
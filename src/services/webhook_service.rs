@@ -0,0 +1,440 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::Utc;
+use futures_util::{StreamExt, TryStreamExt, stream};
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::PgConnection;
+use tracing::error;
+
+use crate::config::WebhookConfig;
+use crate::db::{
+    DbPool, claim_due_webhook_deliveries, create_webhook, delete_webhook,
+    enqueue_webhook_deliveries, find_webhook_by_id, list_multisig_webhooks,
+    list_webhook_deliveries, list_webhook_delivery_attempts, record_webhook_attempt,
+};
+use crate::errors::{AppError, AppResult};
+use crate::models::{
+    AuditAction, ClaimedWebhookDelivery, CreateAuditEntry, CreateWebhook, DeliveryOutcome,
+    ProposalEvent, WEBHOOK_MAX_ATTEMPTS, Webhook, WebhookDelivery, WebhookDeliveryAttempt,
+    WebhookDeliveryStatus, WebhookEventType, webhook_retry_delay,
+};
+use crate::outbound::{PublicOnlyResolver, check_public_url};
+use crate::services::{AuditService, MultisigService, WorkerMonitor};
+use crate::supervisor::Shutdown;
+
+// Deliveries in a batch run DELIVERY_CONCURRENCY at a time. Each one spends at most
+// DELIVERY_TIMEOUT resolving the target and again sending, so even a batch of unresponsive
+// receivers finishes in (50 / 10) * 20s, well inside the lease.
+const DELIVERY_BATCH_SIZE: i64 = 50;
+const DELIVERY_CONCURRENCY: usize = 10;
+const DELIVERY_LEASE_SECONDS: i64 = 300;
+const DELIVERY_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+const DELIVERY_WORKER_STALE_AFTER: Duration = Duration::from_secs(300);
+pub const DELIVERY_WORKER: &str = "webhook_delivery";
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+pub const WEBHOOK_ID_HEADER: &str = "X-Webhook-Id";
+
+pub struct WebhookService;
+
+impl WebhookService {
+    pub async fn create_webhook(
+        pool: &DbPool,
+        webhook_data: CreateWebhook,
+        multisig_id: i64,
+        user_id: i64,
+        config: &WebhookConfig,
+    ) -> AppResult<Webhook> {
+        if let Err(msg) = webhook_data.validate() {
+            return Err(AppError::Validation(msg));
+        }
+
+        MultisigService::check_user_is_owner(pool, multisig_id, user_id).await?;
+
+        if !config.allow_private_targets {
+            check_public_url(&webhook_data.url)
+                .await
+                .map_err(|e| AppError::Validation(format!("Webhook URL is not allowed: {}", e)))?;
+        }
+
+        let secret = Self::generate_secret();
+        let webhook = create_webhook(pool, webhook_data, multisig_id, &secret, user_id).await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::WebhookCreated, Some(user_id))
+            .with_entity("webhook", webhook.id)
+            .with_details(json!({
+                "multisig_id": multisig_id,
+                "url": webhook.url,
+                "event_types": webhook.event_types,
+            }));
+        AuditService::log(pool, audit_entry).await?;
+
+        Ok(webhook)
+    }
+
+    pub async fn list_webhooks(
+        pool: &DbPool,
+        multisig_id: i64,
+        user_id: i64,
+    ) -> AppResult<Vec<Webhook>> {
+        MultisigService::check_user_is_owner(pool, multisig_id, user_id).await?;
+
+        list_multisig_webhooks(pool, multisig_id).await
+    }
+
+    pub async fn get_webhook(
+        pool: &DbPool,
+        multisig_id: i64,
+        webhook_id: i64,
+        user_id: i64,
+    ) -> AppResult<Webhook> {
+        MultisigService::check_user_is_owner(pool, multisig_id, user_id).await?;
+
+        find_webhook_by_id(pool, webhook_id)
+            .await?
+            .filter(|w| w.multisig_id == multisig_id)
+            .ok_or_else(|| AppError::NotFound("Webhook not found".to_string()))
+    }
+
+    pub async fn delete_webhook(
+        pool: &DbPool,
+        multisig_id: i64,
+        webhook_id: i64,
+        user_id: i64,
+    ) -> AppResult<()> {
+        let webhook = Self::get_webhook(pool, multisig_id, webhook_id, user_id).await?;
+
+        delete_webhook(pool, webhook.id).await?;
+
+        let audit_entry = CreateAuditEntry::new(AuditAction::WebhookDeleted, Some(user_id))
+            .with_entity("webhook", webhook.id)
+            .with_details(json!({ "multisig_id": multisig_id, "url": webhook.url }));
+        AuditService::log(pool, audit_entry).await?;
+
+        Ok(())
+    }
+
+    pub async fn list_deliveries(
+        pool: &DbPool,
+        multisig_id: i64,
+        webhook_id: i64,
+        user_id: i64,
+        limit: i64,
+    ) -> AppResult<Vec<WebhookDelivery>> {
+        if limit <= 0 || limit > 100 {
+            return Err(AppError::Validation(
+                "Limit must be between 1 and 100".to_string(),
+            ));
+        }
+
+        let webhook = Self::get_webhook(pool, multisig_id, webhook_id, user_id).await?;
+
+        list_webhook_deliveries(pool, webhook.id, limit).await
+    }
+
+    pub async fn list_delivery_attempts(
+        pool: &DbPool,
+        multisig_id: i64,
+        webhook_id: i64,
+        delivery_id: i64,
+        user_id: i64,
+    ) -> AppResult<Vec<WebhookDeliveryAttempt>> {
+        let webhook = Self::get_webhook(pool, multisig_id, webhook_id, user_id).await?;
+
+        list_webhook_delivery_attempts(pool, webhook.id, delivery_id).await
+    }
+
+    pub async fn enqueue_for_event(
+        conn: &mut PgConnection,
+        multisig_id: i64,
+        event: &ProposalEvent,
+    ) -> AppResult<()> {
+        for event_type in WebhookEventType::from_proposal_event(event) {
+            let payload = event_type.payload(multisig_id, event);
+            enqueue_webhook_deliveries(conn, multisig_id, event_type.as_str(), &payload).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn run_delivery_worker(
+        pool: DbPool,
+        config: WebhookConfig,
+        monitor: WorkerMonitor,
+        shutdown: Shutdown,
+    ) {
+        monitor.register(DELIVERY_WORKER, Some(DELIVERY_WORKER_STALE_AFTER));
+
+        let client = match Self::http_client(&config) {
+            Ok(client) => client,
+            Err(e) => {
+                error!("Webhook worker could not build HTTP client: {}", e);
+                monitor.fail(DELIVERY_WORKER, e);
+                return;
+            }
+        };
+
+        while !shutdown.is_requested() {
+            let result = Self::deliver_due(&pool, &client, &config).await;
+            match &result {
+                Ok(_) => monitor.beat(DELIVERY_WORKER),
+                Err(e) => monitor.fail(DELIVERY_WORKER, e),
+            }
+
+            let idle = match result {
+                Ok(0) => true,
+                Ok(_) => false,
+                Err(e) => {
+                    error!("Webhook delivery batch failed: {}", e);
+                    true
+                }
+            };
+            if idle && !shutdown.sleep(DELIVERY_POLL_INTERVAL).await {
+                break;
+            }
+        }
+    }
+
+    // Redirects are not followed, and unless private targets are allowed the client refuses to
+    // connect to anything but public addresses.
+    fn http_client(config: &WebhookConfig) -> reqwest::Result<reqwest::Client> {
+        let builder = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none());
+
+        if config.allow_private_targets {
+            builder.build()
+        } else {
+            builder.dns_resolver(Arc::new(PublicOnlyResolver)).build()
+        }
+    }
+
+    async fn deliver_due(
+        pool: &DbPool,
+        client: &reqwest::Client,
+        config: &WebhookConfig,
+    ) -> AppResult<usize> {
+        let deliveries =
+            claim_due_webhook_deliveries(pool, DELIVERY_BATCH_SIZE, DELIVERY_LEASE_SECONDS).await?;
+        let count = deliveries.len();
+
+        stream::iter(deliveries)
+            .map(Ok)
+            .try_for_each_concurrent(DELIVERY_CONCURRENCY, |delivery| {
+                Self::deliver(pool, client, config, delivery)
+            })
+            .await?;
+
+        Ok(count)
+    }
+
+    async fn deliver(
+        pool: &DbPool,
+        client: &reqwest::Client,
+        config: &WebhookConfig,
+        delivery: ClaimedWebhookDelivery,
+    ) -> AppResult<()> {
+        let outcome = Self::send(client, config, &delivery).await;
+        let attempts = delivery.attempts + 1;
+
+        let (status, next_attempt_at) = if outcome.is_success() {
+            (WebhookDeliveryStatus::Delivered, Utc::now())
+        } else if attempts >= WEBHOOK_MAX_ATTEMPTS {
+            (WebhookDeliveryStatus::Failed, Utc::now())
+        } else {
+            (
+                WebhookDeliveryStatus::Pending,
+                Utc::now() + webhook_retry_delay(delivery.attempts),
+            )
+        };
+
+        let mut tx = pool.begin().await?;
+        record_webhook_attempt(&mut tx, delivery.id, &outcome, status, next_attempt_at).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn send(
+        client: &reqwest::Client,
+        config: &WebhookConfig,
+        delivery: &ClaimedWebhookDelivery,
+    ) -> DeliveryOutcome {
+        let started = Instant::now();
+
+        // The URL was checked when the webhook was created, but DNS may have changed since.
+        if !config.allow_private_targets {
+            let checked = tokio::time::timeout(DELIVERY_TIMEOUT, check_public_url(&delivery.url))
+                .await
+                .unwrap_or_else(|_| Err("Timed out resolving the webhook URL".to_string()));
+            if let Err(e) = checked {
+                return DeliveryOutcome {
+                    status_code: None,
+                    error: Some(e),
+                    duration_ms: started.elapsed().as_millis() as i64,
+                };
+            }
+        }
+
+        let body = delivery.payload.to_string();
+        let timestamp = Utc::now().timestamp().to_string();
+        let signature = Self::sign(&delivery.secret, &timestamp, &body);
+
+        let result = client
+            .post(&delivery.url)
+            .header("Content-Type", "application/json")
+            .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+            .header(TIMESTAMP_HEADER, &timestamp)
+            .header(EVENT_HEADER, &delivery.event_type)
+            .header(DELIVERY_HEADER, delivery.id.to_string())
+            .header(WEBHOOK_ID_HEADER, delivery.webhook_id.to_string())
+            .body(body)
+            .send()
+            .await;
+
+        let duration_ms = started.elapsed().as_millis() as i64;
+
+        match result {
+            Ok(response) => {
+                let status_code = response.status().as_u16() as i32;
+                let error = (!response.status().is_success())
+                    .then(|| format!("Receiver responded with HTTP {}", status_code));
+                DeliveryOutcome {
+                    status_code: Some(status_code),
+                    error,
+                    duration_ms,
+                }
+            }
+            Err(e) => DeliveryOutcome {
+                status_code: None,
+                error: Some(e.to_string()),
+                duration_ms,
+            },
+        }
+    }
+
+    // Receivers verify `HMAC-SHA256(secret, "{timestamp}.{body}")` against the signature header.
+    pub fn sign(secret: &str, timestamp: &str, body: &str) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key size");
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(body.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn generate_secret() -> String {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        format!("whsec_{}", hex::encode(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+
+    use actix_web::http::header::HeaderMap;
+    use actix_web::{App, HttpRequest, HttpResponse, HttpServer, web};
+
+    use super::*;
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, String)>>>;
+
+    // Starts a receiver on 127.0.0.1 that answers every POST with 204 and keeps what it got.
+    fn start_receiver() -> (String, Received) {
+        let received: Received = Arc::default();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let store = received.clone();
+        let server = HttpServer::new(move || {
+            let store = store.clone();
+            App::new().route(
+                "/hook",
+                web::post().to(move |req: HttpRequest, body: String| {
+                    let store = store.clone();
+                    async move {
+                        store.lock().unwrap().push((req.headers().clone(), body));
+                        HttpResponse::NoContent().finish()
+                    }
+                }),
+            )
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_web::rt::spawn(server);
+
+        (url, received)
+    }
+
+    fn delivery(url: String) -> ClaimedWebhookDelivery {
+        ClaimedWebhookDelivery {
+            id: 7,
+            webhook_id: 3,
+            event_type: "proposal.approved".to_string(),
+            payload: json!({ "proposal_id": 42 }),
+            attempts: 0,
+            url,
+            secret: "whsec_test".to_string(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn delivers_signed_payload_to_allowed_local_receiver() {
+        let (url, received) = start_receiver();
+        let config = WebhookConfig {
+            allow_private_targets: true,
+        };
+        let client = WebhookService::http_client(&config).unwrap();
+
+        let outcome = WebhookService::send(&client, &config, &delivery(url)).await;
+
+        assert!(outcome.is_success(), "{:?}", outcome.error);
+        assert_eq!(outcome.status_code, Some(204));
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (headers, body) = &received[0];
+        let header = |name: &str| headers.get(name).unwrap().to_str().unwrap().to_string();
+        assert_eq!(body, r#"{"proposal_id":42}"#);
+        assert_eq!(header(EVENT_HEADER), "proposal.approved");
+        assert_eq!(header(DELIVERY_HEADER), "7");
+        assert_eq!(
+            header(SIGNATURE_HEADER),
+            format!(
+                "sha256={}",
+                WebhookService::sign("whsec_test", &header(TIMESTAMP_HEADER), body)
+            )
+        );
+    }
+
+    #[actix_web::test]
+    async fn refuses_local_receiver_by_default() {
+        let (url, received) = start_receiver();
+        let config = WebhookConfig::default();
+        let client = WebhookService::http_client(&config).unwrap();
+
+        let outcome = WebhookService::send(&client, &config, &delivery(url.clone())).await;
+        assert!(!outcome.is_success());
+        assert_eq!(outcome.status_code, None);
+
+        // A name resolving to loopback is stopped by the client's resolver as well.
+        let by_name = url.replace("127.0.0.1", "localhost");
+        let result = client.post(&by_name).body("{}").send().await;
+        assert!(result.is_err());
+
+        assert!(received.lock().unwrap().is_empty());
+    }
+}
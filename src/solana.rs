@@ -0,0 +1,102 @@
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::config::SolanaConfig;
+use crate::errors::{AppError, AppResult};
+use crate::metrics::METRICS;
+use crate::telemetry;
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    result: Option<serde_json::Value>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    message: String,
+}
+
+// JSON-RPC client for the configured Solana endpoints. Requests go to the first endpoint and fall
+// through to the next one on failure.
+pub struct SolanaRpc {
+    urls: Vec<String>,
+    http: reqwest::Client,
+}
+
+impl SolanaRpc {
+    pub fn from_config(config: &SolanaConfig) -> AppResult<Option<Self>> {
+        if config.rpc_urls.is_empty() {
+            return Ok(None);
+        }
+
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.rpc_timeout_seconds))
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to build RPC HTTP client: {}", e)))?;
+
+        Ok(Some(Self {
+            urls: config.rpc_urls.clone(),
+            http,
+        }))
+    }
+
+    /// Calls `getHealth` and returns the endpoint that reported healthy.
+    pub async fn health(&self) -> AppResult<&str> {
+        let mut last_error = String::new();
+
+        for url in &self.urls {
+            match self.call(url, "getHealth").await {
+                Ok(_) => return Ok(url),
+                Err(e) => last_error = format!("{}: {}", url, e),
+            }
+        }
+
+        Err(AppError::Internal(format!(
+            "No Solana RPC endpoint is healthy ({})",
+            last_error
+        )))
+    }
+
+    // The URL is left off the span since hosted RPC endpoints often carry an API key in it.
+    #[tracing::instrument(level = "debug", skip(self, url), fields(otel.kind = "client"))]
+    async fn call(&self, url: &str, method: &str) -> Result<serde_json::Value, String> {
+        let started = Instant::now();
+        let result = self.send(url, method).await;
+
+        METRICS
+            .rpc_request_duration
+            .with_label_values(&[method])
+            .observe(started.elapsed().as_secs_f64());
+        if result.is_err() {
+            METRICS.rpc_errors.with_label_values(&[method]).inc();
+        }
+
+        result
+    }
+
+    async fn send(&self, url: &str, method: &str) -> Result<serde_json::Value, String> {
+        let mut request = self.http.post(url);
+        for (name, value) in telemetry::trace_headers() {
+            request = request.header(name, value);
+        }
+
+        let response: RpcResponse = request
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": method}))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(error.message),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err("empty response".to_string()),
+        }
+    }
+}
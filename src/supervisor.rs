@@ -0,0 +1,148 @@
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+
+use futures_util::FutureExt;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+use crate::metrics::METRICS;
+use crate::services::WorkerMonitor;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// A worker that stayed up this long has recovered, so its next failure starts the backoff over.
+const STABLE_AFTER: Duration = Duration::from_secs(300);
+
+/// Handed to every worker; resolves once the process is shutting down.
+#[derive(Clone)]
+pub struct Shutdown {
+    receiver: watch::Receiver<bool>,
+}
+
+impl Shutdown {
+    pub fn is_requested(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    pub async fn requested(&self) {
+        let mut receiver = self.receiver.clone();
+        // The sender only goes away with the supervisor, which also means stop.
+        let _ = receiver.wait_for(|requested| *requested).await;
+    }
+
+    /// Sleeps for `duration`. Returns false if shutdown was requested first.
+    pub async fn sleep(&self, duration: Duration) -> bool {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => true,
+            _ = self.requested() => false,
+        }
+    }
+}
+
+// Runs background workers as tasks that are restarted when they panic or return early. Each worker
+// gets a `Shutdown` and is expected to return promptly once it fires; one that does not is aborted
+// when the shutdown timeout runs out.
+pub struct WorkerSupervisor {
+    monitor: WorkerMonitor,
+    trigger: watch::Sender<bool>,
+    tasks: Vec<(&'static str, JoinHandle<()>)>,
+}
+
+impl WorkerSupervisor {
+    pub fn new(monitor: WorkerMonitor) -> Self {
+        Self {
+            monitor,
+            trigger: watch::Sender::new(false),
+            tasks: Vec::new(),
+        }
+    }
+
+    pub fn spawn<F, Fut>(&mut self, name: &'static str, worker: F)
+    where
+        F: Fn(Shutdown) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let shutdown = Shutdown {
+            receiver: self.trigger.subscribe(),
+        };
+        let task = tokio::spawn(supervise(name, worker, self.monitor.clone(), shutdown));
+        self.tasks.push((name, task));
+    }
+
+    /// Signals every worker to stop and waits up to `timeout` for all of them.
+    pub async fn shutdown(self, timeout: Duration) {
+        self.trigger.send_replace(true);
+        let deadline = Instant::now() + timeout;
+
+        for (name, mut task) in self.tasks {
+            match tokio::time::timeout_at(deadline, &mut task).await {
+                Ok(_) => info!("Worker {} stopped", name),
+                Err(_) => {
+                    warn!(
+                        "Worker {} did not stop within {}s; aborting it",
+                        name,
+                        timeout.as_secs()
+                    );
+                    task.abort();
+                }
+            }
+        }
+    }
+}
+
+async fn supervise<F, Fut>(
+    name: &'static str,
+    worker: F,
+    monitor: WorkerMonitor,
+    shutdown: Shutdown,
+) where
+    F: Fn(Shutdown) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut backoff = INITIAL_BACKOFF;
+
+    loop {
+        let started = Instant::now();
+        let result = AssertUnwindSafe(worker(shutdown.clone()))
+            .catch_unwind()
+            .await;
+
+        if shutdown.is_requested() {
+            return;
+        }
+
+        let reason = match result {
+            Ok(()) => "exited unexpectedly".to_string(),
+            Err(panic) => format!("panicked: {}", panic_message(panic.as_ref())),
+        };
+        if started.elapsed() >= STABLE_AFTER {
+            backoff = INITIAL_BACKOFF;
+        }
+
+        error!(
+            "Worker {} {}; restarting in {}s",
+            name,
+            reason,
+            backoff.as_secs()
+        );
+        monitor.restarting(name, reason);
+        METRICS.worker_restarts.with_label_values(&[name]).inc();
+
+        if !shutdown.sleep(backoff).await {
+            return;
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+use std::io::IsTerminal;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use opentelemetry::global;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, SdkTracerProvider};
+use tracing::level_filters::LevelFilter;
+use tracing::{Instrument, Span, field, warn};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::{LoggingConfig, TracingConfig};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Flushes exported spans when dropped; keep it alive for the life of the process.
+pub struct TelemetryGuard(Option<SdkTracerProvider>);
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.0.take()
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Failed to flush trace spans: {}", e);
+        }
+    }
+}
+
+/// Installs the global subscriber. `RUST_LOG`, when set, takes precedence over `logging.level`.
+/// Spans are only exported when `tracing.otlp_endpoint` is set.
+pub fn init(logging: &LoggingConfig, tracing: &TracingConfig) -> TelemetryGuard {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&logging.level))
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+
+    // Closing a span logs its duration, which gives one timed line per request and per query.
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .with_span_events(FmtSpan::CLOSE)
+        .with_ansi(std::io::stdout().is_terminal());
+    let fmt_layer = if logging.format == "json" {
+        fmt_layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_filter(filter)
+            .boxed()
+    } else {
+        fmt_layer.with_filter(filter).boxed()
+    };
+
+    let (provider, export_error) = match tracing
+        .otlp_endpoint
+        .as_deref()
+        .map(|endpoint| tracer_provider(endpoint, tracing))
+    {
+        Some(Ok(provider)) => (Some(provider), None),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+
+    // Exported traces get this crate's debug spans (service calls, queries, RPC) regardless of the
+    // log level, so a trace shows where the time went without making the logs noisy.
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+            .with_filter(
+                Targets::new()
+                    .with_target(env!("CARGO_CRATE_NAME"), LevelFilter::DEBUG)
+                    .with_default(LevelFilter::WARN),
+            )
+    });
+
+    tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(otel_layer)
+        .init();
+
+    if let Some(e) = export_error {
+        warn!("Trace export disabled: {}", e);
+    }
+
+    TelemetryGuard(provider)
+}
+
+fn tracer_provider(
+    endpoint: &str,
+    config: &TracingConfig,
+) -> Result<SdkTracerProvider, opentelemetry_otlp::ExporterBuildError> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()?;
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            config.sample_ratio,
+        ))))
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build())
+}
+
+/// The id of the request being handled on this task, if any.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(String::clone).ok()
+}
+
+// Wraps every request in an `http_request` span. The id comes from the caller's X-Request-Id when
+// it looks sane, so a proxy's id carries through; otherwise one is generated. Either way it is
+// echoed back in the response header.
+pub async fn request_span(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| is_valid_request_id(value))
+        .map(str::to_string)
+        .unwrap_or_else(generate_request_id);
+
+    let route = req.match_pattern();
+    let route_name = route.as_deref().unwrap_or("unmatched");
+    let span = tracing::info_span!(
+        "http_request",
+        otel.name = %format!("{} {}", req.method(), route_name),
+        otel.kind = "server",
+        request_id = %request_id,
+        method = %req.method(),
+        route = route_name,
+        user_id = field::Empty,
+        multisig_id = field::Empty,
+        proposal_id = field::Empty,
+        status = field::Empty,
+    );
+    if let Some(pattern) = &route {
+        record_path_ids(&span, pattern, req.path());
+    }
+
+    // Continue the caller's trace when it sent a `traceparent`. Without one, or with export
+    // disabled, the extracted context is empty and the span starts its own trace.
+    let parent = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(req.headers()))
+    });
+    let _ = span.set_parent(parent);
+
+    let response = REQUEST_ID
+        .scope(request_id.clone(), next.call(req).instrument(span.clone()))
+        .await;
+
+    match response {
+        Ok(mut response) => {
+            span.record("status", response.status().as_u16());
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                response
+                    .headers_mut()
+                    .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+            Ok(response)
+        }
+        Err(e) => {
+            span.record("status", e.as_response_error().status_code().as_u16());
+            Err(e)
+        }
+    }
+}
+
+/// W3C trace context headers for the current span, to be sent on outbound requests.
+pub fn trace_headers() -> HashMap<String, String> {
+    let mut headers = HashMap::new();
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut headers));
+    headers
+}
+
+/// Records the authenticated user on the current request span.
+pub fn record_user(user_id: i64) {
+    Span::current().record("user_id", user_id);
+}
+
+// Path parameters are only resolved once routing runs inside the app, so the ids are read by
+// lining the request path up with the matched pattern. `{id}` means whichever resource the first
+// segment names.
+fn record_path_ids(span: &Span, pattern: &str, path: &str) {
+    let resource = pattern.trim_start_matches('/').split('/').next();
+
+    for (pattern_segment, path_segment) in pattern.split('/').zip(path.split('/')) {
+        let Some(name) = pattern_segment
+            .strip_prefix('{')
+            .and_then(|s| s.strip_suffix('}'))
+        else {
+            continue;
+        };
+        let Ok(id) = path_segment.parse::<i64>() else {
+            continue;
+        };
+
+        match (name, resource) {
+            ("multisig_id", _) | ("id", Some("multisigs")) => {
+                span.record("multisig_id", id);
+            }
+            ("proposal_id", _) | ("id", Some("proposals")) => {
+                span.record("proposal_id", id);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_valid_request_id(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LENGTH
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+fn generate_request_id() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    use actix_web::middleware::from_fn;
+    use actix_web::web::Bytes;
+    use actix_web::{App, HttpRequest, HttpResponse, HttpServer, test, web};
+
+    use super::*;
+
+    type Exports = Arc<Mutex<Vec<(String, Bytes)>>>;
+
+    // Stands in for an OTLP/HTTP collector: accepts POSTs to /v1/traces and keeps the payloads.
+    fn start_collector() -> (String, Exports) {
+        let exports: Exports = Arc::default();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+        let store = exports.clone();
+        let server = HttpServer::new(move || {
+            let store = store.clone();
+            App::new().route(
+                "/v1/traces",
+                web::post().to(move |req: HttpRequest, body: Bytes| {
+                    let store = store.clone();
+                    async move {
+                        let content_type = req
+                            .headers()
+                            .get("content-type")
+                            .and_then(|value| value.to_str().ok())
+                            .unwrap_or_default()
+                            .to_string();
+                        store.lock().unwrap().push((content_type, body));
+                        HttpResponse::Ok().finish()
+                    }
+                }),
+            )
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_web::rt::spawn(server);
+
+        (endpoint, exports)
+    }
+
+    fn downstream_request(traceparent: Option<&str>) -> test::TestRequest {
+        let req = test::TestRequest::get().uri("/downstream");
+        match traceparent {
+            Some(traceparent) => req.insert_header(("traceparent", traceparent)),
+            None => req,
+        }
+    }
+
+    // The route echoes the headers it would send on an outbound call.
+    async fn traceparent_sent<B: MessageBody>(response: ServiceResponse<B>) -> String {
+        let headers: HashMap<String, String> = test::read_body_json(response).await;
+        headers["traceparent"].clone()
+    }
+
+    fn trace_id(traceparent: &str) -> Vec<u8> {
+        hex::decode(traceparent.split('-').nth(1).unwrap()).unwrap()
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+
+    #[actix_web::test]
+    async fn propagates_traceparent_and_exports_only_sampled_traces() {
+        let (endpoint, exports) = start_collector();
+        let config = TracingConfig {
+            otlp_endpoint: Some(endpoint.clone()),
+            service_name: "telemetry-test".to_string(),
+            // New traces are never kept, so anything exported was sampled by the caller.
+            sample_ratio: 0.0,
+        };
+        let provider = tracer_provider(&endpoint, &config).unwrap();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_opentelemetry::layer().with_tracer(provider.tracer(env!("CARGO_PKG_NAME"))),
+        );
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = test::init_service(App::new().wrap(from_fn(request_span)).route(
+            "/downstream",
+            web::get().to(|| async { HttpResponse::Ok().json(trace_headers()) }),
+        ))
+        .await;
+
+        let sampled = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let response =
+            test::call_service(&app, downstream_request(Some(sampled)).to_request()).await;
+        let continued = traceparent_sent(response).await;
+        assert_eq!(trace_id(&continued), trace_id(sampled));
+        assert!(continued.ends_with("-01"), "{}", continued);
+        assert!(
+            !continued.contains("00f067aa0ba902b7"),
+            "a child span is sent downstream"
+        );
+
+        let unsampled = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00";
+        let response =
+            test::call_service(&app, downstream_request(Some(unsampled)).to_request()).await;
+        let not_sampled = traceparent_sent(response).await;
+        assert_eq!(trace_id(&not_sampled), trace_id(unsampled));
+        assert!(not_sampled.ends_with("-00"), "{}", not_sampled);
+
+        let response = test::call_service(&app, downstream_request(None).to_request()).await;
+        let fresh = traceparent_sent(response).await;
+        assert!(fresh.ends_with("-00"), "{}", fresh);
+
+        // The batch exporter posts synchronously, so flush off the runtime serving the collector.
+        actix_web::rt::task::spawn_blocking(move || provider.shutdown())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let exports = exports.lock().unwrap();
+        assert!(!exports.is_empty(), "nothing was exported");
+        assert!(
+            exports
+                .iter()
+                .all(|(content_type, _)| content_type == "application/x-protobuf")
+        );
+        let payload: Vec<u8> = exports.iter().flat_map(|(_, body)| body.to_vec()).collect();
+        assert!(contains(&payload, b"telemetry-test"));
+        assert!(contains(&payload, &trace_id(sampled)));
+        assert!(!contains(&payload, &trace_id(unsampled)));
+        assert!(!contains(&payload, &trace_id(&fresh)));
+    }
+}
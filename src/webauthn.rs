@@ -0,0 +1,228 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use ciborium::Value;
+use p256::ecdsa::signature::Verifier;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::config::WebauthnConfig;
+use crate::errors::{AppError, AppResult};
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+pub const COSE_ALG_ES256: i32 = -7;
+pub const COSE_ALG_EDDSA: i32 = -8;
+
+/// Credential parsed from a registration response.
+pub struct AttestedCredential {
+    pub credential_id: Vec<u8>,
+    pub public_key: Vec<u8>,
+    pub algorithm: i32,
+    pub sign_count: u32,
+}
+
+#[derive(Deserialize)]
+pub struct ClientData {
+    #[serde(rename = "type")]
+    pub ceremony_type: String,
+    pub challenge: String,
+    pub origin: String,
+}
+
+struct AuthenticatorData<'a> {
+    rp_id_hash: &'a [u8],
+    flags: u8,
+    sign_count: u32,
+    attested: &'a [u8],
+}
+
+// Minimal WebAuthn relying party: ES256 and EdDSA credentials, user verification required.
+// Attestation statements are not checked since registration requests `attestation: "none"`.
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+    pub origin: String,
+}
+
+impl RelyingParty {
+    pub fn from_config(config: &WebauthnConfig) -> Self {
+        Self {
+            id: config.rp_id.clone(),
+            name: config.rp_name.clone(),
+            origin: config.rp_origin.clone(),
+        }
+    }
+
+    /// Parses `clientDataJSON` and checks the ceremony type and origin. The caller still has
+    /// to match the returned challenge against one it issued.
+    pub fn verify_client_data(&self, raw: &[u8], ceremony_type: &str) -> AppResult<ClientData> {
+        let client_data: ClientData =
+            serde_json::from_slice(raw).map_err(|_| invalid("malformed client data"))?;
+
+        if client_data.ceremony_type != ceremony_type {
+            return Err(invalid("unexpected ceremony type"));
+        }
+        if client_data.origin != self.origin {
+            return Err(invalid("origin mismatch"));
+        }
+
+        Ok(client_data)
+    }
+
+    pub fn verify_attestation(&self, attestation_object: &[u8]) -> AppResult<AttestedCredential> {
+        let object: Value = ciborium::from_reader(attestation_object)
+            .map_err(|_| invalid("malformed attestation object"))?;
+        let auth_data = map_get(&object, &Value::Text("authData".to_string()))
+            .and_then(Value::as_bytes)
+            .ok_or_else(|| invalid("missing authenticator data"))?;
+
+        let data = self.verify_authenticator_data(auth_data)?;
+        if data.flags & FLAG_ATTESTED_CREDENTIAL_DATA == 0 || data.attested.len() < 18 {
+            return Err(invalid("missing attested credential data"));
+        }
+
+        // aaguid (16 bytes), credential id length (2 bytes), credential id, COSE key
+        let id_len = u16::from_be_bytes([data.attested[16], data.attested[17]]) as usize;
+        let rest = &data.attested[18..];
+        if rest.len() < id_len {
+            return Err(invalid("truncated credential id"));
+        }
+        let (credential_id, mut key_bytes) = rest.split_at(id_len);
+
+        let before = key_bytes.len();
+        let key: Value =
+            ciborium::from_reader(&mut key_bytes).map_err(|_| invalid("malformed public key"))?;
+        let public_key = rest[id_len..id_len + before - key_bytes.len()].to_vec();
+
+        let algorithm = cose_int(&key, 3).ok_or_else(|| invalid("missing key algorithm"))? as i32;
+        // Parse once now so unsupported keys are rejected at registration.
+        CredentialKey::from_cose(&key)?;
+
+        Ok(AttestedCredential {
+            credential_id: credential_id.to_vec(),
+            public_key,
+            algorithm,
+            sign_count: data.sign_count,
+        })
+    }
+
+    /// Verifies an assertion signature and returns the authenticator's new sign count.
+    pub fn verify_assertion(
+        &self,
+        public_key: &[u8],
+        authenticator_data: &[u8],
+        client_data_json: &[u8],
+        signature: &[u8],
+    ) -> AppResult<u32> {
+        let data = self.verify_authenticator_data(authenticator_data)?;
+
+        let key: Value =
+            ciborium::from_reader(public_key).map_err(|_| invalid("stored key is malformed"))?;
+
+        let mut signed = authenticator_data.to_vec();
+        signed.extend_from_slice(&Sha256::digest(client_data_json));
+        CredentialKey::from_cose(&key)?.verify(&signed, signature)?;
+
+        Ok(data.sign_count)
+    }
+
+    fn verify_authenticator_data<'a>(&self, raw: &'a [u8]) -> AppResult<AuthenticatorData<'a>> {
+        if raw.len() < 37 {
+            return Err(invalid("authenticator data too short"));
+        }
+
+        let data = AuthenticatorData {
+            rp_id_hash: &raw[..32],
+            flags: raw[32],
+            sign_count: u32::from_be_bytes([raw[33], raw[34], raw[35], raw[36]]),
+            attested: &raw[37..],
+        };
+
+        if data.rp_id_hash != Sha256::digest(self.id.as_bytes()).as_slice() {
+            return Err(invalid("relying party id mismatch"));
+        }
+        if data.flags & FLAG_USER_PRESENT == 0 || data.flags & FLAG_USER_VERIFIED == 0 {
+            return Err(invalid("user presence and verification are required"));
+        }
+
+        Ok(data)
+    }
+}
+
+enum CredentialKey {
+    Es256(p256::ecdsa::VerifyingKey),
+    EdDsa(ed25519_dalek::VerifyingKey),
+}
+
+impl CredentialKey {
+    fn from_cose(key: &Value) -> AppResult<Self> {
+        let coordinate = |label: i64| {
+            map_get(key, &Value::Integer(label.into()))
+                .and_then(Value::as_bytes)
+                .filter(|bytes| bytes.len() == 32)
+                .ok_or_else(|| invalid("malformed public key"))
+        };
+
+        match (cose_int(key, 1), cose_int(key, 3), cose_int(key, -1)) {
+            // kty EC2, crv P-256
+            (Some(2), Some(alg), Some(1)) if alg == COSE_ALG_ES256 as i64 => {
+                let mut sec1 = vec![0x04];
+                sec1.extend_from_slice(coordinate(-2)?);
+                sec1.extend_from_slice(coordinate(-3)?);
+                p256::ecdsa::VerifyingKey::from_sec1_bytes(&sec1)
+                    .map(CredentialKey::Es256)
+                    .map_err(|_| invalid("invalid P-256 public key"))
+            }
+            // kty OKP, crv Ed25519
+            (Some(1), Some(alg), Some(6)) if alg == COSE_ALG_EDDSA as i64 => {
+                let bytes: [u8; 32] = coordinate(-2)?
+                    .as_slice()
+                    .try_into()
+                    .expect("length checked");
+                ed25519_dalek::VerifyingKey::from_bytes(&bytes)
+                    .map(CredentialKey::EdDsa)
+                    .map_err(|_| invalid("invalid Ed25519 public key"))
+            }
+            _ => Err(invalid("unsupported public key algorithm")),
+        }
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> AppResult<()> {
+        let verified = match self {
+            CredentialKey::Es256(key) => p256::ecdsa::Signature::from_der(signature)
+                .is_ok_and(|sig| key.verify(message, &sig).is_ok()),
+            CredentialKey::EdDsa(key) => ed25519_dalek::Signature::from_slice(signature)
+                .is_ok_and(|sig| key.verify_strict(message, &sig).is_ok()),
+        };
+
+        if !verified {
+            return Err(invalid("signature verification failed"));
+        }
+
+        Ok(())
+    }
+}
+
+pub fn decode_base64url(value: &str) -> AppResult<Vec<u8>> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| AppError::Validation("Invalid base64url value".to_string()))
+}
+
+fn map_get<'a>(value: &'a Value, key: &Value) -> Option<&'a Value> {
+    value
+        .as_map()?
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v)
+}
+
+fn cose_int(key: &Value, label: i64) -> Option<i64> {
+    let value = map_get(key, &Value::Integer(label.into()))?.as_integer()?;
+    i64::try_from(value).ok()
+}
+
+fn invalid(reason: &str) -> AppError {
+    AppError::Authentication(format!("Invalid passkey response: {}", reason))
+}